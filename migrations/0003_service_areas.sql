@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS service_areas (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    center_latitude REAL,
+    center_longitude REAL,
+    radius_km REAL,
+    polygon TEXT,
+    active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL
+);
+
+ALTER TABLE users ADD COLUMN base_latitude REAL;
+ALTER TABLE users ADD COLUMN base_longitude REAL;
+ALTER TABLE users ADD COLUMN travel_radius_km REAL;
+
+ALTER TABLE appointments ADD COLUMN outside_area INTEGER NOT NULL DEFAULT 0;
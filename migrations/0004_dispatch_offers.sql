@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS dispatch_offers (
+    id TEXT PRIMARY KEY,
+    appointment_id TEXT NOT NULL,
+    barber_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    score REAL NOT NULL,
+    offered_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    responded_at TEXT,
+    FOREIGN KEY (appointment_id) REFERENCES appointments(id) ON DELETE CASCADE,
+    FOREIGN KEY (barber_id) REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_dispatch_offers_appointment ON dispatch_offers(appointment_id);
+CREATE INDEX IF NOT EXISTS idx_dispatch_offers_barber ON dispatch_offers(barber_id, status);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS geocode_cache (
+    query TEXT PRIMARY KEY,
+    latitude REAL,
+    longitude REAL,
+    display_name TEXT,
+    created_at TEXT NOT NULL
+);
+
+ALTER TABLE appointments ADD COLUMN geocode_attempted_at TEXT;
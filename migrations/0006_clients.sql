@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS clients (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    phone TEXT,
+    phone_normalized TEXT,
+    email TEXT,
+    email_normalized TEXT,
+    address TEXT,
+    notes TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_clients_phone ON clients(phone_normalized) WHERE phone_normalized IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_clients_email ON clients(email_normalized) WHERE email_normalized IS NOT NULL;
+
+ALTER TABLE appointments ADD COLUMN client_id TEXT REFERENCES clients(id);
+
+CREATE INDEX IF NOT EXISTS idx_appointments_client ON appointments(client_id);
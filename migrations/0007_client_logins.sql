@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS client_login_tokens (
+    token TEXT PRIMARY KEY,
+    client_id TEXT NOT NULL REFERENCES clients(id),
+    channel TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    used_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS client_sessions (
+    id TEXT PRIMARY KEY,
+    client_id TEXT NOT NULL REFERENCES clients(id),
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_login_tokens_client ON client_login_tokens(client_id);
+CREATE INDEX IF NOT EXISTS idx_client_sessions_client ON client_sessions(client_id);
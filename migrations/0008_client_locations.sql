@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS client_locations (
+    id TEXT PRIMARY KEY,
+    client_id TEXT NOT NULL REFERENCES clients(id),
+    label TEXT NOT NULL,
+    address TEXT NOT NULL,
+    latitude REAL,
+    longitude REAL,
+    access_notes TEXT,
+    gate_code TEXT,
+    active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_locations_client ON client_locations(client_id);
+
+ALTER TABLE appointments ADD COLUMN location_id TEXT REFERENCES client_locations(id);
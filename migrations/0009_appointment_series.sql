@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS appointment_series (
+    id TEXT PRIMARY KEY,
+    client_id TEXT REFERENCES clients(id),
+    frequency TEXT NOT NULL,
+    until_date TEXT,
+    occurrence_count INTEGER,
+    created_at TEXT NOT NULL
+);
+
+ALTER TABLE appointments ADD COLUMN series_id TEXT REFERENCES appointment_series(id);
+ALTER TABLE appointments ADD COLUMN series_index INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_appointments_series ON appointments(series_id, series_index);
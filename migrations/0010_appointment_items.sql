@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS appointment_items (
+    id TEXT PRIMARY KEY,
+    appointment_id TEXT NOT NULL REFERENCES appointments(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    person_name TEXT NOT NULL,
+    service TEXT NOT NULL,
+    duration_minutes INTEGER,
+    barber_id TEXT REFERENCES users(id),
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_appointment_items_appointment ON appointment_items(appointment_id, position);
+CREATE INDEX IF NOT EXISTS idx_appointment_items_barber ON appointment_items(barber_id);
+
+ALTER TABLE appointments ADD COLUMN duration_minutes INTEGER;
+
+INSERT INTO appointment_items (id, appointment_id, position, person_name, service, duration_minutes, barber_id, created_at)
+SELECT lower(hex(randomblob(16))), id, 0, client_name, service, NULL, NULL, requested_at
+FROM appointments;
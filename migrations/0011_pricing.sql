@@ -0,0 +1,18 @@
+ALTER TABLE appointment_items ADD COLUMN price_cents INTEGER;
+
+CREATE TABLE IF NOT EXISTS appointment_addons (
+    id TEXT PRIMARY KEY,
+    appointment_id TEXT NOT NULL REFERENCES appointments(id) ON DELETE CASCADE,
+    addon_key TEXT NOT NULL,
+    name TEXT NOT NULL,
+    price_cents INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_appointment_addons_appointment ON appointment_addons(appointment_id);
+
+ALTER TABLE appointments ADD COLUMN services_cents INTEGER;
+ALTER TABLE appointments ADD COLUMN addons_cents INTEGER;
+ALTER TABLE appointments ADD COLUMN travel_km REAL;
+ALTER TABLE appointments ADD COLUMN travel_cents INTEGER;
+ALTER TABLE appointments ADD COLUMN quote_cents INTEGER;
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS payments (
+    id TEXT PRIMARY KEY,
+    appointment_id TEXT NOT NULL REFERENCES appointments(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    status TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    reference TEXT,
+    charge_reference TEXT,
+    payment_method TEXT,
+    customer_reference TEXT,
+    error TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_payments_appointment ON payments(appointment_id, created_at);
@@ -0,0 +1,34 @@
+CREATE TABLE IF NOT EXISTS invoice_sequence (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    next_number INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO invoice_sequence (id, next_number) VALUES (1, 1);
+
+CREATE TABLE IF NOT EXISTS invoices (
+    id TEXT PRIMARY KEY,
+    number INTEGER NOT NULL UNIQUE,
+    appointment_id TEXT NOT NULL UNIQUE REFERENCES appointments(id) ON DELETE CASCADE,
+    client_name TEXT NOT NULL,
+    client_email TEXT,
+    address TEXT NOT NULL,
+    barber_name TEXT,
+    subtotal_cents INTEGER NOT NULL,
+    tax_rate REAL NOT NULL,
+    tax_cents INTEGER NOT NULL,
+    tip_cents INTEGER NOT NULL DEFAULT 0,
+    total_cents INTEGER NOT NULL,
+    paid_cents INTEGER NOT NULL DEFAULT 0,
+    currency TEXT NOT NULL,
+    issued_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS invoice_lines (
+    id TEXT PRIMARY KEY,
+    invoice_id TEXT NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoice_lines_invoice ON invoice_lines(invoice_id, position);
@@ -0,0 +1,2 @@
+ALTER TABLE appointments ADD COLUMN tip_cents INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN commission_percent REAL;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS client_strikes (
+    id TEXT PRIMARY KEY,
+    client_id TEXT REFERENCES clients(id),
+    appointment_id TEXT NOT NULL UNIQUE REFERENCES appointments(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    fee_cents INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_client_strikes_client ON client_strikes(client_id, created_at);
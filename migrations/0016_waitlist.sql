@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS waitlist_entries (
+    id TEXT PRIMARY KEY,
+    client_id TEXT REFERENCES clients(id),
+    client_name TEXT NOT NULL,
+    client_phone TEXT NOT NULL,
+    client_email TEXT,
+    service TEXT NOT NULL,
+    wanted_date TEXT NOT NULL,
+    barber_id TEXT REFERENCES users(id),
+    push_subscription TEXT,
+    status TEXT NOT NULL,
+    appointment_id TEXT REFERENCES appointments(id),
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_entries_date ON waitlist_entries(wanted_date, status, created_at);
+
+CREATE TABLE IF NOT EXISTS waitlist_offers (
+    id TEXT PRIMARY KEY,
+    entry_id TEXT NOT NULL REFERENCES waitlist_entries(id) ON DELETE CASCADE,
+    appointment_id TEXT NOT NULL REFERENCES appointments(id) ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    status TEXT NOT NULL,
+    offered_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    responded_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_offers_appointment ON waitlist_offers(appointment_id, status);
+CREATE INDEX IF NOT EXISTS idx_waitlist_offers_entry ON waitlist_offers(entry_id);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS reviews (
+    id TEXT PRIMARY KEY,
+    appointment_id TEXT NOT NULL UNIQUE REFERENCES appointments(id) ON DELETE CASCADE,
+    barber_id TEXT NOT NULL REFERENCES users(id),
+    client_name TEXT NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    rating INTEGER CHECK (rating BETWEEN 1 AND 5),
+    comment TEXT,
+    status TEXT NOT NULL,
+    requested_at TEXT NOT NULL,
+    submitted_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_reviews_barber ON reviews(barber_id, status);
+CREATE INDEX IF NOT EXISTS idx_reviews_status ON reviews(status, submitted_at);
@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS barber_profiles (
+    barber_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    bio TEXT,
+    specialties TEXT,
+    languages TEXT,
+    services TEXT,
+    service_area TEXT,
+    photo_path TEXT,
+    approved_at TEXT NOT NULL
+);
+
+-- Edits a barber submitted that an admin has not approved yet; one per barber.
+CREATE TABLE IF NOT EXISTS barber_profile_drafts (
+    barber_id TEXT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    bio TEXT,
+    specialties TEXT,
+    languages TEXT,
+    services TEXT,
+    service_area TEXT,
+    photo_path TEXT,
+    status TEXT NOT NULL,
+    review_note TEXT,
+    submitted_at TEXT NOT NULL
+);
@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS media (
+    id TEXT PRIMARY KEY,
+    original_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    url TEXT NOT NULL UNIQUE,
+    alt_text TEXT,
+    uploaded_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL
+);
+
+-- Downscaled copies for `srcset`; only widths smaller than the original are generated.
+CREATE TABLE IF NOT EXISTS media_variants (
+    media_id TEXT NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+    width INTEGER NOT NULL,
+    url TEXT NOT NULL UNIQUE,
+    PRIMARY KEY (media_id, width)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_created ON media(created_at);
@@ -0,0 +1,26 @@
+-- Every saved version of a CMS block, drafts included, so edits can be compared and rolled back.
+CREATE TABLE IF NOT EXISTS cms_revisions (
+    id TEXT PRIMARY KEY,
+    block_key TEXT NOT NULL REFERENCES cms_blocks(key) ON DELETE CASCADE,
+    html TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    created_by TEXT REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_cms_revisions_block ON cms_revisions(block_key, created_at);
+
+-- The unpublished working copy of a block; one per block. `publish_at` schedules it to go live.
+CREATE TABLE IF NOT EXISTS cms_drafts (
+    block_key TEXT PRIMARY KEY REFERENCES cms_blocks(key) ON DELETE CASCADE,
+    html TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    updated_by TEXT REFERENCES users(id) ON DELETE SET NULL,
+    publish_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_cms_drafts_publish_at ON cms_drafts(publish_at);
+
+-- The live copy of existing blocks becomes their first revision.
+INSERT INTO cms_revisions (id, block_key, html, kind, created_at, created_by)
+SELECT lower(hex(randomblob(16))), key, html, 'published', updated_at, updated_by FROM cms_blocks;
@@ -0,0 +1,17 @@
+-- Admin-created pages served at /p/{slug}; their content lives in cms_blocks rows that point back here.
+CREATE TABLE IF NOT EXISTS cms_pages (
+    id TEXT PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    title TEXT NOT NULL,
+    meta_description TEXT,
+    published INTEGER NOT NULL DEFAULT 0,
+    show_in_nav INTEGER NOT NULL DEFAULT 0,
+    nav_order INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+ALTER TABLE cms_blocks ADD COLUMN page_id TEXT REFERENCES cms_pages(id) ON DELETE CASCADE;
+ALTER TABLE cms_blocks ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_cms_blocks_page ON cms_blocks(page_id, position);
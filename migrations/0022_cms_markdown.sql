@@ -0,0 +1,10 @@
+-- Blocks can be written in Markdown. `html` always holds the rendered, sanitized copy the site shows;
+-- `source` keeps the Markdown so it can be edited again.
+ALTER TABLE cms_blocks ADD COLUMN format TEXT NOT NULL DEFAULT 'html';
+ALTER TABLE cms_blocks ADD COLUMN source TEXT;
+
+ALTER TABLE cms_drafts ADD COLUMN format TEXT NOT NULL DEFAULT 'html';
+ALTER TABLE cms_drafts ADD COLUMN source TEXT;
+
+ALTER TABLE cms_revisions ADD COLUMN format TEXT NOT NULL DEFAULT 'html';
+ALTER TABLE cms_revisions ADD COLUMN source TEXT;
@@ -0,0 +1,9 @@
+-- The language a client used on the site, so messages about their visit or waitlist spot use it too.
+ALTER TABLE appointments ADD COLUMN locale TEXT NOT NULL DEFAULT 'en';
+ALTER TABLE waitlist_entries ADD COLUMN locale TEXT NOT NULL DEFAULT 'en';
+
+-- Translated copies of a block are blocks of their own, keyed "<key>:<locale>", so drafts, revisions and
+-- scheduling work for them unchanged. Visitors fall back to the original when a translation is missing.
+ALTER TABLE cms_blocks ADD COLUMN locale TEXT;
+ALTER TABLE cms_blocks ADD COLUMN variant_of TEXT REFERENCES cms_blocks(key) ON DELETE CASCADE;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_cms_blocks_variant ON cms_blocks(variant_of, locale);
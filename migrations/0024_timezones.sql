@@ -0,0 +1,6 @@
+-- IANA zone a barber works in, e.g. "Europe/Zagreb"; NULL uses BUSINESS_TIMEZONE.
+ALTER TABLE users ADD COLUMN timezone TEXT;
+
+-- `appointments.scheduled_for` now holds UTC instants ("2026-03-29T08:00:00Z"). Older values
+-- without an offset are converted from the business or barber zone on startup.
+CREATE INDEX IF NOT EXISTS idx_appointments_scheduled ON appointments(scheduled_for);
@@ -0,0 +1,7 @@
+-- A contact only signs a client in once a link sent to it has been opened. Contacts saved before
+-- this start unverified, because bookings used to be able to attach them to any client.
+ALTER TABLE clients ADD COLUMN phone_verified_at TEXT;
+ALTER TABLE clients ADD COLUMN email_verified_at TEXT;
+
+-- Set on links that confirm a contact: the normalized phone or email the link was sent to.
+ALTER TABLE client_login_tokens ADD COLUMN contact TEXT;
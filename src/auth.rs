@@ -19,6 +19,7 @@ use crate::{models::{ROLE_ADMIN, ROLE_BARBER}, models::UserRow, state::AppState}
 
 pub const AUTH_REALM: &str = "Barber2Go";
 const LOGOUT_COOKIE: &str = "b2g_logged_out";
+pub const CLIENT_SESSION_COOKIE: &str = "b2g_client";
 
 #[derive(Clone, Debug)]
 pub struct AuthUser {
@@ -49,7 +50,7 @@ async fn authenticate(req: &ServiceRequest, credentials: &BasicAuth) -> Result<A
         .ok_or_else(|| ErrorUnauthorized("Unauthorized"))?;
     let username = credentials.user_id();
     let password = credentials.password().unwrap_or_default();
-    authenticate_credentials(&state, username, password)
+    authenticate_credentials(state, username, password)
         .await
         .ok_or_else(|| ErrorUnauthorized("Unauthorized"))
 }
@@ -161,6 +162,30 @@ pub fn clear_logout_cookie(req: &HttpRequest) -> Cookie<'static> {
     builder.finish()
 }
 
+pub fn client_session_cookie(req: &HttpRequest, session_id: &str, days: i64) -> Cookie<'static> {
+    let mut builder = Cookie::build(CLIENT_SESSION_COOKIE, session_id.to_string())
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::days(days));
+    if req.connection_info().scheme() == "https" {
+        builder = builder.secure(true);
+    }
+    builder.finish()
+}
+
+pub fn clear_client_session_cookie(req: &HttpRequest) -> Cookie<'static> {
+    let mut builder = Cookie::build(CLIENT_SESSION_COOKIE, "")
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::seconds(0));
+    if req.connection_info().scheme() == "https" {
+        builder = builder.secure(true);
+    }
+    builder.finish()
+}
+
 pub fn is_logged_out(req: &HttpRequest) -> bool {
     req.cookie(LOGOUT_COOKIE).is_some()
 }
@@ -0,0 +1,491 @@
+use actix_web::HttpRequest;
+use chrono::{Duration, SecondsFormat, Utc};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{new_id, CLIENT_SESSION_COOKIE},
+    messaging::Channel,
+    models::{ClientLocationRow, ClientRow},
+    validation,
+};
+
+/// Digits only, keeping a leading `+` (or `00`, rewritten to `+`) so the same number
+/// typed with spaces, dashes or brackets maps to one client.
+pub fn normalize_phone(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    let digits: String = trimmed.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() < 6 {
+        return None;
+    }
+    if trimmed.starts_with('+') {
+        Some(format!("+{digits}"))
+    } else if let Some(rest) = digits.strip_prefix("00") {
+        Some(format!("+{rest}"))
+    } else {
+        Some(digits)
+    }
+}
+
+/// The key a phone is stored and looked up under: E.164 when it can be worked out, so "091 123 4567"
+/// and "+385 91 123 4567" are one client once `PHONE_COUNTRY_CODE` is 385.
+pub fn phone_key(raw: &str, country_code: Option<&str>) -> Option<String> {
+    validation::to_e164(raw, country_code).or_else(|| normalize_phone(raw))
+}
+
+pub fn normalize_email(raw: &str) -> Option<String> {
+    let email = raw.trim().to_lowercase();
+    if email.is_empty() || !email.contains('@') {
+        return None;
+    }
+    Some(email)
+}
+
+async fn find_existing(
+    pool: &SqlitePool,
+    phone: Option<&str>,
+    email: Option<&str>,
+) -> Result<Option<String>, sqlx::Error> {
+    if let Some(phone) = phone {
+        let found = sqlx::query_scalar::<_, String>("SELECT id FROM clients WHERE phone_normalized = ?")
+            .bind(phone)
+            .fetch_optional(pool)
+            .await?;
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    if let Some(email) = email {
+        return sqlx::query_scalar::<_, String>("SELECT id FROM clients WHERE email_normalized = ?")
+            .bind(email)
+            .fetch_optional(pool)
+            .await;
+    }
+    Ok(None)
+}
+
+/// The client a booking would be filed under, without creating one.
+pub async fn find_known(
+    pool: &SqlitePool,
+    phone: &str,
+    email: Option<&str>,
+    country_code: Option<&str>,
+) -> Option<String> {
+    let phone_normalized = phone_key(phone, country_code);
+    let email_normalized = email.and_then(normalize_email);
+    find_existing(pool, phone_normalized.as_deref(), email_normalized.as_deref())
+        .await
+        .unwrap_or(None)
+}
+
+/// Returns the client matching the phone or email (the phone wins when they point at different
+/// clients), creating one when neither is known. A matched client is never changed, so a booking
+/// can't attach its contacts to someone else's account.
+pub async fn find_or_create(
+    pool: &SqlitePool,
+    name: &str,
+    phone: &str,
+    email: Option<&str>,
+    address: &str,
+    country_code: Option<&str>,
+) -> Result<String, sqlx::Error> {
+    let phone_normalized = phone_key(phone, country_code);
+    let email_normalized = email.and_then(normalize_email);
+
+    if let Some(client_id) = find_existing(pool, phone_normalized.as_deref(), email_normalized.as_deref()).await? {
+        return Ok(client_id);
+    }
+
+    let client_id = new_id();
+    let now = Utc::now().to_rfc3339();
+    sqlx::query(
+        r#"INSERT INTO clients
+           (id, name, phone, phone_normalized, email, email_normalized, address, notes, created_at, updated_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, NULL, ?, ?)"#,
+    )
+    .bind(&client_id)
+    .bind(name.trim())
+    .bind(Some(phone.trim()).filter(|value| !value.is_empty()))
+    .bind(phone_normalized)
+    .bind(email.map(str::trim).filter(|value| !value.is_empty()))
+    .bind(email_normalized)
+    .bind(address.trim())
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await?;
+
+    Ok(client_id)
+}
+
+/// Links appointments booked before client accounts existed.
+pub async fn link_existing_appointments(pool: &SqlitePool, country_code: Option<&str>) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String, String, Option<String>, String)>(
+        r#"SELECT id, client_name, client_phone, client_email, address
+           FROM appointments
+           WHERE client_id IS NULL
+           ORDER BY requested_at"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (appointment_id, name, phone, email, address) in rows {
+        let client_id = find_or_create(pool, &name, &phone, email.as_deref(), &address, country_code).await?;
+        sqlx::query("UPDATE appointments SET client_id = ? WHERE id = ?")
+            .bind(client_id)
+            .bind(appointment_id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Rewrites phone keys saved before they were E.164. A key that would collide with another
+/// client's is left as it is, since the unique index allows only one holder.
+pub async fn store_phones_as_e164(pool: &SqlitePool, country_code: Option<&str>) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "SELECT id, phone_normalized FROM clients WHERE phone_normalized NOT LIKE '+%'",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut rewritten = 0;
+    for (id, stored) in rows {
+        let Some(phone) = validation::to_e164(&stored, country_code) else {
+            continue;
+        };
+        if let Some(holder) = find_existing(pool, Some(&phone), None).await? {
+            log::warn!("Client {id} phone {stored:?} matches client {holder} as {phone}; left as it is");
+            continue;
+        }
+        sqlx::query("UPDATE clients SET phone_normalized = ? WHERE id = ?")
+            .bind(&phone)
+            .bind(&id)
+            .execute(pool)
+            .await?;
+        rewritten += 1;
+    }
+    if rewritten > 0 {
+        log::info!("Stored {rewritten} client phone numbers as E.164");
+    }
+    Ok(())
+}
+
+/// Resolves whatever the client typed on the login form to a known client and delivery channel.
+pub async fn find_by_contact(
+    pool: &SqlitePool,
+    contact: &str,
+    country_code: Option<&str>,
+) -> Option<(ClientRow, Channel, String)> {
+    // Only verified contacts sign in; an unverified one may have been typed by someone else.
+    let (condition, normalized, channel) = if contact.contains('@') {
+        ("email_normalized = ? AND email_verified_at IS NOT NULL", normalize_email(contact)?, Channel::Email)
+    } else {
+        (
+            "phone_normalized = ? AND phone_verified_at IS NOT NULL",
+            phone_key(contact, country_code)?,
+            Channel::Sms,
+        )
+    };
+    let client = sqlx::query_as::<_, ClientRow>(&format!(
+        "SELECT id, name, phone, email, address, notes, created_at FROM clients WHERE {condition}"
+    ))
+    .bind(&normalized)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)?;
+
+    let destination = match channel {
+        Channel::Email => client.email.clone(),
+        Channel::Sms => client.phone.clone(),
+    }
+    .unwrap_or(normalized);
+    Some((client, channel, destination))
+}
+
+/// How long the link sent after a booking to confirm the client's contact stays valid.
+const CONFIRMATION_LINK_DAYS: i64 = 7;
+
+fn timestamp_in(duration: Duration) -> String {
+    (Utc::now() + duration).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+pub async fn issue_login_token(
+    pool: &SqlitePool,
+    client_id: &str,
+    channel: Channel,
+    ttl_minutes: i64,
+) -> Result<String, sqlx::Error> {
+    let token = random_token();
+    sqlx::query(
+        r#"INSERT INTO client_login_tokens (token, client_id, channel, created_at, expires_at)
+           VALUES (?, ?, ?, ?, ?)"#,
+    )
+    .bind(&token)
+    .bind(client_id)
+    .bind(channel.as_str())
+    .bind(timestamp_in(Duration::zero()))
+    .bind(timestamp_in(Duration::minutes(ttl_minutes)))
+    .execute(pool)
+    .await?;
+    Ok(token)
+}
+
+/// The contact a booking confirmation goes to while none of the client's contacts is verified:
+/// their email, else their phone. Contacts another client holds aren't stored as the client's own,
+/// so a confirmation never goes to someone else.
+pub async fn unverified_contact(pool: &SqlitePool, client_id: &str) -> Option<(Channel, String, String)> {
+    let (phone, phone_normalized, phone_verified, email, email_normalized, email_verified) =
+        sqlx::query_as::<_, (Option<String>, Option<String>, Option<String>, Option<String>, Option<String>, Option<String>)>(
+            r#"SELECT phone, phone_normalized, phone_verified_at, email, email_normalized, email_verified_at
+               FROM clients WHERE id = ?"#,
+        )
+        .bind(client_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)?;
+    if phone_verified.is_some() || email_verified.is_some() {
+        return None;
+    }
+    match (email_normalized, phone_normalized) {
+        (Some(normalized), _) => Some((Channel::Email, email.unwrap_or_else(|| normalized.clone()), normalized)),
+        (None, Some(normalized)) => Some((Channel::Sms, phone.unwrap_or_else(|| normalized.clone()), normalized)),
+        (None, None) => None,
+    }
+}
+
+/// A sign-in link that also confirms `contact` belongs to the client once it is opened.
+pub async fn issue_confirmation_token(
+    pool: &SqlitePool,
+    client_id: &str,
+    channel: Channel,
+    contact: &str,
+) -> Result<String, sqlx::Error> {
+    let token = random_token();
+    sqlx::query(
+        r#"INSERT INTO client_login_tokens (token, client_id, channel, created_at, expires_at, contact)
+           VALUES (?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&token)
+    .bind(client_id)
+    .bind(channel.as_str())
+    .bind(timestamp_in(Duration::zero()))
+    .bind(timestamp_in(Duration::days(CONFIRMATION_LINK_DAYS)))
+    .bind(contact)
+    .execute(pool)
+    .await?;
+    Ok(token)
+}
+
+/// Marks the token used and returns its client, or `None` if it is unknown, expired or already used.
+/// Confirmation links verify the contact they were sent to, and fail if the client no longer has it.
+pub async fn redeem_login_token(pool: &SqlitePool, token: &str) -> Option<String> {
+    let (client_id, channel, contact) = sqlx::query_as::<_, (String, String, Option<String>)>(
+        r#"UPDATE client_login_tokens
+           SET used_at = ?
+           WHERE token = ? AND used_at IS NULL AND expires_at > ?
+           RETURNING client_id, channel, contact"#,
+    )
+    .bind(timestamp_in(Duration::zero()))
+    .bind(token)
+    .bind(timestamp_in(Duration::zero()))
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)?;
+
+    let Some(contact) = contact else {
+        return Some(client_id);
+    };
+    let query = if channel == Channel::Email.as_str() {
+        "UPDATE clients SET email_verified_at = COALESCE(email_verified_at, ?) WHERE id = ? AND email_normalized = ?"
+    } else {
+        "UPDATE clients SET phone_verified_at = COALESCE(phone_verified_at, ?) WHERE id = ? AND phone_normalized = ?"
+    };
+    let verified = sqlx::query(query)
+        .bind(timestamp_in(Duration::zero()))
+        .bind(&client_id)
+        .bind(&contact)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+        .unwrap_or(false);
+    verified.then_some(client_id)
+}
+
+pub async fn create_session(pool: &SqlitePool, client_id: &str, days: i64) -> Result<String, sqlx::Error> {
+    let session_id = random_token();
+    sqlx::query(
+        "INSERT INTO client_sessions (id, client_id, created_at, expires_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(&session_id)
+    .bind(client_id)
+    .bind(timestamp_in(Duration::zero()))
+    .bind(timestamp_in(Duration::days(days)))
+    .execute(pool)
+    .await?;
+    Ok(session_id)
+}
+
+pub async fn end_session(pool: &SqlitePool, session_id: &str) {
+    let _ = sqlx::query("DELETE FROM client_sessions WHERE id = ?")
+        .bind(session_id)
+        .execute(pool)
+        .await;
+}
+
+/// The client signed in through a magic link on this browser, if any.
+pub async fn current_client(req: &HttpRequest, pool: &SqlitePool) -> Option<ClientRow> {
+    let cookie = req.cookie(CLIENT_SESSION_COOKIE)?;
+    sqlx::query_as::<_, ClientRow>(
+        r#"SELECT c.id, c.name, c.phone, c.email, c.address, c.notes, c.created_at
+           FROM client_sessions s
+           JOIN clients c ON c.id = s.client_id
+           WHERE s.id = ? AND s.expires_at > ?"#,
+    )
+    .bind(cookie.value())
+    .bind(timestamp_in(Duration::zero()))
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+pub struct NewLocation<'a> {
+    pub label: &'a str,
+    pub address: &'a str,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub access_notes: Option<&'a str>,
+    pub gate_code: Option<&'a str>,
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+pub async fn fetch_locations(pool: &SqlitePool, client_id: &str) -> Vec<ClientLocationRow> {
+    sqlx::query_as::<_, ClientLocationRow>(
+        r#"SELECT id, label, address, latitude, longitude, access_notes, gate_code
+           FROM client_locations
+           WHERE client_id = ? AND active = 1
+           ORDER BY label"#,
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn fetch_location(pool: &SqlitePool, client_id: &str, location_id: &str) -> Option<ClientLocationRow> {
+    sqlx::query_as::<_, ClientLocationRow>(
+        r#"SELECT id, label, address, latitude, longitude, access_notes, gate_code
+           FROM client_locations
+           WHERE id = ? AND client_id = ? AND active = 1"#,
+    )
+    .bind(location_id)
+    .bind(client_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+pub async fn create_location(
+    pool: &SqlitePool,
+    client_id: &str,
+    location: NewLocation<'_>,
+) -> Result<String, sqlx::Error> {
+    let location_id = new_id();
+    let now = Utc::now().to_rfc3339();
+    let label = non_empty(Some(location.label)).unwrap_or(location.address.trim());
+    sqlx::query(
+        r#"INSERT INTO client_locations
+           (id, client_id, label, address, latitude, longitude, access_notes, gate_code, active, created_at, updated_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)"#,
+    )
+    .bind(&location_id)
+    .bind(client_id)
+    .bind(label)
+    .bind(location.address.trim())
+    .bind(location.latitude)
+    .bind(location.longitude)
+    .bind(non_empty(location.access_notes))
+    .bind(non_empty(location.gate_code))
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await?;
+    Ok(location_id)
+}
+
+/// Hides the location from the client; appointments that already use it keep their access details.
+pub async fn archive_location(pool: &SqlitePool, client_id: &str, location_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE client_locations SET active = 0, updated_at = ? WHERE id = ? AND client_id = ?")
+        .bind(Utc::now().to_rfc3339())
+        .bind(location_id)
+        .bind(client_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    async fn book(pool: &SqlitePool, phone: &str, email: Option<&str>) -> String {
+        find_or_create(pool, "Ana Horvat", phone, email, "Ilica 1, Zagreb", Some("385"))
+            .await
+            .unwrap()
+    }
+
+    async fn client_count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM clients").fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn repeat_bookings_find_the_client_by_phone_or_email() {
+        let pool = test_pool().await;
+        let client_id = book(&pool, "+385 91 234 5678", Some("ana@example.com")).await;
+
+        // Same phone typed differently, email dropped.
+        assert_eq!(book(&pool, "091 234 5678", None).await, client_id);
+        // Same phone, new email.
+        assert_eq!(book(&pool, "+385912345678", Some("ana.h@example.com")).await, client_id);
+        // New phone, same email in another case.
+        assert_eq!(book(&pool, "+385 98 765 4321", Some("Ana@Example.com")).await, client_id);
+        assert_eq!(client_count(&pool).await, 1);
+    }
+
+    #[tokio::test]
+    async fn bookings_with_both_contacts_changed_get_a_new_client() {
+        let pool = test_pool().await;
+        let client_id = book(&pool, "+385 91 234 5678", Some("ana@example.com")).await;
+
+        let other = book(&pool, "+385 98 765 4321", Some("marko@example.com")).await;
+
+        assert_ne!(other, client_id);
+        assert_eq!(client_count(&pool).await, 2);
+    }
+
+    #[tokio::test]
+    async fn a_matched_client_keeps_its_contacts() {
+        let pool = test_pool().await;
+        let client_id = book(&pool, "+385 91 234 5678", None).await;
+        let other = book(&pool, "+385 98 765 4321", Some("marko@example.com")).await;
+
+        // The phone belongs to the first client and wins over the other client's email.
+        assert_eq!(book(&pool, "+385 91 234 5678", Some("marko@example.com")).await, client_id);
+
+        let contacts: (Option<String>, Option<String>) =
+            sqlx::query_as("SELECT phone_normalized, email_normalized FROM clients WHERE id = ?")
+                .bind(&client_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(contacts, (Some("+385912345678".to_string()), None));
+        assert_ne!(other, client_id);
+    }
+}
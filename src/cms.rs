@@ -0,0 +1,364 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use pulldown_cmark::{html::push_html, Parser};
+use similar::{ChangeTag, TextDiff};
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    db::log_activity,
+    i18n::Locale,
+    models::{CmsDraftRow, CmsRevisionRow, CMS_DRAFT, CMS_FORMAT_HTML, CMS_FORMAT_MARKDOWN, CMS_PUBLISHED},
+    sanitize::{sanitize_html, Sanitized},
+    state::AppState,
+};
+
+/// How often scheduled drafts are checked.
+const SCHEDULER_INTERVAL_SECS: u64 = 30;
+
+const DRAFT_COLUMNS: &str =
+    "d.block_key, d.html, d.format, d.source, d.updated_at, d.updated_by, u.display_name AS author, d.publish_at";
+
+const REVISION_COLUMNS: &str = "r.id, r.html, r.format, r.source, r.kind, r.created_at, u.display_name AS author";
+
+/// A block's content as stored: the sanitized HTML the site shows, plus the Markdown it came from, if any.
+pub struct BlockContent {
+    pub format: String,
+    pub source: Option<String>,
+    pub html: String,
+}
+
+impl BlockContent {
+    /// Cleans editor input in either format. Also returns what the sanitizer removed.
+    pub fn prepare(format: &str, html: &str, markdown: &str) -> (Self, Vec<String>) {
+        if format == CMS_FORMAT_MARKDOWN {
+            let Sanitized { html, removed } = render_markdown(markdown);
+            let content = Self {
+                format: CMS_FORMAT_MARKDOWN.to_string(),
+                source: Some(markdown.to_string()),
+                html,
+            };
+            (content, removed)
+        } else {
+            let Sanitized { html, removed } = sanitize_html(html);
+            (Self::html(html), removed)
+        }
+    }
+
+    pub fn html(html: String) -> Self {
+        Self {
+            format: CMS_FORMAT_HTML.to_string(),
+            source: None,
+            html,
+        }
+    }
+}
+
+impl From<CmsDraftRow> for BlockContent {
+    fn from(draft: CmsDraftRow) -> Self {
+        Self {
+            format: draft.format,
+            source: draft.source,
+            html: draft.html,
+        }
+    }
+}
+
+impl From<CmsRevisionRow> for BlockContent {
+    fn from(revision: CmsRevisionRow) -> Self {
+        Self {
+            format: revision.format,
+            source: revision.source,
+            html: revision.html,
+        }
+    }
+}
+
+/// Renders Markdown and runs the result through the same sanitizer as HTML blocks; raw HTML in the
+/// Markdown is allowed but cleaned.
+pub fn render_markdown(source: &str) -> Sanitized {
+    let mut html = String::new();
+    push_html(&mut html, Parser::new(source));
+    sanitize_html(&html)
+}
+
+/// Key of the block's translation, e.g. "home_about:hr". The default language uses the block itself.
+pub fn variant_key(key: &str, locale: Locale) -> String {
+    if locale == Locale::default() {
+        key.to_string()
+    } else {
+        format!("{key}:{}", locale.code())
+    }
+}
+
+/// Splits a translation's key into the original block's key and the language.
+pub fn split_variant(key: &str) -> (&str, Option<Locale>) {
+    match key.rsplit_once(':') {
+        Some((base, code)) => match Locale::parse(code).filter(|locale| *locale != Locale::default()) {
+            Some(locale) => (base, Some(locale)),
+            None => (key, None),
+        },
+        None => (key, None),
+    }
+}
+
+/// Adds the row for a translation the first time it is saved. It stays empty, and visitors keep
+/// seeing the original, until the translation is published.
+pub async fn ensure_variant(pool: &SqlitePool, key: &str) -> Result<(), sqlx::Error> {
+    let (base, Some(locale)) = split_variant(key) else {
+        return Ok(());
+    };
+    sqlx::query(
+        r#"INSERT INTO cms_blocks (key, title, html, updated_at, locale, variant_of)
+           SELECT ?, title || ' (' || ? || ')', '', ?, ?, key FROM cms_blocks WHERE key = ? AND variant_of IS NULL
+           ON CONFLICT(key) DO NOTHING"#,
+    )
+    .bind(key)
+    .bind(locale.name())
+    .bind(Utc::now().to_rfc3339())
+    .bind(locale.code())
+    .bind(base)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The block's live HTML in `locale`, or the original's when it has not been translated.
+pub async fn localized_html(pool: &SqlitePool, key: &str, locale: Locale) -> String {
+    sqlx::query_scalar::<_, String>(
+        r#"SELECT COALESCE(NULLIF(v.html, ''), b.html)
+           FROM cms_blocks b
+           LEFT JOIN cms_blocks v ON v.variant_of = b.key AND v.locale = ?
+           WHERE b.key = ?"#,
+    )
+    .bind(locale.code())
+    .bind(key)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+    .unwrap_or_default()
+}
+
+/// Makes `html` the live copy of the block and records it as a revision; any draft is dropped.
+pub async fn publish(pool: &SqlitePool, key: &str, content: &BlockContent, author: Option<&str>) -> Result<(), sqlx::Error> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO cms_blocks (key, title, html, format, source, updated_at, updated_by)
+           VALUES (?, ?, ?, ?, ?, ?, ?)
+           ON CONFLICT(key) DO UPDATE SET
+             html = excluded.html, format = excluded.format, source = excluded.source,
+             updated_at = excluded.updated_at, updated_by = excluded.updated_by"#,
+    )
+    .bind(key)
+    .bind(key)
+    .bind(&content.html)
+    .bind(&content.format)
+    .bind(&content.source)
+    .bind(&now)
+    .bind(author)
+    .execute(&mut *tx)
+    .await?;
+    insert_revision(&mut tx, key, content, CMS_PUBLISHED, &now, author).await?;
+    sqlx::query("DELETE FROM cms_drafts WHERE block_key = ?")
+        .bind(key)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+/// Stores `html` as the block's draft without touching the live copy. A pending schedule is kept.
+pub async fn save_draft(pool: &SqlitePool, key: &str, content: &BlockContent, author: Option<&str>) -> Result<(), sqlx::Error> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO cms_drafts (block_key, html, format, source, updated_at, updated_by, publish_at)
+           VALUES (?, ?, ?, ?, ?, ?, NULL)
+           ON CONFLICT(block_key) DO UPDATE SET
+             html = excluded.html, format = excluded.format, source = excluded.source,
+             updated_at = excluded.updated_at, updated_by = excluded.updated_by"#,
+    )
+    .bind(key)
+    .bind(&content.html)
+    .bind(&content.format)
+    .bind(&content.source)
+    .bind(&now)
+    .bind(author)
+    .execute(&mut *tx)
+    .await?;
+    insert_revision(&mut tx, key, content, CMS_DRAFT, &now, author).await?;
+    tx.commit().await
+}
+
+pub async fn insert_revision(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    key: &str,
+    content: &BlockContent,
+    kind: &str,
+    created_at: &str,
+    author: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"INSERT INTO cms_revisions (id, block_key, html, format, source, kind, created_at, created_by)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(new_id())
+    .bind(key)
+    .bind(&content.html)
+    .bind(&content.format)
+    .bind(&content.source)
+    .bind(kind)
+    .bind(created_at)
+    .bind(author)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Drafts keyed by block.
+pub async fn drafts(pool: &SqlitePool) -> HashMap<String, CmsDraftRow> {
+    sqlx::query_as::<_, CmsDraftRow>(&format!(
+        "SELECT {DRAFT_COLUMNS} FROM cms_drafts d LEFT JOIN users u ON u.id = d.updated_by"
+    ))
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|draft| (draft.block_key.clone(), draft))
+    .collect()
+}
+
+/// Sets or clears when the block's draft goes live. Returns false when the block has no draft.
+pub async fn schedule(pool: &SqlitePool, key: &str, publish_at: Option<DateTime<Utc>>) -> Result<bool, sqlx::Error> {
+    sqlx::query("UPDATE cms_drafts SET publish_at = ? WHERE block_key = ?")
+        .bind(publish_at.map(|at| at.to_rfc3339()))
+        .bind(key)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+pub async fn discard_draft(pool: &SqlitePool, key: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM cms_drafts WHERE block_key = ?")
+        .bind(key)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+/// The block's history, newest first.
+pub async fn revisions(pool: &SqlitePool, key: &str, limit: i64) -> Vec<CmsRevisionRow> {
+    sqlx::query_as::<_, CmsRevisionRow>(&format!(
+        r#"SELECT {REVISION_COLUMNS}
+           FROM cms_revisions r
+           LEFT JOIN users u ON u.id = r.created_by
+           WHERE r.block_key = ?
+           ORDER BY r.created_at DESC
+           LIMIT ?"#
+    ))
+    .bind(key)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn fetch_revision(pool: &SqlitePool, key: &str, revision_id: &str) -> Option<CmsRevisionRow> {
+    sqlx::query_as::<_, CmsRevisionRow>(&format!(
+        r#"SELECT {REVISION_COLUMNS}
+           FROM cms_revisions r
+           LEFT JOIN users u ON u.id = r.created_by
+           WHERE r.block_key = ? AND r.id = ?"#
+    ))
+    .bind(key)
+    .bind(revision_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+pub struct DiffLine {
+    /// "added", "removed" or "same".
+    pub kind: &'static str,
+    pub text: String,
+}
+
+/// Line diff of two blocks' HTML, with a line break after each block-level element.
+pub fn diff(old: &str, new: &str) -> Vec<DiffLine> {
+    diff_text(&split_blocks(old), &split_blocks(new))
+}
+
+/// Line diff of plain text such as Markdown sources.
+pub fn diff_text(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            kind: match change.tag() {
+                ChangeTag::Insert => "added",
+                ChangeTag::Delete => "removed",
+                ChangeTag::Equal => "same",
+            },
+            text: change.value().trim_end().to_string(),
+        })
+        .filter(|line| !line.text.is_empty())
+        .collect()
+}
+
+fn split_blocks(html: &str) -> String {
+    let mut lines = html.replace('\n', "");
+    for tag in ["</p>", "</h1>", "</h2>", "</h3>", "</h4>", "</li>", "</blockquote>", "</figure>", "<br>", "<hr>"] {
+        lines = lines.replace(tag, &format!("{tag}\n"));
+    }
+    lines.push('\n');
+    lines
+}
+
+/// Publishes drafts whose `publish_at` has passed, every `SCHEDULER_INTERVAL_SECS`.
+pub fn spawn_scheduler(state: &AppState) {
+    let state = state.clone();
+    actix_web::rt::spawn(async move {
+        loop {
+            publish_due(&state).await;
+            tokio::time::sleep(Duration::from_secs(SCHEDULER_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+async fn publish_due(state: &AppState) {
+    let now = Utc::now();
+    let scheduled = sqlx::query_as::<_, CmsDraftRow>(&format!(
+        r#"SELECT {DRAFT_COLUMNS}
+           FROM cms_drafts d
+           LEFT JOIN users u ON u.id = d.updated_by
+           WHERE d.publish_at IS NOT NULL"#
+    ))
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    for draft in scheduled {
+        let due = draft
+            .publish_at
+            .as_deref()
+            .and_then(|publish_at| DateTime::parse_from_rfc3339(publish_at).ok())
+            .is_some_and(|publish_at| publish_at <= now);
+        if !due {
+            continue;
+        }
+        let key = draft.block_key.clone();
+        let author = draft.updated_by.clone();
+        if let Err(err) = publish(&state.db, &key, &BlockContent::from(draft), author.as_deref()).await {
+            log::warn!("Publishing scheduled CMS block {key} failed: {err}");
+            continue;
+        }
+        log_activity(
+            &state.db,
+            "cms_published",
+            &format!("Scheduled draft of CMS block {key} was published."),
+            None,
+            None,
+        )
+        .await;
+    }
+}
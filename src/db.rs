@@ -1,25 +1,37 @@
 use std::{env, fs, path::Path};
 
 use chrono::Utc;
+use chrono_tz::Tz;
 use sqlx::SqlitePool;
 
 use crate::{
     auth::{hash_password, new_id},
-    models::{AppointmentRow, ROLE_ADMIN, ROLE_BARBER},
+    models::{AppointmentRow, CMS_PUBLISHED, ROLE_ADMIN, ROLE_BARBER},
+    sanitize::sanitize_html,
+    schedule,
 };
 
 pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::migrate::MigrateError> {
     sqlx::migrate!("./migrations").run(pool).await
 }
 
+/// A migrated in-memory database. It has one connection, since every connection to `:memory:`
+/// opens a database of its own.
+#[cfg(test)]
+pub async fn test_pool() -> SqlitePool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    run_migrations(&pool).await.unwrap();
+    pool
+}
+
 pub fn ensure_sqlite_dir(db_url: &str) -> std::io::Result<()> {
-    let path = if let Some(path) = db_url.strip_prefix("sqlite://") {
-        Some(path)
-    } else if let Some(path) = db_url.strip_prefix("sqlite:") {
-        Some(path)
-    } else {
-        None
-    };
+    let path = db_url
+        .strip_prefix("sqlite://")
+        .or_else(|| db_url.strip_prefix("sqlite:"));
 
     let Some(path) = path else {
         return Ok(());
@@ -41,6 +53,7 @@ pub fn ensure_sqlite_dir(db_url: &str) -> std::io::Result<()> {
 pub async fn seed_defaults(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     seed_admin(pool).await?;
     seed_cms(pool).await?;
+    sanitize_cms(pool).await?;
     Ok(())
 }
 
@@ -124,6 +137,54 @@ async fn seed_admin(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     Ok(())
 }
 
+/// Re-cleans blocks saved before sanitization existed; blocks that are already clean are left alone.
+async fn sanitize_cms(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let blocks = sqlx::query_as::<_, (String, String)>("SELECT key, html FROM cms_blocks")
+        .fetch_all(pool)
+        .await?;
+    for (key, html) in blocks {
+        let cleaned = sanitize_html(&html);
+        if cleaned.html == html {
+            continue;
+        }
+        log::warn!("Sanitized CMS block {key}: removed {}", cleaned.removed.join(", "));
+        sqlx::query("UPDATE cms_blocks SET html = ? WHERE key = ?")
+            .bind(&cleaned.html)
+            .bind(&key)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Converts times saved as bare wall-clock readings into UTC, reading each in its barber's zone.
+pub async fn store_scheduled_as_utc(pool: &SqlitePool, business_zone: Tz) -> Result<(), sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String, Option<String>)>(
+        r#"SELECT a.id, a.scheduled_for, u.timezone
+           FROM appointments a
+           LEFT JOIN users u ON a.barber_id = u.id
+           WHERE a.scheduled_for NOT LIKE '%Z'"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    for (id, scheduled_for, zone) in &rows {
+        let zone = zone.as_deref().and_then(schedule::parse_zone).unwrap_or(business_zone);
+        let Some(at) = schedule::parse_local(scheduled_for, zone) else {
+            log::warn!("Appointment {id} has an unreadable time {scheduled_for:?}; left as it is");
+            continue;
+        };
+        sqlx::query("UPDATE appointments SET scheduled_for = ? WHERE id = ?")
+            .bind(schedule::to_stored(at))
+            .bind(id)
+            .execute(pool)
+            .await?;
+    }
+    if !rows.is_empty() {
+        log::info!("Stored {} appointment times as UTC", rows.len());
+    }
+    Ok(())
+}
+
 async fn seed_cms(pool: &SqlitePool) -> Result<(), sqlx::Error> {
     let blocks = vec![
         (
@@ -162,7 +223,18 @@ async fn seed_cms(pool: &SqlitePool) -> Result<(), sqlx::Error> {
         .bind(key)
         .bind(title)
         .bind(html)
-        .bind(now)
+        .bind(&now)
+        .execute(pool)
+        .await?;
+        sqlx::query(
+            r#"INSERT INTO cms_revisions (id, block_key, html, kind, created_at, created_by)
+               VALUES (?, ?, ?, ?, ?, NULL)"#,
+        )
+        .bind(new_id())
+        .bind(key)
+        .bind(html)
+        .bind(CMS_PUBLISHED)
+        .bind(&now)
         .execute(pool)
         .await?;
     }
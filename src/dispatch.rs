@@ -0,0 +1,431 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    db::{fetch_appointment_event, log_activity},
+    geo::{self, haversine_km},
+    models::{
+        service_minutes, AppointmentRow, OFFER_ACCEPTED, OFFER_DECLINED, OFFER_EXPIRED,
+        OFFER_OPEN, OFFER_WITHDRAWN, STATUS_ACCEPTED, STATUS_PENDING,
+    },
+    schedule::{self, parse_scheduled_for},
+    state::{AppState, ServerEvent, TimeConfig},
+};
+
+/// Each accepted job that day counts like this many extra kilometres of travel.
+const WORKLOAD_PENALTY_KM: f64 = 5.0;
+/// Barbers without a base location rank as if they were this far away.
+const UNKNOWN_DISTANCE_KM: f64 = 25.0;
+/// Travel buffer kept free on either side of an accepted visit.
+const BUFFER_MINUTES: i64 = 30;
+const POLL_INTERVAL_SECS: u64 = 5;
+
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub barber_id: String,
+    pub display_name: String,
+    pub distance_km: Option<f64>,
+    pub workload: i64,
+    pub score: f64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct OfferRow {
+    id: String,
+    barber_id: String,
+    status: String,
+    expires_at: String,
+}
+
+enum OfferOutcome {
+    Accepted,
+    Declined,
+    Expired,
+    Withdrawn,
+}
+
+/// Starts auto-dispatch for an unassigned appointment when the engine is enabled.
+pub fn spawn(state: &AppState, appointment_id: &str) {
+    if !state.dispatch.enabled {
+        return;
+    }
+    let state = state.clone();
+    let appointment_id = appointment_id.to_string();
+    actix_web::rt::spawn(async move {
+        run(&state, &appointment_id).await;
+    });
+}
+
+/// Picks up offers that were still open when the server stopped.
+pub async fn resume(state: &AppState) {
+    if !state.dispatch.enabled {
+        return;
+    }
+    let appointment_ids = sqlx::query_scalar::<_, String>(
+        r#"SELECT DISTINCT o.appointment_id
+           FROM dispatch_offers o
+           JOIN appointments a ON a.id = o.appointment_id
+           WHERE o.status = ? AND a.status = ?"#,
+    )
+    .bind(OFFER_OPEN)
+    .bind(STATUS_PENDING)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    for appointment_id in appointment_ids {
+        spawn(state, &appointment_id);
+    }
+}
+
+/// Records a barber's answer to an open offer. Returns false when no offer was open.
+pub async fn respond(pool: &SqlitePool, appointment_id: &str, barber_id: &str, accepted: bool) -> bool {
+    let status = if accepted { OFFER_ACCEPTED } else { OFFER_DECLINED };
+    sqlx::query(
+        r#"UPDATE dispatch_offers SET status = ?, responded_at = ?
+           WHERE appointment_id = ? AND barber_id = ? AND status = ?"#,
+    )
+    .bind(status)
+    .bind(Utc::now().to_rfc3339())
+    .bind(appointment_id)
+    .bind(barber_id)
+    .bind(OFFER_OPEN)
+    .execute(pool)
+    .await
+    .map(|result| result.rows_affected() > 0)
+    .unwrap_or(false)
+}
+
+pub async fn rank_candidates(pool: &SqlitePool, time: &TimeConfig, appointment: &AppointmentRow) -> Vec<Candidate> {
+    let reach = geo::fetch_barber_reach(pool).await.unwrap_or_default();
+    let already_offered = sqlx::query_scalar::<_, String>(
+        "SELECT barber_id FROM dispatch_offers WHERE appointment_id = ?",
+    )
+    .bind(&appointment.id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+
+    let location = appointment.latitude.zip(appointment.longitude);
+    let start = parse_scheduled_for(&appointment.scheduled_for);
+    let minutes = appointment_minutes(pool, &appointment.id, &appointment.service).await;
+
+    let mut candidates = Vec::new();
+    for barber in reach {
+        if already_offered.contains(&barber.id) {
+            continue;
+        }
+        if let Some((lat, lon)) = location
+            && !barber.covers(lat, lon)
+        {
+            continue;
+        }
+
+        let commitments = commitments(pool, &barber.id, &appointment.id).await;
+
+        // Workload counts visits on the same day where the barber works.
+        let zone = schedule::barber_zone(pool, time, Some(&barber.id)).await;
+        let mut workload = 0;
+        let mut available = true;
+        for &(other_start, other_minutes) in &commitments {
+            let Some(start) = start else {
+                workload += 1;
+                continue;
+            };
+            if schedule::wall_clock(other_start, zone).date() == schedule::wall_clock(start, zone).date() {
+                workload += 1;
+            }
+            if overlaps(start, minutes, other_start, other_minutes) {
+                available = false;
+            }
+        }
+        if !available {
+            continue;
+        }
+
+        let distance_km = match (location, barber.base_latitude.zip(barber.base_longitude)) {
+            (Some(to), Some(from)) => Some(haversine_km(from, to)),
+            _ => None,
+        };
+        let score = distance_km.unwrap_or(UNKNOWN_DISTANCE_KM) + workload as f64 * WORKLOAD_PENALTY_KM;
+        candidates.push(Candidate {
+            barber_id: barber.id,
+            display_name: barber.display_name,
+            distance_km,
+            workload,
+            score,
+        });
+    }
+
+    candidates.sort_by(|a, b| a.score.total_cmp(&b.score));
+    candidates
+}
+
+/// Whether the barber's accepted visits, with travel buffers, leave room for the appointment at `start`.
+pub async fn barber_is_free(
+    pool: &SqlitePool,
+    barber_id: &str,
+    appointment_id: &str,
+    service: &str,
+    start: DateTime<Utc>,
+) -> bool {
+    let minutes = appointment_minutes(pool, appointment_id, service).await;
+    commitments(pool, barber_id, appointment_id)
+        .await
+        .into_iter()
+        .all(|(other_start, other_minutes)| !overlaps(start, minutes, other_start, other_minutes))
+}
+
+async fn appointment_minutes(pool: &SqlitePool, appointment_id: &str, service: &str) -> i64 {
+    sqlx::query_scalar::<_, Option<i64>>("SELECT duration_minutes FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .flatten()
+        .unwrap_or_else(|| service_minutes(service))
+}
+
+/// Start and length of the barber's accepted visits other than `appointment_id`.
+async fn commitments(pool: &SqlitePool, barber_id: &str, appointment_id: &str) -> Vec<(DateTime<Utc>, i64)> {
+    sqlx::query_as::<_, (String, String, Option<i64>)>(
+        r#"SELECT scheduled_for, service, duration_minutes FROM appointments
+           WHERE (barber_id = ? OR id IN (SELECT appointment_id FROM appointment_items WHERE barber_id = ?))
+             AND status = ? AND id != ?"#,
+    )
+    .bind(barber_id)
+    .bind(barber_id)
+    .bind(STATUS_ACCEPTED)
+    .bind(appointment_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .filter_map(|(scheduled_for, service, duration)| {
+        Some((parse_scheduled_for(&scheduled_for)?, duration.unwrap_or_else(|| service_minutes(&service))))
+    })
+    .collect()
+}
+
+fn overlaps(start: DateTime<Utc>, minutes: i64, other_start: DateTime<Utc>, other_minutes: i64) -> bool {
+    let other_end = other_start + chrono::Duration::minutes(other_minutes + BUFFER_MINUTES);
+    let other_start = other_start - chrono::Duration::minutes(BUFFER_MINUTES);
+    let end = start + chrono::Duration::minutes(minutes);
+    start < other_end && other_start < end
+}
+
+async fn run(state: &AppState, appointment_id: &str) {
+    loop {
+        let Some(appointment) = fetch_appointment_event(&state.db, appointment_id).await else {
+            return;
+        };
+        if appointment.status != STATUS_PENDING {
+            return;
+        }
+
+        let offer = match open_offer(&state.db, appointment_id).await {
+            Some(offer) => offer,
+            None => {
+                if appointment.barber_id.is_some() {
+                    return;
+                }
+                let candidates = rank_candidates(&state.db, &state.time, &appointment).await;
+                let Some(best) = candidates.into_iter().next() else {
+                    log_activity(
+                        &state.db,
+                        "dispatch_exhausted",
+                        &format!(
+                            "No available barber for {}; left in the open pool.",
+                            appointment.client_name
+                        ),
+                        None,
+                        Some(appointment_id),
+                    )
+                    .await;
+                    return;
+                };
+                match make_offer(state, &appointment, &best).await {
+                    Some(offer) => offer,
+                    None => return,
+                }
+            }
+        };
+
+        let outcome = wait_for_response(state, appointment_id, &offer).await;
+        match outcome {
+            OfferOutcome::Accepted | OfferOutcome::Withdrawn => return,
+            OfferOutcome::Declined | OfferOutcome::Expired => {
+                release(state, appointment_id, &offer.barber_id).await;
+            }
+        }
+    }
+}
+
+async fn open_offer(pool: &SqlitePool, appointment_id: &str) -> Option<OfferRow> {
+    sqlx::query_as::<_, OfferRow>(
+        r#"SELECT id, barber_id, status, expires_at
+           FROM dispatch_offers
+           WHERE appointment_id = ? AND status = ?
+           ORDER BY offered_at DESC
+           LIMIT 1"#,
+    )
+    .bind(appointment_id)
+    .bind(OFFER_OPEN)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+async fn make_offer(state: &AppState, appointment: &AppointmentRow, candidate: &Candidate) -> Option<OfferRow> {
+    let now = Utc::now();
+    let expires_at = now + chrono::Duration::seconds(state.dispatch.offer_timeout_secs as i64);
+    let offer = OfferRow {
+        id: new_id(),
+        barber_id: candidate.barber_id.clone(),
+        status: OFFER_OPEN.to_string(),
+        expires_at: expires_at.to_rfc3339(),
+    };
+
+    let assigned = sqlx::query(
+        "UPDATE appointments SET barber_id = ? WHERE id = ? AND status = ? AND barber_id IS NULL",
+    )
+    .bind(&candidate.barber_id)
+    .bind(&appointment.id)
+    .bind(STATUS_PENDING)
+    .execute(&state.db)
+    .await
+    .map(|result| result.rows_affected() > 0)
+    .unwrap_or(false);
+    if !assigned {
+        return None;
+    }
+
+    sqlx::query(
+        r#"INSERT INTO dispatch_offers (id, appointment_id, barber_id, status, score, offered_at, expires_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&offer.id)
+    .bind(&appointment.id)
+    .bind(&offer.barber_id)
+    .bind(OFFER_OPEN)
+    .bind(candidate.score)
+    .bind(now.to_rfc3339())
+    .bind(&offer.expires_at)
+    .execute(&state.db)
+    .await
+    .ok()?;
+
+    let distance = candidate
+        .distance_km
+        .map(|km| format!("{km:.1} km away"))
+        .unwrap_or_else(|| "distance unknown".to_string());
+    log_activity(
+        &state.db,
+        "dispatch_offered",
+        &format!(
+            "Offered {}'s booking to {} ({}, {} job(s) that day).",
+            appointment.client_name, candidate.display_name, distance, candidate.workload
+        ),
+        Some(&candidate.barber_id),
+        Some(&appointment.id),
+    )
+    .await;
+
+    if let Some(row) = fetch_appointment_event(&state.db, &appointment.id).await {
+        let _ = state
+            .events
+            .send(ServerEvent::from_row("appointment_offered", row));
+    }
+
+    Some(offer)
+}
+
+async fn wait_for_response(state: &AppState, appointment_id: &str, offer: &OfferRow) -> OfferOutcome {
+    let deadline = DateTime::parse_from_rfc3339(&offer.expires_at)
+        .map(|value| value.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+    let poll = Duration::from_secs(POLL_INTERVAL_SECS.min(state.dispatch.offer_timeout_secs.max(1)));
+
+    loop {
+        let status = sqlx::query_scalar::<_, String>("SELECT status FROM dispatch_offers WHERE id = ?")
+            .bind(&offer.id)
+            .fetch_optional(&state.db)
+            .await
+            .unwrap_or(None)
+            .unwrap_or_else(|| offer.status.clone());
+        match status.as_str() {
+            OFFER_ACCEPTED => return OfferOutcome::Accepted,
+            OFFER_DECLINED => return OfferOutcome::Declined,
+            OFFER_EXPIRED => return OfferOutcome::Expired,
+            OFFER_WITHDRAWN => return OfferOutcome::Withdrawn,
+            _ => {}
+        }
+
+        // Staff may reassign or close the appointment while the offer is open.
+        let current = sqlx::query_as::<_, (Option<String>, String)>(
+            "SELECT barber_id, status FROM appointments WHERE id = ?",
+        )
+        .bind(appointment_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None);
+        let still_offered = matches!(
+            &current,
+            Some((Some(barber_id), status)) if *barber_id == offer.barber_id && status == STATUS_PENDING
+        );
+        if !still_offered {
+            let accepted = matches!(
+                &current,
+                Some((Some(barber_id), status)) if *barber_id == offer.barber_id && status == STATUS_ACCEPTED
+            );
+            let outcome = if accepted { OFFER_ACCEPTED } else { OFFER_WITHDRAWN };
+            close_offer(&state.db, &offer.id, outcome).await;
+            return if accepted {
+                OfferOutcome::Accepted
+            } else {
+                OfferOutcome::Withdrawn
+            };
+        }
+
+        if Utc::now() >= deadline {
+            close_offer(&state.db, &offer.id, OFFER_EXPIRED).await;
+            return OfferOutcome::Expired;
+        }
+        tokio::time::sleep(poll).await;
+    }
+}
+
+async fn close_offer(pool: &SqlitePool, offer_id: &str, status: &str) {
+    let _ = sqlx::query(
+        "UPDATE dispatch_offers SET status = ?, responded_at = ? WHERE id = ? AND status = ?",
+    )
+    .bind(status)
+    .bind(Utc::now().to_rfc3339())
+    .bind(offer_id)
+    .bind(OFFER_OPEN)
+    .execute(pool)
+    .await;
+}
+
+/// Returns the appointment to the pool so the next candidate can be offered it.
+pub async fn release(state: &AppState, appointment_id: &str, barber_id: &str) {
+    let released = sqlx::query(
+        "UPDATE appointments SET barber_id = NULL WHERE id = ? AND barber_id = ? AND status = ?",
+    )
+    .bind(appointment_id)
+    .bind(barber_id)
+    .bind(STATUS_PENDING)
+    .execute(&state.db)
+    .await
+    .map(|result| result.rows_affected() > 0)
+    .unwrap_or(false);
+
+    if released && let Some(row) = fetch_appointment_event(&state.db, appointment_id).await {
+        let _ = state
+            .events
+            .send(ServerEvent::from_row("appointment_updated", row));
+    }
+}
@@ -0,0 +1,153 @@
+use sqlx::SqlitePool;
+
+use crate::models::{ServiceAreaRow, AREA_KIND_POLYGON, AREA_KIND_RADIUS, ROLE_BARBER};
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coverage {
+    Inside,
+    Outside,
+    Unknown,
+}
+
+#[derive(Clone, Debug, sqlx::FromRow)]
+pub struct BarberReachRow {
+    pub id: String,
+    pub display_name: String,
+    pub base_latitude: Option<f64>,
+    pub base_longitude: Option<f64>,
+    pub travel_radius_km: Option<f64>,
+}
+
+impl BarberReachRow {
+    /// Barbers without a base location or radius travel anywhere we serve.
+    pub fn covers(&self, latitude: f64, longitude: f64) -> bool {
+        match (self.base_latitude, self.base_longitude, self.travel_radius_km) {
+            (Some(base_lat), Some(base_lon), Some(radius)) if radius > 0.0 => {
+                haversine_km((base_lat, base_lon), (latitude, longitude)) <= radius
+            }
+            _ => true,
+        }
+    }
+}
+
+impl ServiceAreaRow {
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        match self.kind.as_str() {
+            AREA_KIND_RADIUS => match (self.center_latitude, self.center_longitude, self.radius_km) {
+                (Some(lat), Some(lon), Some(radius)) => {
+                    haversine_km((lat, lon), (latitude, longitude)) <= radius
+                }
+                _ => false,
+            },
+            AREA_KIND_POLYGON => self
+                .polygon
+                .as_deref()
+                .and_then(parse_polygon)
+                .map(|points| point_in_polygon((latitude, longitude), &points))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+}
+
+pub fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = lon2 - lon1;
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Ray casting over (lat, lon) vertices; the ring is closed implicitly.
+pub fn point_in_polygon(point: (f64, f64), polygon: &[(f64, f64)]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+    let (y, x) = point;
+    let mut inside = false;
+    let mut j = polygon.len() - 1;
+    for i in 0..polygon.len() {
+        let (yi, xi) = polygon[i];
+        let (yj, xj) = polygon[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+pub fn parse_polygon(raw: &str) -> Option<Vec<(f64, f64)>> {
+    let points: Vec<[f64; 2]> = serde_json::from_str(raw).ok()?;
+    Some(points.into_iter().map(|[lat, lon]| (lat, lon)).collect())
+}
+
+/// Parses one `lat, lon` pair per line, as typed into the admin area form.
+pub fn parse_polygon_lines(input: &str) -> Option<Vec<(f64, f64)>> {
+    let mut points = Vec::new();
+    for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (lat, lon) = line.split_once(',')?;
+        let lat = lat.trim().parse::<f64>().ok()?;
+        let lon = lon.trim().parse::<f64>().ok()?;
+        if !valid_coordinates(lat, lon) {
+            return None;
+        }
+        points.push((lat, lon));
+    }
+    if points.len() < 3 {
+        return None;
+    }
+    Some(points)
+}
+
+pub fn polygon_to_json(points: &[(f64, f64)]) -> String {
+    let pairs: Vec<[f64; 2]> = points.iter().map(|(lat, lon)| [*lat, *lon]).collect();
+    serde_json::to_string(&pairs).unwrap_or_else(|_| "[]".to_string())
+}
+
+pub fn valid_coordinates(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+}
+
+pub async fn fetch_active_areas(pool: &SqlitePool) -> Vec<ServiceAreaRow> {
+    sqlx::query_as::<_, ServiceAreaRow>(
+        r#"SELECT id, name, kind, center_latitude, center_longitude, radius_km, polygon, active
+           FROM service_areas
+           WHERE active = 1
+           ORDER BY name"#,
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// With no areas configured every location is served.
+pub async fn area_coverage(pool: &SqlitePool, latitude: Option<f64>, longitude: Option<f64>) -> Coverage {
+    let areas = fetch_active_areas(pool).await;
+    if areas.is_empty() {
+        return Coverage::Inside;
+    }
+    let (Some(latitude), Some(longitude)) = (latitude, longitude) else {
+        return Coverage::Unknown;
+    };
+    if areas.iter().any(|area| area.contains(latitude, longitude)) {
+        Coverage::Inside
+    } else {
+        Coverage::Outside
+    }
+}
+
+pub async fn fetch_barber_reach(pool: &SqlitePool) -> Result<Vec<BarberReachRow>, sqlx::Error> {
+    sqlx::query_as::<_, BarberReachRow>(
+        r#"SELECT id, display_name, base_latitude, base_longitude, travel_radius_km
+           FROM users
+           WHERE role = ? AND active = 1
+           ORDER BY display_name"#,
+    )
+    .bind(ROLE_BARBER)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,490 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use isahc::{config::Configurable, AsyncReadResponseExt, HttpClient};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::{
+    db::{fetch_appointment_event, log_activity},
+    geo::{self, Coverage},
+    state::{AppState, ServerEvent},
+};
+
+/// Nominatim's usage policy allows at most one request per second.
+const REQUEST_SPACING_MS: u64 = 1100;
+const BACKFILL_BATCH: i64 = 20;
+
+#[derive(Clone, Debug)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum GeocodeError {
+    Http(String),
+    Parse(String),
+}
+
+impl fmt::Display for GeocodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeocodeError::Http(message) => write!(f, "geocoder request failed: {message}"),
+            GeocodeError::Parse(message) => write!(f, "geocoder response invalid: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for GeocodeError {}
+
+#[async_trait]
+pub trait Geocoder: Send + Sync {
+    /// Resolves a free-text address; `Ok(None)` means the provider found no match.
+    async fn geocode(&self, address: &str) -> Result<Option<GeoPoint>, GeocodeError>;
+}
+
+/// Talks to any service exposing Nominatim's `/search?format=jsonv2` API.
+pub struct NominatimGeocoder {
+    base_url: String,
+    country_codes: Option<String>,
+    client: HttpClient,
+}
+
+#[derive(Deserialize)]
+struct NominatimPlace {
+    lat: String,
+    lon: String,
+    display_name: Option<String>,
+}
+
+impl NominatimGeocoder {
+    pub fn new(base_url: &str, user_agent: &str, country_codes: Option<String>) -> Result<Self, GeocodeError> {
+        let client = HttpClient::builder()
+            .timeout(Duration::from_secs(10))
+            .default_header("User-Agent", user_agent)
+            .build()
+            .map_err(|err| GeocodeError::Http(err.to_string()))?;
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            country_codes,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl Geocoder for NominatimGeocoder {
+    async fn geocode(&self, address: &str) -> Result<Option<GeoPoint>, GeocodeError> {
+        let mut url = url::Url::parse(&format!("{}/search", self.base_url))
+            .map_err(|err| GeocodeError::Http(err.to_string()))?;
+        url.query_pairs_mut()
+            .append_pair("format", "jsonv2")
+            .append_pair("limit", "1")
+            .append_pair("q", address);
+        if let Some(codes) = self.country_codes.as_deref() {
+            url.query_pairs_mut().append_pair("countrycodes", codes);
+        }
+
+        let mut response = self
+            .client
+            .get_async(url.as_str())
+            .await
+            .map_err(|err| GeocodeError::Http(err.to_string()))?;
+        if !response.status().is_success() {
+            return Err(GeocodeError::Http(format!("status {}", response.status())));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|err| GeocodeError::Http(err.to_string()))?;
+        let places: Vec<NominatimPlace> =
+            serde_json::from_str(&body).map_err(|err| GeocodeError::Parse(err.to_string()))?;
+
+        let Some(place) = places.into_iter().next() else {
+            return Ok(None);
+        };
+        let latitude = place
+            .lat
+            .parse::<f64>()
+            .map_err(|err| GeocodeError::Parse(err.to_string()))?;
+        let longitude = place
+            .lon
+            .parse::<f64>()
+            .map_err(|err| GeocodeError::Parse(err.to_string()))?;
+        Ok(Some(GeoPoint {
+            latitude,
+            longitude,
+            display_name: place.display_name,
+        }))
+    }
+}
+
+fn cache_key(address: &str) -> String {
+    address
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Looks up the cache before asking the provider; misses are cached too.
+pub async fn lookup(
+    pool: &SqlitePool,
+    geocoder: &dyn Geocoder,
+    address: &str,
+) -> Result<Option<GeoPoint>, GeocodeError> {
+    let key = cache_key(address);
+    if key.is_empty() {
+        return Ok(None);
+    }
+
+    let cached = sqlx::query_as::<_, (Option<f64>, Option<f64>, Option<String>)>(
+        "SELECT latitude, longitude, display_name FROM geocode_cache WHERE query = ?",
+    )
+    .bind(&key)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None);
+    if let Some((latitude, longitude, display_name)) = cached {
+        return Ok(latitude.zip(longitude).map(|(latitude, longitude)| GeoPoint {
+            latitude,
+            longitude,
+            display_name,
+        }));
+    }
+
+    let point = geocoder.geocode(address).await?;
+    let _ = sqlx::query(
+        r#"INSERT INTO geocode_cache (query, latitude, longitude, display_name, created_at)
+           VALUES (?, ?, ?, ?, ?)
+           ON CONFLICT(query) DO UPDATE SET
+             latitude = excluded.latitude,
+             longitude = excluded.longitude,
+             display_name = excluded.display_name,
+             created_at = excluded.created_at"#,
+    )
+    .bind(&key)
+    .bind(point.as_ref().map(|point| point.latitude))
+    .bind(point.as_ref().map(|point| point.longitude))
+    .bind(point.as_ref().and_then(|point| point.display_name.clone()))
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(pool)
+    .await;
+
+    Ok(point)
+}
+
+/// Finds a booking's address with the configured geocoder, if there is one.
+pub async fn locate(state: &AppState, address: &str) -> Option<GeoPoint> {
+    let geocoder = state.geocoder.as_ref()?;
+    match lookup(&state.db, geocoder.as_ref(), address).await {
+        Ok(point) => point,
+        Err(err) => {
+            log::warn!("Geocoding a booking address failed: {err}");
+            None
+        }
+    }
+}
+
+/// Geocodes one appointment that has an address but no coordinates.
+pub async fn geocode_appointment(state: &AppState, geocoder: &dyn Geocoder, appointment_id: &str) {
+    let Some(row) = fetch_appointment_event(&state.db, appointment_id).await else {
+        return;
+    };
+    if row.latitude.is_some() && row.longitude.is_some() {
+        return;
+    }
+
+    let _ = sqlx::query("UPDATE appointments SET geocode_attempted_at = ? WHERE id = ?")
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(appointment_id)
+        .execute(&state.db)
+        .await;
+
+    let point = match lookup(&state.db, geocoder, &row.address).await {
+        Ok(Some(point)) => point,
+        Ok(None) => return,
+        Err(err) => {
+            log::warn!("Geocoding appointment {appointment_id} failed: {err}");
+            return;
+        }
+    };
+
+    let coverage = geo::area_coverage(&state.db, Some(point.latitude), Some(point.longitude)).await;
+    let located = sqlx::query(
+        "UPDATE appointments SET latitude = ?, longitude = ?, outside_area = ? WHERE id = ? AND latitude IS NULL",
+    )
+    .bind(point.latitude)
+    .bind(point.longitude)
+    .bind(coverage != Coverage::Inside)
+    .bind(appointment_id)
+    .execute(&state.db)
+    .await;
+    if !matches!(located, Ok(result) if result.rows_affected() > 0) {
+        return;
+    }
+
+    log_activity(
+        &state.db,
+        "appointment_geocoded",
+        &format!("Located {}'s address on the map.", row.client_name),
+        None,
+        Some(appointment_id),
+    )
+    .await;
+    // The booking was taken before its address was known, so it can only be flagged now.
+    if coverage == Coverage::Outside {
+        log_activity(
+            &state.db,
+            "appointment_flagged",
+            &format!("{}'s address turned out to be outside the service areas.", row.client_name),
+            None,
+            Some(appointment_id),
+        )
+        .await;
+    }
+
+    if let Some(row) = fetch_appointment_event(&state.db, appointment_id).await {
+        let _ = state
+            .events
+            .send(ServerEvent::from_row("appointment_updated", row));
+    }
+}
+
+/// Geocodes a freshly created appointment without holding up the booking request.
+pub fn spawn_for_appointment(state: &AppState, appointment_id: &str) {
+    let Some(geocoder) = state.geocoder.clone() else {
+        return;
+    };
+    let state = state.clone();
+    let appointment_id = appointment_id.to_string();
+    actix_web::rt::spawn(async move {
+        geocode_appointment(&state, geocoder.as_ref(), &appointment_id).await;
+    });
+}
+
+/// Periodically fills in coordinates for appointments that only have an address.
+pub fn spawn_backfill(state: &AppState, interval_secs: u64) {
+    let Some(geocoder) = state.geocoder.clone() else {
+        return;
+    };
+    let state = state.clone();
+    actix_web::rt::spawn(async move {
+        loop {
+            backfill(&state, geocoder.clone()).await;
+            tokio::time::sleep(Duration::from_secs(interval_secs.max(1))).await;
+        }
+    });
+}
+
+async fn backfill(state: &AppState, geocoder: Arc<dyn Geocoder>) {
+    let pending = sqlx::query_scalar::<_, String>(
+        r#"SELECT a.id
+           FROM appointments a
+           WHERE (a.latitude IS NULL OR a.longitude IS NULL)
+             AND trim(a.address) != ''
+             AND a.geocode_attempted_at IS NULL
+           ORDER BY a.requested_at DESC
+           LIMIT ?"#,
+    )
+    .bind(BACKFILL_BATCH)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    for appointment_id in pending {
+        geocode_appointment(state, geocoder.as_ref(), &appointment_id).await;
+        tokio::time::sleep(Duration::from_millis(REQUEST_SPACING_MS)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use super::*;
+    use crate::db::test_pool;
+
+    /// Answers from a fixed table of addresses and remembers every address it was asked about.
+    #[derive(Default)]
+    struct StubGeocoder {
+        places: HashMap<String, GeoPoint>,
+        asked: Mutex<Vec<String>>,
+    }
+
+    impl StubGeocoder {
+        fn with_place(address: &str, latitude: f64, longitude: f64) -> Self {
+            let point = GeoPoint {
+                latitude,
+                longitude,
+                display_name: Some(address.to_string()),
+            };
+            Self {
+                places: HashMap::from([(address.to_string(), point)]),
+                asked: Mutex::default(),
+            }
+        }
+
+        fn asked(&self) -> Vec<String> {
+            self.asked.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Geocoder for StubGeocoder {
+        async fn geocode(&self, address: &str) -> Result<Option<GeoPoint>, GeocodeError> {
+            self.asked.lock().unwrap().push(address.to_string());
+            Ok(self.places.get(address).cloned())
+        }
+    }
+
+    async fn insert_appointment(pool: &SqlitePool, id: &str, address: &str, point: Option<(f64, f64)>) {
+        sqlx::query(
+            r#"INSERT INTO appointments
+               (id, client_name, client_phone, address, service, requested_at, scheduled_for, status, latitude, longitude)
+               VALUES (?, 'Ana', '+385911234567', ?, 'Signature Cut', '2026-01-01T09:00:00Z', '2026-01-02T09:00:00Z', 'pending', ?, ?)"#,
+        )
+        .bind(id)
+        .bind(address)
+        .bind(point.map(|(lat, _)| lat))
+        .bind(point.map(|(_, lon)| lon))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn coordinates(pool: &SqlitePool, id: &str) -> (Option<f64>, Option<f64>, Option<String>) {
+        sqlx::query_as("SELECT latitude, longitude, geocode_attempted_at FROM appointments WHERE id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn lookup_asks_the_provider_once_per_address() {
+        let pool = test_pool().await;
+        let geocoder = StubGeocoder::with_place("Ilica 1, Zagreb", 45.81, 15.97);
+
+        let first = lookup(&pool, &geocoder, "Ilica 1, Zagreb").await.unwrap().unwrap();
+        let again = lookup(&pool, &geocoder, "  ilica 1,   ZAGREB ").await.unwrap().unwrap();
+
+        assert_eq!((first.latitude, first.longitude), (45.81, 15.97));
+        assert_eq!((again.latitude, again.longitude), (45.81, 15.97));
+        assert_eq!(again.display_name.as_deref(), Some("Ilica 1, Zagreb"));
+        assert_eq!(geocoder.asked(), ["Ilica 1, Zagreb"]);
+    }
+
+    #[tokio::test]
+    async fn lookup_caches_addresses_the_provider_could_not_find() {
+        let pool = test_pool().await;
+        let geocoder = StubGeocoder::default();
+
+        assert!(lookup(&pool, &geocoder, "Nowhere 99").await.unwrap().is_none());
+        assert!(lookup(&pool, &geocoder, "nowhere 99").await.unwrap().is_none());
+
+        assert_eq!(geocoder.asked(), ["Nowhere 99"]);
+        let cached: (Option<f64>, Option<f64>) =
+            sqlx::query_as("SELECT latitude, longitude FROM geocode_cache WHERE query = 'nowhere 99'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(cached, (None, None));
+    }
+
+    #[tokio::test]
+    async fn lookup_skips_blank_addresses() {
+        let pool = test_pool().await;
+        let geocoder = StubGeocoder::default();
+
+        assert!(lookup(&pool, &geocoder, "   ").await.unwrap().is_none());
+        assert!(geocoder.asked().is_empty());
+    }
+
+    #[tokio::test]
+    async fn geocode_appointment_fills_in_coordinates() {
+        let state = AppState::for_tests(test_pool().await);
+        let geocoder = StubGeocoder::with_place("Ilica 1, Zagreb", 45.81, 15.97);
+        insert_appointment(&state.db, "a1", "Ilica 1, Zagreb", None).await;
+
+        geocode_appointment(&state, &geocoder, "a1").await;
+
+        let (latitude, longitude, attempted_at) = coordinates(&state.db, "a1").await;
+        assert_eq!((latitude, longitude), (Some(45.81), Some(15.97)));
+        assert!(attempted_at.is_some());
+        let logged: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM activities WHERE kind = 'appointment_geocoded' AND appointment_id = 'a1'")
+                .fetch_one(&state.db)
+                .await
+                .unwrap();
+        assert_eq!(logged, 1);
+    }
+
+    #[tokio::test]
+    async fn geocode_appointment_records_the_attempt_when_nothing_is_found() {
+        let state = AppState::for_tests(test_pool().await);
+        let geocoder = StubGeocoder::default();
+        insert_appointment(&state.db, "a1", "Nowhere 99", None).await;
+
+        geocode_appointment(&state, &geocoder, "a1").await;
+
+        let (latitude, longitude, attempted_at) = coordinates(&state.db, "a1").await;
+        assert_eq!((latitude, longitude), (None, None));
+        assert!(attempted_at.is_some());
+        assert_eq!(geocoder.asked(), ["Nowhere 99"]);
+    }
+
+    #[tokio::test]
+    async fn geocode_appointment_leaves_pinned_locations_alone() {
+        let state = AppState::for_tests(test_pool().await);
+        let geocoder = StubGeocoder::with_place("Ilica 1, Zagreb", 45.81, 15.97);
+        insert_appointment(&state.db, "a1", "Ilica 1, Zagreb", Some((45.0, 16.0))).await;
+
+        geocode_appointment(&state, &geocoder, "a1").await;
+
+        let (latitude, longitude, attempted_at) = coordinates(&state.db, "a1").await;
+        assert_eq!((latitude, longitude), (Some(45.0), Some(16.0)));
+        assert!(attempted_at.is_none());
+        assert!(geocoder.asked().is_empty());
+    }
+
+    #[tokio::test]
+    async fn geocode_appointment_flags_addresses_outside_the_service_areas() {
+        let state = AppState::for_tests(test_pool().await);
+        sqlx::query(
+            r#"INSERT INTO service_areas (id, name, kind, center_latitude, center_longitude, radius_km, active, created_at)
+               VALUES ('z1', 'Zagreb', 'radius', 45.81, 15.97, 10.0, 1, '2026-01-01T00:00:00Z')"#,
+        )
+        .execute(&state.db)
+        .await
+        .unwrap();
+        let geocoder = StubGeocoder::with_place("Riva 1, Split", 43.51, 16.44);
+        insert_appointment(&state.db, "a1", "Riva 1, Split", None).await;
+
+        geocode_appointment(&state, &geocoder, "a1").await;
+
+        let outside: bool = sqlx::query_scalar("SELECT outside_area FROM appointments WHERE id = 'a1'")
+            .fetch_one(&state.db)
+            .await
+            .unwrap();
+        assert!(outside);
+        let flagged: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM activities WHERE kind = 'appointment_flagged' AND appointment_id = 'a1'")
+                .fetch_one(&state.db)
+                .await
+                .unwrap();
+        assert_eq!(flagged, 1);
+    }
+
+    #[tokio::test]
+    async fn locate_needs_a_geocoder() {
+        let mut state = AppState::for_tests(test_pool().await);
+        assert!(locate(&state, "Ilica 1, Zagreb").await.is_none());
+
+        state.geocoder = Some(Arc::new(StubGeocoder::with_place("Ilica 1, Zagreb", 45.81, 15.97)));
+        let point = locate(&state, "Ilica 1, Zagreb").await.unwrap();
+        assert_eq!((point.latitude, point.longitude), (45.81, 15.97));
+    }
+}
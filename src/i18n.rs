@@ -0,0 +1,156 @@
+use std::{collections::HashMap, fmt::Display, sync::LazyLock};
+
+use actix_web::{
+    cookie::{time::Duration, Cookie, SameSite},
+    http::header,
+    HttpRequest,
+};
+use sqlx::SqlitePool;
+
+pub const LOCALE_COOKIE: &str = "b2g_locale";
+
+/// Messages the public scripts show, shipped to the browser with each page.
+const CLIENT_MESSAGES: [&str; 17] = [
+    "Enable notifications",
+    "Notifications unavailable",
+    "Notifications blocked",
+    "Notifications enabled",
+    "Set VAPID keys and use HTTPS or localhost to enable notifications.",
+    "This browser does not support push notifications.",
+    "Allow notifications in your browser settings.",
+    "You'll receive status updates on this device.",
+    "We couldn't access the push service.",
+    "No preference",
+    "Unassigned",
+    "pending",
+    "accepted",
+    "declined",
+    "completed",
+    "cancelled",
+    "no_show",
+];
+
+/// Catalogs map the English source text to its translation; English needs none.
+static CATALOGS: LazyLock<HashMap<Locale, HashMap<String, String>>> = LazyLock::new(|| {
+    [(Locale::Hr, include_str!("../locales/hr.json"))]
+        .into_iter()
+        .map(|(locale, raw)| {
+            let catalog = serde_json::from_str(raw).unwrap_or_else(|err| {
+                log::error!("Translation catalog {} is invalid: {err}", locale.code());
+                HashMap::new()
+            });
+            (locale, catalog)
+        })
+        .collect()
+});
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[default]
+    En,
+    Hr,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Hr];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Hr => "hr",
+        }
+    }
+
+    /// The language's own name, for the switcher.
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Hr => "Hrvatski",
+        }
+    }
+
+    /// Accepts a bare language or a tag with a region, e.g. "hr" or "hr-HR".
+    pub fn parse(value: &str) -> Option<Self> {
+        let language = value.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|locale| locale.code() == language)
+    }
+
+    /// The switcher's cookie wins, then the browser's `Accept-Language`, then English.
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        if let Some(locale) = req.cookie(LOCALE_COOKIE).and_then(|cookie| Self::parse(cookie.value())) {
+            return locale;
+        }
+        req.headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(from_accept_language)
+            .unwrap_or_default()
+    }
+
+    /// The translation of `text`, or `text` itself when the catalog has none.
+    pub fn t(self, text: &str) -> &str {
+        CATALOGS
+            .get(&self)
+            .and_then(|catalog| catalog.get(text))
+            .map(String::as_str)
+            .unwrap_or(text)
+    }
+
+    /// Translates `text` and fills in its `{name}` placeholders.
+    pub fn format(self, text: &str, args: &[(&str, &dyn Display)]) -> String {
+        let mut message = self.t(text).to_string();
+        for (name, value) in args {
+            message = message.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        message
+    }
+
+    /// `CLIENT_MESSAGES` as a JSON object for the page scripts.
+    pub fn client_messages(self) -> String {
+        let messages: HashMap<&str, &str> = CLIENT_MESSAGES.iter().map(|text| (*text, self.t(text))).collect();
+        serde_json::to_string(&messages)
+            .unwrap_or_default()
+            .replace("</", "<\\/")
+    }
+}
+
+/// Picks the supported language the browser ranks highest, e.g. "hr-HR,hr;q=0.9,en;q=0.8".
+fn from_accept_language(header: &str) -> Option<Locale> {
+    let mut ranked: Vec<(f32, Locale)> = header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let locale = Locale::parse(parts.next()?)?;
+            let quality = parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .and_then(|value| value.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (quality > 0.0).then_some((quality, locale))
+        })
+        .collect();
+    // Stable, so equally ranked languages keep the browser's order.
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    ranked.first().map(|(_, locale)| *locale)
+}
+
+pub fn locale_cookie(req: &HttpRequest, locale: Locale) -> Cookie<'static> {
+    let mut builder = Cookie::build(LOCALE_COOKIE, locale.code())
+        .path("/")
+        .same_site(SameSite::Lax)
+        .max_age(Duration::days(365));
+    if req.connection_info().scheme() == "https" {
+        builder = builder.secure(true);
+    }
+    builder.finish()
+}
+
+/// The language the client booked in, for notifications about the visit.
+pub async fn appointment_locale(pool: &SqlitePool, appointment_id: &str) -> Locale {
+    sqlx::query_scalar::<_, String>("SELECT locale FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .and_then(|code| Locale::parse(&code))
+        .unwrap_or_default()
+}
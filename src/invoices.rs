@@ -0,0 +1,417 @@
+use chrono::Utc;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    db::{fetch_appointment_event, log_activity},
+    i18n::{self, Locale},
+    line_items,
+    messaging::{Channel, OutboundMessage},
+    models::{
+        format_money, service_price_cents, AppointmentRow, InvoiceLineRow, InvoiceRow, CURRENCY_CODE, STATUS_COMPLETED,
+    },
+    payments, payouts, pricing,
+    state::{AppState, InvoiceConfig},
+};
+
+const INVOICE_COLUMNS: &str = r#"id, number, appointment_id, client_name, client_email, address, barber_name,
+    subtotal_cents, tax_rate, tax_cents, tip_cents, total_cents, paid_cents, issued_at"#;
+
+impl InvoiceConfig {
+    /// Sequential number with the configured prefix, e.g. "B2G-000042".
+    pub fn display_number(&self, number: i64) -> String {
+        format!("{}{number:06}", self.prefix)
+    }
+
+    /// The tax share of a tax-inclusive amount.
+    pub fn tax_included(&self, gross_cents: i64) -> i64 {
+        if self.tax_rate <= 0.0 {
+            return 0;
+        }
+        (gross_cents as f64 * self.tax_rate / (100.0 + self.tax_rate)).round() as i64
+    }
+
+    /// `INVOICE_BUSINESS_DETAILS` lines may be separated by newlines or semicolons.
+    pub fn detail_lines(&self) -> Vec<String> {
+        self.business_details
+            .as_deref()
+            .unwrap_or_default()
+            .split(['\n', ';'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+pub struct TotalLine {
+    pub label: String,
+    pub amount_cents: i64,
+    pub strong: bool,
+}
+
+/// The summary under the line items, shared by the HTML and PDF receipts.
+pub fn totals(config: &InvoiceConfig, invoice: &InvoiceRow, locale: Locale) -> Vec<TotalLine> {
+    let line = |label: &str, amount_cents: i64, strong: bool| TotalLine {
+        label: locale.t(label).to_string(),
+        amount_cents,
+        strong,
+    };
+    let mut totals = vec![line("Subtotal", invoice.subtotal_cents, false)];
+    if invoice.tax_cents > 0 {
+        let label = locale.format(
+            "incl. {tax} {rate}",
+            &[("tax", &config.tax_label), ("rate", &format_rate(invoice.tax_rate))],
+        );
+        totals.push(TotalLine {
+            label,
+            amount_cents: invoice.tax_cents,
+            strong: false,
+        });
+    }
+    if invoice.tip_cents > 0 {
+        totals.push(line("Tip", invoice.tip_cents, false));
+    }
+    totals.push(line("Total", invoice.total_cents, true));
+    if invoice.paid_cents > 0 {
+        totals.push(line("Paid online", invoice.paid_cents, false));
+        // Tips are handed to the barber directly, so they never count towards what is still owed.
+        let due = invoice.subtotal_cents - invoice.paid_cents;
+        if due > 0 {
+            totals.push(line("Balance due", due, true));
+        }
+    }
+    totals
+}
+
+pub fn issued_on(invoice: &InvoiceRow) -> &str {
+    invoice.issued_at.get(..10).unwrap_or(&invoice.issued_at)
+}
+
+/// Formats a stored rate without trailing zeros, e.g. "25%" or "9.5%".
+fn format_rate(rate: f64) -> String {
+    format!("{}%", (rate * 100.0).round() / 100.0)
+}
+
+pub async fn fetch_invoice(pool: &SqlitePool, appointment_id: &str) -> Option<InvoiceRow> {
+    sqlx::query_as::<_, InvoiceRow>(&format!(
+        "SELECT {INVOICE_COLUMNS} FROM invoices WHERE appointment_id = ?"
+    ))
+    .bind(appointment_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+pub async fn fetch_lines(pool: &SqlitePool, invoice_id: &str) -> Vec<InvoiceLineRow> {
+    sqlx::query_as::<_, InvoiceLineRow>(
+        "SELECT description, amount_cents FROM invoice_lines WHERE invoice_id = ? ORDER BY position",
+    )
+    .bind(invoice_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn list_invoices(pool: &SqlitePool) -> Vec<InvoiceRow> {
+    sqlx::query_as::<_, InvoiceRow>(&format!(
+        "SELECT {INVOICE_COLUMNS} FROM invoices ORDER BY number DESC LIMIT 500"
+    ))
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// What the client is billed for: every person's service, the add-ons and the travel surcharge.
+async fn billable_lines(pool: &SqlitePool, appointment_id: &str, fallback_service: &str) -> Vec<(String, i64)> {
+    let items = line_items::fetch_items(pool, appointment_id).await;
+    let party = items.len() > 1;
+    let mut lines: Vec<(String, i64)> = items
+        .iter()
+        .map(|item| {
+            let description = if party {
+                format!("{} — {}", item.service, item.person_name)
+            } else {
+                item.service.clone()
+            };
+            let amount = item.price_cents.unwrap_or_else(|| service_price_cents(&item.service));
+            (description, amount)
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push((fallback_service.to_string(), service_price_cents(fallback_service)));
+    }
+
+    let addons = sqlx::query_as::<_, (String, i64)>(
+        "SELECT name, price_cents FROM appointment_addons WHERE appointment_id = ? ORDER BY name",
+    )
+    .bind(appointment_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+    lines.extend(addons);
+
+    if let Some(quote) = pricing::fetch_quote(pool, appointment_id).await
+        && quote.travel_cents > 0
+    {
+        let description = match quote.travel_km {
+            Some(km) => format!("Travel surcharge ({km:.1} km)"),
+            None => "Travel surcharge".to_string(),
+        };
+        lines.push((description, quote.travel_cents));
+    }
+    lines
+}
+
+/// Takes the next number and stores the receipt in one transaction, so numbers never skip.
+async fn save(
+    state: &AppState,
+    row: &AppointmentRow,
+    lines: &[(String, i64)],
+    tip_cents: i64,
+    paid_cents: i64,
+) -> Result<i64, sqlx::Error> {
+    let invoice_id = new_id();
+    let subtotal_cents: i64 = lines.iter().map(|(_, amount)| amount).sum();
+    let mut tx = state.db.begin().await?;
+    let number = sqlx::query_scalar::<_, i64>(
+        "UPDATE invoice_sequence SET next_number = next_number + 1 WHERE id = 1 RETURNING next_number - 1",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"INSERT INTO invoices
+           (id, number, appointment_id, client_name, client_email, address, barber_name,
+            subtotal_cents, tax_rate, tax_cents, tip_cents, total_cents, paid_cents, currency, issued_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&invoice_id)
+    .bind(number)
+    .bind(&row.id)
+    .bind(&row.client_name)
+    .bind(&row.client_email)
+    .bind(&row.address)
+    .bind(&row.barber_name)
+    .bind(subtotal_cents)
+    .bind(state.invoices.tax_rate)
+    .bind(state.invoices.tax_included(subtotal_cents))
+    .bind(tip_cents)
+    .bind(subtotal_cents + tip_cents)
+    .bind(paid_cents)
+    .bind(CURRENCY_CODE)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&mut *tx)
+    .await?;
+    for (position, (description, amount_cents)) in lines.iter().enumerate() {
+        sqlx::query(
+            r#"INSERT INTO invoice_lines (id, invoice_id, position, description, amount_cents)
+               VALUES (?, ?, ?, ?, ?)"#,
+        )
+        .bind(new_id())
+        .bind(&invoice_id)
+        .bind(position as i64)
+        .bind(description)
+        .bind(amount_cents)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(number)
+}
+
+/// Issues the receipt for a completed appointment and sends the client a link to it.
+/// Does nothing for other statuses or when the appointment already has one, so it is safe to call
+/// after every status change.
+pub async fn issue(state: &AppState, appointment_id: &str) {
+    let Some(row) = fetch_appointment_event(&state.db, appointment_id).await else {
+        return;
+    };
+    if row.status != STATUS_COMPLETED || fetch_invoice(&state.db, appointment_id).await.is_some() {
+        return;
+    }
+
+    let lines = billable_lines(&state.db, appointment_id, &row.service).await;
+    let subtotal_cents: i64 = lines.iter().map(|(_, amount)| amount).sum();
+    let tip_cents = payouts::fetch_tip(&state.db, appointment_id).await;
+    let paid_cents = payments::net_paid(&state.db, appointment_id).await.max(0);
+
+    let result = save(state, &row, &lines, tip_cents, paid_cents).await;
+    let number = match result {
+        Ok(number) => state.invoices.display_number(number),
+        Err(err) => {
+            log::warn!("Issuing invoice for appointment {appointment_id} failed: {err}");
+            return;
+        }
+    };
+    log_activity(
+        &state.db,
+        "invoice_issued",
+        &format!("Invoice {number} issued to {}", row.client_name),
+        None,
+        Some(appointment_id),
+    )
+    .await;
+
+    let (channel, to) = match row.client_email.as_deref().filter(|email| !email.trim().is_empty()) {
+        Some(email) => (Channel::Email, email.to_string()),
+        None => (Channel::Sms, row.client_phone.clone()),
+    };
+    let Some(base_url) = state.portal.base_url.as_deref() else {
+        log::error!("PUBLIC_BASE_URL is not set, so invoice {number} wasn't sent to the client");
+        return;
+    };
+    let locale = i18n::appointment_locale(&state.db, appointment_id).await;
+    let message = OutboundMessage {
+        channel,
+        to,
+        subject: locale.format("Your Barber2Go receipt {number}", &[("number", &number)]),
+        body: locale.format(
+            "Hi {name}, thanks for your visit. Your receipt {number} for {amount} is here: {link}",
+            &[
+                ("name", &row.client_name),
+                ("number", &number),
+                ("amount", &format_money(subtotal_cents + tip_cents)),
+                ("link", &format!("{base_url}/status/{appointment_id}/invoice")),
+            ],
+        ),
+    };
+    if let Err(err) = state.messages.send(&message).await {
+        log::warn!("Sending invoice {number} failed: {err}");
+    }
+}
+
+/// The standard PDF fonts only cover Windows-1252, which is enough for most names and the euro sign.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| match ch {
+            '€' => 0x80,
+            '—' => 0x97,
+            '–' => 0x96,
+            '·' => 0xB7,
+            'Š' => 0x8A,
+            'š' => 0x9A,
+            'Ž' => 0x8E,
+            'ž' => 0x9E,
+            // Letters outside the code page fall back to their base letter.
+            'Č' | 'Ć' => b'C',
+            'č' | 'ć' => b'c',
+            'Đ' => b'D',
+            'đ' => b'd',
+            ch if (ch as u32) < 0x80 || (0xA0..=0xFF).contains(&(ch as u32)) => ch as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Approximate Helvetica advance width, good enough to right-align amounts.
+fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|ch| match ch {
+            '.' | ',' | ' ' => 278,
+            '-' => 333,
+            '%' => 889,
+            _ => 556,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+fn show(content: &mut Content, font: Name, size: f32, x: f32, y: f32, value: &str) {
+    content
+        .begin_text()
+        .set_font(font, size)
+        .next_line(x, y)
+        .show(Str(&win_ansi(value)))
+        .end_text();
+}
+
+fn show_right(content: &mut Content, font: Name, size: f32, right: f32, y: f32, value: &str) {
+    show(content, font, size, right - text_width(value, size), y, value);
+}
+
+pub fn render_pdf(config: &InvoiceConfig, invoice: &InvoiceRow, lines: &[InvoiceLineRow]) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let regular_id = Ref::new(4);
+    let bold_id = Ref::new(5);
+    let content_id = Ref::new(6);
+    let regular = Name(b"F1");
+    let bold = Name(b"F2");
+
+    const LEFT: f32 = 56.0;
+    const RIGHT: f32 = 539.0;
+
+    let mut content = Content::new();
+    let mut y = 780.0;
+    show(&mut content, bold, 20.0, LEFT, y, &config.business_name);
+    for line in config.detail_lines() {
+        y -= 14.0;
+        show(&mut content, regular, 10.0, LEFT, y, &line);
+    }
+
+    y -= 40.0;
+    let title = format!("Receipt {}", config.display_number(invoice.number));
+    show(&mut content, bold, 14.0, LEFT, y, &title);
+    y -= 18.0;
+    show(&mut content, regular, 10.0, LEFT, y, &format!("Issued {}", issued_on(invoice)));
+    y -= 28.0;
+    show(&mut content, bold, 10.0, LEFT, y, "Billed to");
+    show(&mut content, bold, 10.0, 320.0, y, "Barber");
+    y -= 14.0;
+    show(&mut content, regular, 10.0, LEFT, y, &invoice.client_name);
+    let barber = invoice.barber_name.as_deref().unwrap_or("Barber2Go team");
+    show(&mut content, regular, 10.0, 320.0, y, barber);
+    if let Some(email) = invoice.client_email.as_deref().filter(|email| !email.is_empty()) {
+        y -= 14.0;
+        show(&mut content, regular, 10.0, LEFT, y, email);
+    }
+    y -= 14.0;
+    show(&mut content, regular, 10.0, LEFT, y, &invoice.address);
+
+    y -= 36.0;
+    show(&mut content, bold, 11.0, LEFT, y, "Description");
+    show_right(&mut content, bold, 11.0, RIGHT, y, "Amount");
+    y -= 8.0;
+    content.set_line_width(0.5).move_to(LEFT, y).line_to(RIGHT, y).stroke();
+    for line in lines {
+        y -= 18.0;
+        show(&mut content, regular, 11.0, LEFT, y, &line.description);
+        show_right(&mut content, regular, 11.0, RIGHT, y, &format_money(line.amount_cents));
+    }
+    y -= 10.0;
+    content.set_line_width(0.5).move_to(LEFT, y).line_to(RIGHT, y).stroke();
+
+    // The built-in PDF fonts only cover Latin-1, so the PDF stays in English.
+    for total in totals(config, invoice, Locale::En) {
+        let font = if total.strong { bold } else { regular };
+        y -= 18.0;
+        show(&mut content, font, 11.0, 340.0, y, &total.label);
+        show_right(&mut content, font, 11.0, RIGHT, y, &format_money(total.amount_cents));
+    }
+
+    y -= 48.0;
+    show(&mut content, regular, 10.0, LEFT, y, "Thank you for choosing Barber2Go.");
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids([page_id]).count(1);
+    let mut page = pdf.page(page_id);
+    page.media_box(Rect::new(0.0, 0.0, 595.0, 842.0));
+    page.parent(page_tree_id);
+    page.contents(content_id);
+    let mut resources = page.resources();
+    resources.fonts().pair(regular, regular_id).pair(bold, bold_id);
+    resources.finish();
+    page.finish();
+    pdf.type1_font(regular_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.stream(content_id, &content.finish());
+    pdf.finish()
+}
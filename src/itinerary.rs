@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    geo::haversine_km,
+    models::{service_minutes, AppointmentRow},
+    schedule::{self, parse_scheduled_for},
+};
+
+/// Straight-line distance undercounts real streets; this is a rough urban detour factor.
+const ROAD_FACTOR: f64 = 1.3;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Stop {
+    pub appointment_id: String,
+    pub client_name: String,
+    pub address: String,
+    pub service: String,
+    pub scheduled_for: String,
+    pub duration_minutes: i64,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Leg {
+    pub from_id: String,
+    pub to_id: String,
+    pub distance_km: Option<f64>,
+    pub drive_minutes: Option<i64>,
+    pub gap_minutes: Option<i64>,
+    pub feasible: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Itinerary {
+    pub date: String,
+    pub stops: Vec<Stop>,
+    pub legs: Vec<Leg>,
+    pub total_distance_km: f64,
+}
+
+impl Itinerary {
+    /// Orders stops by start time and checks each leg against the gap after the previous visit.
+    /// Stop times are written out in `zone`.
+    pub fn build(
+        date: &str,
+        rows: Vec<AppointmentRow>,
+        durations: &HashMap<String, i64>,
+        average_speed_kmh: f64,
+        zone: Tz,
+    ) -> Self {
+        let mut timed: Vec<(Option<DateTime<Utc>>, Stop)> = rows
+            .into_iter()
+            .map(|row| {
+                let start = parse_scheduled_for(&row.scheduled_for);
+                let stop = Stop {
+                    duration_minutes: durations
+                        .get(&row.id)
+                        .copied()
+                        .unwrap_or_else(|| service_minutes(&row.service)),
+                    appointment_id: row.id,
+                    client_name: row.client_name,
+                    address: row.address,
+                    service: row.service,
+                    scheduled_for: schedule::display(&row.scheduled_for, zone),
+                    latitude: row.latitude,
+                    longitude: row.longitude,
+                };
+                (start, stop)
+            })
+            .collect();
+        timed.sort_by_key(|(start, _)| *start);
+
+        let mut legs = Vec::new();
+        let mut total_distance_km = 0.0;
+        for pair in timed.windows(2) {
+            let (from_start, from) = &pair[0];
+            let (to_start, to) = &pair[1];
+            let distance_km = match (from.latitude.zip(from.longitude), to.latitude.zip(to.longitude)) {
+                (Some(a), Some(b)) => Some(haversine_km(a, b) * ROAD_FACTOR),
+                _ => None,
+            };
+            let drive_minutes = distance_km
+                .filter(|_| average_speed_kmh > 0.0)
+                .map(|km| (km / average_speed_kmh * 60.0).ceil() as i64);
+            let gap_minutes = match (from_start, to_start) {
+                (Some(from_start), Some(to_start)) => {
+                    Some((*to_start - *from_start).num_minutes() - from.duration_minutes)
+                }
+                _ => None,
+            };
+            let feasible = match (drive_minutes, gap_minutes) {
+                (Some(drive), Some(gap)) => drive <= gap,
+                (None, Some(gap)) => gap >= 0,
+                _ => true,
+            };
+            total_distance_km += distance_km.unwrap_or_default();
+            legs.push(Leg {
+                from_id: from.appointment_id.clone(),
+                to_id: to.appointment_id.clone(),
+                distance_km,
+                drive_minutes,
+                gap_minutes,
+                feasible,
+            });
+        }
+
+        Self {
+            date: date.to_string(),
+            stops: timed.into_iter().map(|(_, stop)| stop).collect(),
+            legs,
+            total_distance_km,
+        }
+    }
+
+    fn located_stops(&self) -> impl Iterator<Item = (&Stop, f64, f64)> {
+        self.stops
+            .iter()
+            .filter_map(|stop| Some((stop, stop.latitude?, stop.longitude?)))
+    }
+
+    pub fn to_geojson(&self) -> serde_json::Value {
+        let mut features: Vec<serde_json::Value> = self
+            .located_stops()
+            .enumerate()
+            .map(|(index, (stop, lat, lon))| {
+                json!({
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": [lon, lat] },
+                    "properties": {
+                        "order": index + 1,
+                        "appointment_id": stop.appointment_id,
+                        "client_name": stop.client_name,
+                        "address": stop.address,
+                        "service": stop.service,
+                        "scheduled_for": stop.scheduled_for,
+                    }
+                })
+            })
+            .collect();
+
+        let line: Vec<[f64; 2]> = self.located_stops().map(|(_, lat, lon)| [lon, lat]).collect();
+        if line.len() > 1 {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": line },
+                "properties": {
+                    "date": self.date,
+                    "distance_km": self.total_distance_km,
+                }
+            }));
+        }
+
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+
+    pub fn to_gpx(&self) -> String {
+        let mut gpx = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\" creator=\"Barber2Go\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+        );
+        for (stop, lat, lon) in self.located_stops() {
+            gpx.push_str(&format!(
+                "  <wpt lat=\"{lat}\" lon=\"{lon}\">\n    <name>{}</name>\n    <desc>{}</desc>\n  </wpt>\n",
+                xml_escape(&format!("{} · {}", stop.scheduled_for, stop.client_name)),
+                xml_escape(&format!("{} — {}", stop.service, stop.address)),
+            ));
+        }
+        gpx.push_str(&format!("  <rte>\n    <name>Route {}</name>\n", xml_escape(&self.date)));
+        for (stop, lat, lon) in self.located_stops() {
+            gpx.push_str(&format!(
+                "    <rtept lat=\"{lat}\" lon=\"{lon}\"><name>{}</name></rtept>\n",
+                xml_escape(&stop.client_name),
+            ));
+        }
+        gpx.push_str("  </rte>\n</gpx>\n");
+        gpx
+    }
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    models::{service_catalog, service_minutes, service_price_cents, AppointmentItemRow, AppointmentRow},
+};
+
+/// People that can be added on top of the person booking.
+pub const MAX_GUESTS: usize = 5;
+
+pub struct NewItem {
+    pub person_name: String,
+    pub service: String,
+}
+
+pub fn is_known_service(name: &str) -> bool {
+    service_catalog().iter().any(|service| service.name == name)
+}
+
+pub async fn fetch_items(pool: &SqlitePool, appointment_id: &str) -> Vec<AppointmentItemRow> {
+    sqlx::query_as::<_, AppointmentItemRow>(
+        r#"SELECT i.id, i.person_name, i.service, i.duration_minutes, i.barber_id,
+                  u.display_name as barber_name, i.price_cents
+           FROM appointment_items i
+           LEFT JOIN users u ON i.barber_id = u.id
+           WHERE i.appointment_id = ?
+           ORDER BY i.position"#,
+    )
+    .bind(appointment_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// Replaces the appointment's line items and stores the resulting duration.
+pub async fn save_items(pool: &SqlitePool, appointment_id: &str, items: &[NewItem]) -> Result<(), sqlx::Error> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM appointment_items WHERE appointment_id = ?")
+        .bind(appointment_id)
+        .execute(&mut *tx)
+        .await?;
+    for (position, item) in items.iter().enumerate() {
+        sqlx::query(
+            r#"INSERT INTO appointment_items
+               (id, appointment_id, position, person_name, service, duration_minutes, price_cents, barber_id, created_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, NULL, ?)"#,
+        )
+        .bind(new_id())
+        .bind(appointment_id)
+        .bind(position as i64)
+        .bind(item.person_name.trim())
+        .bind(&item.service)
+        .bind(service_minutes(&item.service))
+        .bind(service_price_cents(&item.service))
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    recompute_duration(pool, appointment_id).await
+}
+
+/// Hands one person's service to a specific barber; `None` leaves it with the appointment's barber.
+pub async fn assign_barber(
+    pool: &SqlitePool,
+    appointment_id: &str,
+    item_id: &str,
+    barber_id: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE appointment_items SET barber_id = ? WHERE id = ? AND appointment_id = ?")
+        .bind(barber_id)
+        .bind(item_id)
+        .bind(appointment_id)
+        .execute(pool)
+        .await?;
+    recompute_duration(pool, appointment_id).await
+}
+
+/// Barbers work in parallel, so the visit lasts as long as the busiest barber's share.
+pub fn chair_minutes(items: &[AppointmentItemRow], appointment_barber: Option<&str>) -> i64 {
+    let mut per_barber: HashMap<Option<&str>, i64> = HashMap::new();
+    for item in items {
+        let barber = item.barber_id.as_deref().or(appointment_barber);
+        *per_barber.entry(barber).or_default() += item.minutes();
+    }
+    per_barber.into_values().max().unwrap_or(0)
+}
+
+pub async fn recompute_duration(pool: &SqlitePool, appointment_id: &str) -> Result<(), sqlx::Error> {
+    let barber_id = sqlx::query_scalar::<_, Option<String>>("SELECT barber_id FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+    let items = fetch_items(pool, appointment_id).await;
+    let minutes = Some(chair_minutes(&items, barber_id.as_deref())).filter(|minutes| *minutes > 0);
+    sqlx::query("UPDATE appointments SET duration_minutes = ? WHERE id = ?")
+        .bind(minutes)
+        .bind(appointment_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Stored duration per appointment, falling back to the catalog for bookings that predate line items.
+pub async fn durations(pool: &SqlitePool, rows: &[AppointmentRow]) -> HashMap<String, i64> {
+    let mut durations = HashMap::new();
+    for row in rows {
+        let stored = sqlx::query_scalar::<_, Option<i64>>("SELECT duration_minutes FROM appointments WHERE id = ?")
+            .bind(&row.id)
+            .fetch_optional(pool)
+            .await
+            .unwrap_or(None)
+            .flatten();
+        durations.insert(row.id.clone(), stored.unwrap_or_else(|| service_minutes(&row.service)));
+    }
+    durations
+}
@@ -1,20 +1,56 @@
 mod auth;
+mod clients;
+mod cms;
 mod db;
+mod dispatch;
 mod filters;
+mod geo;
+mod geocode;
+mod i18n;
+mod invoices;
+mod itinerary;
+mod line_items;
+mod media;
+mod messaging;
 mod models;
+mod pages;
+mod payments;
+mod payouts;
+mod policy;
+mod pricing;
+mod profiles;
 mod push;
+mod recurrence;
+mod reviews;
 mod routes;
+mod sanitize;
+mod schedule;
 mod state;
 mod templates;
+mod uploads;
+mod validation;
+mod waitlist;
 
 use actix_files::Files;
+use actix_multipart::form::MultipartFormConfig;
 use actix_web::{middleware, web, App, HttpServer};
 use actix_web_httpauth::extractors::basic;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use std::env;
 use std::str::FromStr;
+use std::sync::Arc;
 
-use crate::{auth::AUTH_REALM, state::{AppState, PushConfig}};
+use crate::{
+    auth::AUTH_REALM,
+    geocode::{Geocoder, NominatimGeocoder},
+    messaging::LogTransport,
+    payments::{FakePaymentProvider, PaymentProvider, StripeProvider},
+    state::{
+        AppState, BookingConfig, DepositConfig, DispatchConfig, InvoiceConfig, PayoutConfig, Penalty,
+        PolicyConfig, PortalConfig, PricingConfig, PushConfig, RouteConfig, ServiceAreaConfig, TimeConfig,
+        UploadConfig, WaitlistConfig,
+    },
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -40,8 +76,30 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
         .connect_with(connect_options)
         .await?;
 
+    let zone_name = env::var("BUSINESS_TIMEZONE").unwrap_or_else(|_| "UTC".to_string());
+    let time = TimeConfig {
+        business_zone: schedule::parse_zone(&zone_name)
+            .ok_or_else(|| format!("BUSINESS_TIMEZONE {zone_name:?} is not an IANA time zone"))?,
+    };
+
+    let booking = BookingConfig {
+        horizon_days: env::var("BOOKING_HORIZON_DAYS")
+            .ok()
+            .and_then(|value| value.trim().parse::<i64>().ok())
+            .filter(|days| *days > 0)
+            .unwrap_or(90),
+        phone_country_code: env::var("PHONE_COUNTRY_CODE")
+            .ok()
+            .map(|value| value.trim().trim_start_matches('+').to_string())
+            .filter(|code| !code.is_empty() && code.len() <= 3 && code.chars().all(|c| c.is_ascii_digit())),
+    };
+
     db::run_migrations(&pool).await?;
     db::seed_defaults(&pool).await?;
+    db::store_scheduled_as_utc(&pool, time.business_zone).await?;
+    let country_code = booking.phone_country_code.as_deref();
+    clients::store_phones_as_e164(&pool, country_code).await?;
+    clients::link_existing_appointments(&pool, country_code).await?;
 
     let (events, _) = tokio::sync::broadcast::channel(200);
     let push = PushConfig {
@@ -50,12 +108,180 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
         subject: env::var("VAPID_SUBJECT").unwrap_or_else(|_| "mailto:admin@barber2go.local".to_string()),
     };
 
+    let areas = ServiceAreaConfig {
+        reject_outside: env::var("SERVICE_AREA_POLICY")
+            .map(|value| value.trim() != "flag")
+            .unwrap_or(true),
+    };
+
+    let dispatch = DispatchConfig {
+        enabled: env::var("DISPATCH_ENABLED")
+            .map(|value| value.trim() == "true")
+            .unwrap_or(false),
+        offer_timeout_secs: env::var("DISPATCH_OFFER_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(300),
+    };
+
+    let routes = RouteConfig {
+        average_speed_kmh: env::var("ROUTE_AVERAGE_SPEED_KMH")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(30.0),
+    };
+
+    let geocoder: Option<Arc<dyn Geocoder>> = match env::var("GEOCODER_URL") {
+        Ok(url) if !url.trim().is_empty() => {
+            let user_agent = env::var("GEOCODER_USER_AGENT")
+                .unwrap_or_else(|_| "Barber2Go/0.1 (admin@barber2go.local)".to_string());
+            let country_codes = env::var("GEOCODER_COUNTRY_CODES").ok().filter(|value| !value.trim().is_empty());
+            Some(Arc::new(NominatimGeocoder::new(&url, &user_agent, country_codes)?))
+        }
+        _ => None,
+    };
+    let backfill_secs: u64 = env::var("GEOCODER_BACKFILL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(300);
+
+    let portal = PortalConfig {
+        base_url: env::var("PUBLIC_BASE_URL")
+            .ok()
+            .map(|value| value.trim().trim_end_matches('/').to_string())
+            .filter(|value| !value.is_empty()),
+        link_ttl_minutes: env::var("CLIENT_LINK_MINUTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(20),
+        session_days: env::var("CLIENT_SESSION_DAYS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(30),
+    };
+
+    if portal.base_url.is_none() {
+        log::warn!("PUBLIC_BASE_URL is not set; messages that need a link to the site won't be sent");
+    }
+
+    // Surcharge amounts are configured in whole currency units, e.g. `TRAVEL_RATE_PER_KM=1.50`.
+    let cents = |name: &str, default: f64| {
+        let value = env::var(name)
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .unwrap_or(default);
+        (value * 100.0).round() as i64
+    };
+    let pricing = PricingConfig {
+        travel_free_km: env::var("TRAVEL_FREE_KM")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(5.0),
+        travel_per_km_cents: cents("TRAVEL_RATE_PER_KM", 1.0),
+        travel_max_cents: cents("TRAVEL_SURCHARGE_MAX", 25.0),
+    };
+
+    let payments: Option<Arc<dyn PaymentProvider>> = match env::var("PAYMENT_PROVIDER").as_deref().map(str::trim) {
+        Ok("fake") => Some(Arc::new(FakePaymentProvider::default())),
+        Ok("stripe") => {
+            let secret_key = env::var("STRIPE_SECRET_KEY").map_err(|_| "STRIPE_SECRET_KEY is required")?;
+            let base_url = env::var("STRIPE_API_BASE").unwrap_or_else(|_| "https://api.stripe.com".to_string());
+            Some(Arc::new(StripeProvider::new(&base_url, &secret_key)?))
+        }
+        _ => None,
+    };
+    let deposits = DepositConfig {
+        percent: env::var("DEPOSIT_PERCENT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(20),
+    };
+
+    let invoices = InvoiceConfig {
+        prefix: env::var("INVOICE_PREFIX").unwrap_or_else(|_| "B2G-".to_string()),
+        business_name: env::var("INVOICE_BUSINESS_NAME").unwrap_or_else(|_| "Barber2Go".to_string()),
+        business_details: env::var("INVOICE_BUSINESS_DETAILS")
+            .ok()
+            .filter(|value| !value.trim().is_empty()),
+        tax_label: env::var("TAX_LABEL").unwrap_or_else(|_| "VAT".to_string()),
+        tax_rate: env::var("TAX_RATE_PERCENT")
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .unwrap_or(0.0)
+            .max(0.0),
+    };
+
+    let payouts = PayoutConfig {
+        default_commission_percent: env::var("COMMISSION_PERCENT")
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .unwrap_or(0.0)
+            .clamp(0.0, 100.0),
+    };
+
+    let policy = PolicyConfig {
+        cutoff_hours: env::var("CANCELLATION_CUTOFF_HOURS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(24),
+        penalty: match env::var("CANCELLATION_PENALTY").as_deref().map(str::trim) {
+            Ok("fee") => Penalty::Fee(cents("CANCELLATION_FEE", 15.0)),
+            Ok("deposit") => Penalty::Deposit,
+            _ => Penalty::None,
+        },
+        max_no_shows: env::var("NO_SHOW_LIMIT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(2),
+    };
+
+    let waitlist = WaitlistConfig {
+        claim_minutes: env::var("WAITLIST_CLAIM_MINUTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(30),
+    };
+
+    let uploads = UploadConfig {
+        dir: env::var("UPLOAD_DIR")
+            .unwrap_or_else(|_| "./data/uploads".to_string())
+            .into(),
+        max_bytes: env::var("UPLOAD_MAX_MB")
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or(5)
+            * 1024
+            * 1024,
+    };
+    std::fs::create_dir_all(&uploads.dir)?;
+
     let state = AppState {
         db: pool.clone(),
         events,
         push,
+        areas,
+        dispatch,
+        routes,
+        geocoder,
+        messages: Arc::new(LogTransport),
+        portal,
+        pricing,
+        payments,
+        deposits,
+        invoices,
+        payouts,
+        policy,
+        waitlist,
+        uploads,
+        time,
+        booking,
     };
 
+    dispatch::resume(&state).await;
+    geocode::spawn_backfill(&state, backfill_secs);
+    waitlist::spawn_sweeper(&state);
+    cms::spawn_scheduler(&state);
+
     let port: u16 = env::var("PORT")
         .ok()
         .and_then(|value| value.parse().ok())
@@ -69,8 +295,15 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
             .app_data(web::Data::new(state.clone()))
             .app_data(basic::Config::default().realm(AUTH_REALM))
             .wrap(middleware::Logger::default())
+            .app_data(
+                MultipartFormConfig::default()
+                    .memory_limit(state.uploads.max_bytes + 64 * 1024)
+                    .total_limit(state.uploads.max_bytes + 64 * 1024),
+            )
             .service(Files::new("/static", "./static").prefer_utf8(true))
+            .service(Files::new(uploads::PUBLIC_PREFIX, &state.uploads.dir))
             .configure(routes::public::configure)
+            .configure(routes::portal::configure)
             .configure(routes::events::configure)
             .configure(routes::admin::configure)
             .configure(routes::barber::configure)
@@ -0,0 +1,268 @@
+use std::{collections::HashMap, io::Cursor};
+
+use chrono::Utc;
+use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    auth::new_id,
+    models::MediaRow,
+    state::AppState,
+    uploads::{self, ImageKind, UploadError},
+};
+
+/// Widths the resizer produces; only those narrower than the original are kept.
+pub const VARIANT_WIDTHS: [u32; 3] = [320, 640, 1280];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MediaItem {
+    pub id: String,
+    pub original_name: String,
+    pub url: String,
+    pub width: i64,
+    pub height: i64,
+    pub size_bytes: i64,
+    pub alt_text: String,
+    pub created_at: String,
+    /// `(width, url)` pairs, narrowest first.
+    pub variants: Vec<(i64, String)>,
+}
+
+impl MediaItem {
+    fn new(row: MediaRow, variants: Vec<(i64, String)>) -> Self {
+        Self {
+            id: row.id,
+            original_name: row.original_name,
+            url: row.url,
+            width: row.width,
+            height: row.height,
+            size_bytes: row.size_bytes,
+            alt_text: row.alt_text.unwrap_or_default(),
+            created_at: row.created_at,
+            variants,
+        }
+    }
+
+    /// The narrowest copy at least `width` pixels wide, or the original.
+    pub fn url_for(&self, width: i64) -> &str {
+        self.variants
+            .iter()
+            .find(|(variant_width, _)| *variant_width >= width)
+            .map(|(_, url)| url.as_str())
+            .unwrap_or(&self.url)
+    }
+
+    /// Every copy including the original, for an `<img srcset>`.
+    pub fn srcset(&self) -> String {
+        self.variants
+            .iter()
+            .map(|(width, url)| format!("{url} {width}w"))
+            .chain(std::iter::once(format!("{} {}w", self.url, self.width)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn size_label(&self) -> String {
+        if self.size_bytes >= 1024 * 1024 {
+            format!("{:.1} MB", self.size_bytes as f64 / (1024.0 * 1024.0))
+        } else {
+            format!("{} KB", (self.size_bytes + 1023) / 1024)
+        }
+    }
+}
+
+/// Validates, resizes and stores an image, and adds it to the media library.
+pub async fn upload(
+    state: &AppState,
+    bytes: Vec<u8>,
+    original_name: &str,
+    alt_text: Option<&str>,
+    uploaded_by: Option<&str>,
+) -> Result<MediaItem, UploadError> {
+    let kind = uploads::check_image(&state.uploads, &bytes)?;
+    let size_bytes = bytes.len() as i64;
+    let (bytes, rendered) =
+        tokio::task::spawn_blocking(move || render_variants(&bytes, kind).map(|rendered| (bytes, rendered)))
+            .await
+            .map_err(|err| UploadError::Io(std::io::Error::other(err)))?
+            .map_err(|_| UploadError::NotAnImage)?;
+
+    let stem = Uuid::new_v4().simple().to_string();
+    let url = uploads::save_file(&state.uploads, &format!("{stem}.{}", kind.extension()), &bytes).await?;
+    let mut variants = Vec::new();
+    for (variant_width, data) in rendered.variants {
+        let name = format!("{stem}-{variant_width}.{}", kind.extension());
+        let variant_url = uploads::save_file(&state.uploads, &name, &data).await?;
+        variants.push((variant_width as i64, variant_url));
+    }
+
+    let row = MediaRow {
+        id: new_id(),
+        original_name: original_name.trim().chars().take(200).collect(),
+        width: rendered.width as i64,
+        height: rendered.height as i64,
+        size_bytes,
+        url,
+        alt_text: alt_text.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    if let Err(err) = insert(&state.db, &row, kind, &variants, uploaded_by).await {
+        for path in variants.iter().map(|(_, url)| url).chain(std::iter::once(&row.url)) {
+            uploads::remove(&state.uploads, path).await;
+        }
+        return Err(UploadError::Database(err));
+    }
+    Ok(MediaItem::new(row, variants))
+}
+
+async fn insert(
+    pool: &SqlitePool,
+    row: &MediaRow,
+    kind: ImageKind,
+    variants: &[(i64, String)],
+    uploaded_by: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO media (id, original_name, content_type, width, height, size_bytes, url, alt_text, uploaded_by, created_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&row.id)
+    .bind(&row.original_name)
+    .bind(kind.content_type())
+    .bind(row.width)
+    .bind(row.height)
+    .bind(row.size_bytes)
+    .bind(&row.url)
+    .bind(&row.alt_text)
+    .bind(uploaded_by)
+    .bind(&row.created_at)
+    .execute(&mut *tx)
+    .await?;
+    for (width, url) in variants {
+        sqlx::query("INSERT INTO media_variants (media_id, width, url) VALUES (?, ?, ?)")
+            .bind(&row.id)
+            .bind(width)
+            .bind(url)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+struct Rendered {
+    width: u32,
+    height: u32,
+    /// Encoded copies keyed by width.
+    variants: Vec<(u32, Vec<u8>)>,
+}
+
+/// Decodes the image and encodes a downscaled copy for each smaller width in `VARIANT_WIDTHS`.
+/// GIFs are kept as uploaded so animations survive.
+fn render_variants(bytes: &[u8], kind: ImageKind) -> Result<Rendered, image::ImageError> {
+    let format = match kind {
+        ImageKind::Jpeg => ImageFormat::Jpeg,
+        ImageKind::Png => ImageFormat::Png,
+        ImageKind::Webp => ImageFormat::WebP,
+        ImageKind::Gif => ImageFormat::Gif,
+    };
+    let image = image::load_from_memory_with_format(bytes, format)?;
+    let (width, height) = image.dimensions();
+    let mut rendered = Rendered {
+        width,
+        height,
+        variants: Vec::new(),
+    };
+    if kind == ImageKind::Gif {
+        return Ok(rendered);
+    }
+
+    for target in VARIANT_WIDTHS.into_iter().filter(|target| *target < width) {
+        let mut resized = image.resize(target, u32::MAX, FilterType::Lanczos3);
+        if kind == ImageKind::Jpeg {
+            resized = DynamicImage::ImageRgb8(resized.to_rgb8());
+        }
+        let mut encoded = Cursor::new(Vec::new());
+        resized.write_to(&mut encoded, format)?;
+        rendered.variants.push((target, encoded.into_inner()));
+    }
+    Ok(rendered)
+}
+
+/// The library, newest first.
+pub async fn list(pool: &SqlitePool) -> Vec<MediaItem> {
+    let rows = sqlx::query_as::<_, MediaRow>(
+        r#"SELECT id, original_name, width, height, size_bytes, url, alt_text, created_at
+           FROM media
+           ORDER BY created_at DESC"#,
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+    let mut variants: HashMap<String, Vec<(i64, String)>> = HashMap::new();
+    for (media_id, width, url) in sqlx::query_as::<_, (String, i64, String)>(
+        "SELECT media_id, width, url FROM media_variants ORDER BY width",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+    {
+        variants.entry(media_id).or_default().push((width, url));
+    }
+    rows.into_iter()
+        .map(|row| {
+            let own = variants.remove(&row.id).unwrap_or_default();
+            MediaItem::new(row, own)
+        })
+        .collect()
+}
+
+pub async fn update_alt_text(pool: &SqlitePool, media_id: &str, alt_text: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE media SET alt_text = ? WHERE id = ?")
+        .bind(Some(alt_text.trim()).filter(|value| !value.is_empty()))
+        .bind(media_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Deletes the library entry and every file behind it. Returns false when nothing matched.
+pub async fn delete(state: &AppState, media_id: &str) -> Result<bool, sqlx::Error> {
+    let variants = sqlx::query_scalar::<_, String>("SELECT url FROM media_variants WHERE media_id = ?")
+        .bind(media_id)
+        .fetch_all(&state.db)
+        .await?;
+    let Some(url) = sqlx::query_scalar::<_, String>("DELETE FROM media WHERE id = ? RETURNING url")
+        .bind(media_id)
+        .fetch_optional(&state.db)
+        .await?
+    else {
+        return Ok(false);
+    };
+    for path in variants.iter().chain(std::iter::once(&url)) {
+        uploads::remove(&state.uploads, path).await;
+    }
+    Ok(true)
+}
+
+/// Deletes the library entry that `url` (the original or any resized copy) belongs to,
+/// or just the file when it never went through the library.
+pub async fn delete_by_url(state: &AppState, url: &str) -> Result<(), sqlx::Error> {
+    let media_id = sqlx::query_scalar::<_, String>(
+        r#"SELECT id FROM media WHERE url = ?
+           UNION SELECT media_id FROM media_variants WHERE url = ?"#,
+    )
+    .bind(url)
+    .bind(url)
+    .fetch_optional(&state.db)
+    .await?;
+    match media_id {
+        Some(media_id) => {
+            delete(state, &media_id).await?;
+        }
+        None => uploads::remove(&state.uploads, url).await,
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use async_trait::async_trait;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Email,
+    Sms,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Email => "email",
+            Channel::Sms => "sms",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OutboundMessage {
+    pub channel: Channel,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct TransportError(pub String);
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "message delivery failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+#[async_trait]
+pub trait MessageTransport: Send + Sync {
+    async fn send(&self, message: &OutboundMessage) -> Result<(), TransportError>;
+}
+
+/// Development stand-in: writes every message to the application log instead of delivering it.
+pub struct LogTransport;
+
+#[async_trait]
+impl MessageTransport for LogTransport {
+    async fn send(&self, message: &OutboundMessage) -> Result<(), TransportError> {
+        log::info!(
+            "[{}] to {} — {}\n{}",
+            message.channel.as_str(),
+            message.to,
+            message.subject,
+            message.body
+        );
+        Ok(())
+    }
+}
@@ -7,6 +7,45 @@ pub const STATUS_PENDING: &str = "pending";
 pub const STATUS_ACCEPTED: &str = "accepted";
 pub const STATUS_DECLINED: &str = "declined";
 pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_CANCELLED: &str = "cancelled";
+pub const STATUS_NO_SHOW: &str = "no_show";
+
+pub const PAYMENT_DEPOSIT: &str = "deposit";
+pub const PAYMENT_CAPTURE: &str = "capture";
+pub const PAYMENT_REFUND: &str = "refund";
+pub const PAYMENT_FEE: &str = "fee";
+pub const PAYMENT_SUCCEEDED: &str = "succeeded";
+pub const PAYMENT_FAILED: &str = "failed";
+
+pub const STRIKE_NO_SHOW: &str = "no_show";
+pub const STRIKE_LATE_CANCELLATION: &str = "late_cancellation";
+
+pub const WAITLIST_WAITING: &str = "waiting";
+pub const WAITLIST_BOOKED: &str = "booked";
+pub const WAITLIST_REMOVED: &str = "removed";
+
+pub const REVIEW_REQUESTED: &str = "requested";
+pub const REVIEW_PENDING: &str = "pending";
+pub const REVIEW_APPROVED: &str = "approved";
+pub const REVIEW_REJECTED: &str = "rejected";
+
+pub const PROFILE_PENDING: &str = "pending";
+pub const PROFILE_REJECTED: &str = "rejected";
+
+pub const CMS_DRAFT: &str = "draft";
+pub const CMS_PUBLISHED: &str = "published";
+
+pub const CMS_FORMAT_HTML: &str = "html";
+pub const CMS_FORMAT_MARKDOWN: &str = "markdown";
+
+pub const OFFER_OPEN: &str = "offered";
+pub const OFFER_ACCEPTED: &str = "accepted";
+pub const OFFER_DECLINED: &str = "declined";
+pub const OFFER_EXPIRED: &str = "expired";
+pub const OFFER_WITHDRAWN: &str = "withdrawn";
+
+pub const AREA_KIND_RADIUS: &str = "radius";
+pub const AREA_KIND_POLYGON: &str = "polygon";
 
 #[allow(dead_code)]
 #[derive(Debug, Clone, sqlx::FromRow)]
@@ -50,6 +89,220 @@ pub struct CmsBlockRow {
     pub key: String,
     pub title: String,
     pub html: String,
+    /// Only selected by the editor; public pages just need `html`.
+    #[sqlx(default)]
+    pub format: String,
+    #[sqlx(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CmsPageRow {
+    pub id: String,
+    pub slug: String,
+    pub title: String,
+    pub meta_description: Option<String>,
+    pub published: bool,
+    pub show_in_nav: bool,
+    pub nav_order: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CmsRevisionRow {
+    pub id: String,
+    pub html: String,
+    pub format: String,
+    pub source: Option<String>,
+    pub kind: String,
+    pub created_at: String,
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CmsDraftRow {
+    pub block_key: String,
+    pub html: String,
+    pub format: String,
+    pub source: Option<String>,
+    pub updated_at: String,
+    pub updated_by: Option<String>,
+    pub author: Option<String>,
+    pub publish_at: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ServiceAreaRow {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    pub center_latitude: Option<f64>,
+    pub center_longitude: Option<f64>,
+    pub radius_km: Option<f64>,
+    pub polygon: Option<String>,
+    pub active: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClientRow {
+    pub id: String,
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub notes: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClientStrikeRow {
+    pub id: String,
+    pub appointment_id: String,
+    pub kind: String,
+    pub fee_cents: i64,
+    pub created_at: String,
+    pub scheduled_for: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WaitlistEntryRow {
+    pub id: String,
+    pub client_name: String,
+    pub client_phone: String,
+    pub client_email: Option<String>,
+    pub service: String,
+    pub wanted_date: String,
+    pub barber_name: Option<String>,
+    pub offer_expires_at: Option<String>,
+}
+
+/// An open waitlist offer joined with the freed slot it is for.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WaitlistOfferRow {
+    pub token: String,
+    pub client_name: String,
+    pub client_phone: String,
+    pub client_email: Option<String>,
+    pub service: String,
+    pub scheduled_for: String,
+    pub barber_id: Option<String>,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReviewRow {
+    pub id: String,
+    pub appointment_id: String,
+    pub barber_name: Option<String>,
+    pub client_name: String,
+    pub rating: Option<i64>,
+    pub comment: Option<String>,
+    pub status: String,
+    pub submitted_at: Option<String>,
+}
+
+/// Public profile fields; the same shape is used for approved profiles and pending drafts.
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct BarberProfileRow {
+    pub bio: Option<String>,
+    pub specialties: Option<String>,
+    pub languages: Option<String>,
+    pub services: Option<String>,
+    pub service_area: Option<String>,
+    pub photo_path: Option<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ProfileDraftRow {
+    #[sqlx(flatten)]
+    pub profile: BarberProfileRow,
+    pub status: String,
+    pub review_note: Option<String>,
+    pub submitted_at: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MediaRow {
+    pub id: String,
+    pub original_name: String,
+    pub width: i64,
+    pub height: i64,
+    pub size_bytes: i64,
+    pub url: String,
+    pub alt_text: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClientLocationRow {
+    pub id: String,
+    pub label: String,
+    pub address: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub access_notes: Option<String>,
+    pub gate_code: Option<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AppointmentItemRow {
+    pub id: String,
+    pub person_name: String,
+    pub service: String,
+    pub duration_minutes: Option<i64>,
+    pub barber_id: Option<String>,
+    pub barber_name: Option<String>,
+    pub price_cents: Option<i64>,
+}
+
+impl AppointmentItemRow {
+    pub fn minutes(&self) -> i64 {
+        self.duration_minutes.unwrap_or_else(|| service_minutes(&self.service))
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct QuoteRow {
+    pub services_cents: i64,
+    pub addons_cents: i64,
+    pub travel_km: Option<f64>,
+    pub travel_cents: i64,
+    pub quote_cents: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PaymentRow {
+    pub kind: String,
+    pub status: String,
+    pub amount_cents: i64,
+    pub provider: String,
+    pub reference: Option<String>,
+    pub error: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct InvoiceRow {
+    pub id: String,
+    pub number: i64,
+    pub appointment_id: String,
+    pub client_name: String,
+    pub client_email: Option<String>,
+    pub address: String,
+    pub barber_name: Option<String>,
+    pub subtotal_cents: i64,
+    pub tax_rate: f64,
+    pub tax_cents: i64,
+    pub tip_cents: i64,
+    pub total_cents: i64,
+    pub paid_cents: i64,
+    pub issued_at: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct InvoiceLineRow {
+    pub description: String,
+    pub amount_cents: i64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -57,5 +310,141 @@ pub struct ServiceOption {
     pub name: &'static str,
     pub duration: &'static str,
     pub description: &'static str,
+    pub price_cents: i64,
     pub selected: bool,
 }
+
+impl ServiceOption {
+    pub fn price(&self) -> String {
+        format_money(self.price_cents)
+    }
+
+    pub fn duration_minutes(&self) -> i64 {
+        self.duration
+            .split_whitespace()
+            .next()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_SERVICE_MINUTES)
+    }
+}
+
+pub const DEFAULT_SERVICE_MINUTES: i64 = 45;
+
+/// Catalog prices are in euro cents.
+pub const CURRENCY_SYMBOL: &str = "€";
+pub const CURRENCY_CODE: &str = "eur";
+
+pub fn format_money(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.abs();
+    format!("{sign}{CURRENCY_SYMBOL}{}.{:02}", cents / 100, cents % 100)
+}
+
+/// Plain amount without the currency symbol, for CSV exports and form fields, e.g. "45.00".
+pub fn format_decimal(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{sign}{}.{:02}", cents.abs() / 100, cents.abs() % 100)
+}
+
+/// Parses an amount typed in whole currency units, e.g. "5", "4.50" or "€4,50".
+pub fn parse_money(value: &str) -> Option<i64> {
+    let value = value.trim().trim_start_matches(CURRENCY_SYMBOL).trim().replace(',', ".");
+    if value.is_empty() {
+        return Some(0);
+    }
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|amount| amount.is_finite() && *amount >= 0.0)
+        .map(|amount| (amount * 100.0).round() as i64)
+}
+
+pub fn service_catalog() -> Vec<ServiceOption> {
+    vec![
+        ServiceOption {
+            name: "Signature Cut",
+            duration: "45 min",
+            description: "Precision cut, styling, and lineup.",
+            price_cents: 4500,
+            selected: false,
+        },
+        ServiceOption {
+            name: "Fade & Line-Up",
+            duration: "35 min",
+            description: "Skin fade with sharp finishing touches.",
+            price_cents: 3800,
+            selected: false,
+        },
+        ServiceOption {
+            name: "Beard Sculpt",
+            duration: "25 min",
+            description: "Shape, trim, and conditioning for the beard.",
+            price_cents: 2500,
+            selected: false,
+        },
+        ServiceOption {
+            name: "Full Grooming",
+            duration: "60 min",
+            description: "Cut, beard, and grooming refresh.",
+            price_cents: 6500,
+            selected: false,
+        },
+    ]
+}
+
+pub fn service_minutes(name: &str) -> i64 {
+    service_catalog()
+        .into_iter()
+        .find(|service| service.name == name)
+        .map(|service| service.duration_minutes())
+        .unwrap_or(DEFAULT_SERVICE_MINUTES)
+}
+
+pub fn service_price_cents(name: &str) -> i64 {
+    service_catalog()
+        .into_iter()
+        .find(|service| service.name == name)
+        .map(|service| service.price_cents)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AddOnOption {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub price_cents: i64,
+    pub selected: bool,
+}
+
+impl AddOnOption {
+    pub fn price(&self) -> String {
+        format_money(self.price_cents)
+    }
+}
+
+pub fn addon_catalog() -> Vec<AddOnOption> {
+    vec![
+        AddOnOption {
+            key: "hot_towel",
+            name: "Hot towel",
+            description: "Steamed towel finish after the cut or shave.",
+            price_cents: 800,
+            selected: false,
+        },
+        AddOnOption {
+            key: "beard_oil",
+            name: "Beard oil",
+            description: "Conditioning oil worked in after the trim.",
+            price_cents: 500,
+            selected: false,
+        },
+        AddOnOption {
+            key: "kids_cut",
+            name: "Kids' cut",
+            description: "A quick cut for a child under 12 at the same visit.",
+            price_cents: 2000,
+            selected: false,
+        },
+    ]
+}
@@ -0,0 +1,223 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    cms,
+    i18n::Locale,
+    models::{CmsBlockRow, CmsPageRow, CMS_PUBLISHED},
+};
+
+const PAGE_COLUMNS: &str = "id, slug, title, meta_description, published, show_in_nav, nav_order";
+
+pub const MAX_META_DESCRIPTION_CHARS: usize = 300;
+
+/// What admins fill in on the page settings form.
+pub struct PageInput {
+    pub slug: String,
+    pub title: String,
+    pub meta_description: Option<String>,
+    pub published: bool,
+    pub show_in_nav: bool,
+    pub nav_order: i64,
+}
+
+/// A link in the public site navigation.
+#[derive(Clone, Debug)]
+pub struct NavPage {
+    pub slug: String,
+    pub title: String,
+}
+
+/// Lowercase ASCII letters, digits and single dashes, e.g. "Terms & Conditions" → "terms-conditions".
+pub fn slugify(value: &str) -> String {
+    let mut slug = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').chars().take(80).collect()
+}
+
+pub async fn list(pool: &SqlitePool) -> Vec<CmsPageRow> {
+    sqlx::query_as::<_, CmsPageRow>(&format!(
+        "SELECT {PAGE_COLUMNS} FROM cms_pages ORDER BY nav_order, title"
+    ))
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn fetch(pool: &SqlitePool, page_id: &str) -> Option<CmsPageRow> {
+    sqlx::query_as::<_, CmsPageRow>(&format!("SELECT {PAGE_COLUMNS} FROM cms_pages WHERE id = ?"))
+        .bind(page_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+}
+
+/// A page clients can see; unpublished pages are not found.
+pub async fn fetch_published(pool: &SqlitePool, slug: &str) -> Option<CmsPageRow> {
+    sqlx::query_as::<_, CmsPageRow>(&format!(
+        "SELECT {PAGE_COLUMNS} FROM cms_pages WHERE slug = ? AND published = 1"
+    ))
+    .bind(slug)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+pub async fn slug_taken(pool: &SqlitePool, slug: &str, except_id: Option<&str>) -> bool {
+    sqlx::query_scalar::<_, String>("SELECT id FROM cms_pages WHERE slug = ? AND id IS NOT ?")
+        .bind(slug)
+        .bind(except_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .is_some()
+}
+
+/// Published pages flagged for the site navigation, in menu order.
+pub async fn nav(pool: &SqlitePool) -> Vec<NavPage> {
+    sqlx::query_as::<_, (String, String)>(
+        "SELECT slug, title FROM cms_pages WHERE published = 1 AND show_in_nav = 1 ORDER BY nav_order, title",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(slug, title)| NavPage { slug, title })
+    .collect()
+}
+
+/// The page's content blocks in display order, live copies.
+pub async fn blocks(pool: &SqlitePool, page_id: &str) -> Vec<CmsBlockRow> {
+    sqlx::query_as::<_, CmsBlockRow>(
+        "SELECT key, title, html, format, source FROM cms_blocks WHERE page_id = ? ORDER BY position, key",
+    )
+    .bind(page_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// The live HTML of each section in `locale`, falling back to the original where it is not translated.
+pub async fn localized_blocks(pool: &SqlitePool, page_id: &str, locale: Locale) -> Vec<String> {
+    sqlx::query_scalar::<_, String>(
+        r#"SELECT COALESCE(NULLIF(v.html, ''), b.html)
+           FROM cms_blocks b
+           LEFT JOIN cms_blocks v ON v.variant_of = b.key AND v.locale = ?
+           WHERE b.page_id = ?
+           ORDER BY b.position, b.key"#,
+    )
+    .bind(locale.code())
+    .bind(page_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// Creates the page with one empty section. Returns the new page id.
+pub async fn create(pool: &SqlitePool, input: &PageInput, author: Option<&str>) -> Result<String, sqlx::Error> {
+    let page_id = new_id();
+    let now = Utc::now().to_rfc3339();
+    sqlx::query(
+        r#"INSERT INTO cms_pages (id, slug, title, meta_description, published, show_in_nav, nav_order, created_at, updated_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&page_id)
+    .bind(&input.slug)
+    .bind(&input.title)
+    .bind(&input.meta_description)
+    .bind(input.published)
+    .bind(input.show_in_nav)
+    .bind(input.nav_order)
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await?;
+    add_block(pool, &page_id, &format!("<h1>{}</h1>", escape_text(&input.title)), author).await?;
+    Ok(page_id)
+}
+
+pub async fn update(pool: &SqlitePool, page_id: &str, input: &PageInput) -> Result<bool, sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE cms_pages
+           SET slug = ?, title = ?, meta_description = ?, published = ?, show_in_nav = ?, nav_order = ?, updated_at = ?
+           WHERE id = ?"#,
+    )
+    .bind(&input.slug)
+    .bind(&input.title)
+    .bind(&input.meta_description)
+    .bind(input.published)
+    .bind(input.show_in_nav)
+    .bind(input.nav_order)
+    .bind(Utc::now().to_rfc3339())
+    .bind(page_id)
+    .execute(pool)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+/// Deletes the page; its blocks, drafts and revisions go with it.
+pub async fn delete(pool: &SqlitePool, page_id: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM cms_pages WHERE id = ?")
+        .bind(page_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+/// Appends a section to the page and returns its block key.
+pub async fn add_block(pool: &SqlitePool, page_id: &str, html: &str, author: Option<&str>) -> Result<String, sqlx::Error> {
+    let key = format!("page-{}", new_id());
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+    let position = sqlx::query_scalar::<_, i64>("SELECT COALESCE(MAX(position), 0) + 1 FROM cms_blocks WHERE page_id = ?")
+        .bind(page_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    sqlx::query(
+        r#"INSERT INTO cms_blocks (key, title, html, updated_at, updated_by, page_id, position)
+           VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&key)
+    .bind(format!("Section {position}"))
+    .bind(html)
+    .bind(&now)
+    .bind(author)
+    .bind(page_id)
+    .bind(position)
+    .execute(&mut *tx)
+    .await?;
+    let content = cms::BlockContent::html(html.to_string());
+    cms::insert_revision(&mut tx, &key, &content, CMS_PUBLISHED, &now, author).await?;
+    tx.commit().await?;
+    Ok(key)
+}
+
+pub async fn remove_block(pool: &SqlitePool, page_id: &str, key: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query("DELETE FROM cms_blocks WHERE key = ? AND page_id = ?")
+        .bind(key)
+        .bind(page_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+/// The page a block belongs to, if it is not one of the home page blocks.
+pub async fn page_for_block(pool: &SqlitePool, key: &str) -> Option<String> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT page_id FROM cms_blocks WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .flatten()
+}
+
+fn escape_text(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,523 @@
+use std::{collections::HashMap, fmt, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use isahc::{config::Configurable, AsyncReadResponseExt, HttpClient, Request};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    auth::new_id,
+    db::log_activity,
+    models::{
+        PaymentRow, CURRENCY_CODE, PAYMENT_CAPTURE, PAYMENT_DEPOSIT, PAYMENT_FAILED, PAYMENT_FEE,
+        PAYMENT_REFUND, PAYMENT_SUCCEEDED, STATUS_CANCELLED, STATUS_COMPLETED, STATUS_DECLINED, STATUS_NO_SHOW,
+    },
+    policy, pricing,
+    state::{AppState, DepositConfig},
+};
+
+#[derive(Clone, Debug)]
+pub struct ChargeRequest {
+    pub amount_cents: i64,
+    pub currency: String,
+    pub payment_method: String,
+    pub customer: Option<String>,
+    pub description: String,
+    /// Set when charging a saved card without the client present.
+    pub off_session: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Charge {
+    pub reference: String,
+    pub payment_method: String,
+    pub customer: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum PaymentError {
+    Declined(String),
+    Http(String),
+    Parse(String),
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::Declined(message) => write!(f, "{message}"),
+            PaymentError::Http(message) => write!(f, "payment provider request failed: {message}"),
+            PaymentError::Parse(message) => write!(f, "payment provider response invalid: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PaymentError {}
+
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn charge(&self, request: &ChargeRequest) -> Result<Charge, PaymentError>;
+    /// Returns the provider's reference for the refund itself.
+    async fn refund(&self, charge_reference: &str, amount_cents: i64) -> Result<String, PaymentError>;
+}
+
+/// Local stand-in that keeps charges in memory. Any payment method succeeds unless it contains "decline".
+#[derive(Default)]
+pub struct FakePaymentProvider {
+    refundable: Mutex<HashMap<String, i64>>,
+}
+
+#[async_trait]
+impl PaymentProvider for FakePaymentProvider {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    async fn charge(&self, request: &ChargeRequest) -> Result<Charge, PaymentError> {
+        if request.payment_method.trim().is_empty() {
+            return Err(PaymentError::Declined("No payment method was provided.".to_string()));
+        }
+        if request.payment_method.contains("decline") {
+            return Err(PaymentError::Declined("Your card was declined.".to_string()));
+        }
+        let reference = format!("fake_pi_{}", Uuid::new_v4().simple());
+        if let Ok(mut refundable) = self.refundable.lock() {
+            refundable.insert(reference.clone(), request.amount_cents);
+        }
+        Ok(Charge {
+            reference,
+            payment_method: request.payment_method.clone(),
+            customer: request
+                .customer
+                .clone()
+                .or_else(|| Some(format!("fake_cus_{}", Uuid::new_v4().simple()))),
+        })
+    }
+
+    async fn refund(&self, charge_reference: &str, amount_cents: i64) -> Result<String, PaymentError> {
+        let mut refundable = self
+            .refundable
+            .lock()
+            .map_err(|_| PaymentError::Http("fake provider state poisoned".to_string()))?;
+        let Some(remaining) = refundable.get_mut(charge_reference) else {
+            return Err(PaymentError::Declined(format!("Unknown charge {charge_reference}.")));
+        };
+        if amount_cents > *remaining {
+            return Err(PaymentError::Declined("Refund exceeds the charged amount.".to_string()));
+        }
+        *remaining -= amount_cents;
+        Ok(format!("fake_re_{}", Uuid::new_v4().simple()))
+    }
+}
+
+/// Talks to Stripe's REST API, or any service that mimics its payment intents and refunds endpoints.
+pub struct StripeProvider {
+    base_url: String,
+    secret_key: String,
+    client: HttpClient,
+}
+
+impl StripeProvider {
+    pub fn new(base_url: &str, secret_key: &str) -> Result<Self, PaymentError> {
+        let client = HttpClient::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .map_err(|err| PaymentError::Http(err.to_string()))?;
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            secret_key: secret_key.to_string(),
+            client,
+        })
+    }
+
+    async fn post(&self, path: &str, params: &[(&str, String)]) -> Result<Value, PaymentError> {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params.iter().map(|(key, value)| (*key, value.as_str())))
+            .finish();
+        let request = Request::post(format!("{}{path}", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.secret_key))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .map_err(|err| PaymentError::Http(err.to_string()))?;
+        let mut response = self
+            .client
+            .send_async(request)
+            .await
+            .map_err(|err| PaymentError::Http(err.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|err| PaymentError::Http(err.to_string()))?;
+        let json: Value = serde_json::from_str(&text).map_err(|err| PaymentError::Parse(err.to_string()))?;
+        if status.is_success() {
+            return Ok(json);
+        }
+        let message = json["error"]["message"]
+            .as_str()
+            .unwrap_or("unknown error")
+            .to_string();
+        // Stripe answers card errors with 402; everything else is an integration problem.
+        if status.as_u16() == 402 {
+            Err(PaymentError::Declined(message))
+        } else {
+            Err(PaymentError::Http(format!("status {status}: {message}")))
+        }
+    }
+}
+
+fn string_field(json: &Value, field: &str) -> Result<String, PaymentError> {
+    json[field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| PaymentError::Parse(format!("missing `{field}`")))
+}
+
+#[async_trait]
+impl PaymentProvider for StripeProvider {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    async fn charge(&self, request: &ChargeRequest) -> Result<Charge, PaymentError> {
+        // Off-session charges need the card attached to a customer, so the first charge creates one.
+        let customer = match &request.customer {
+            Some(customer) => customer.clone(),
+            None => {
+                let created = self
+                    .post("/v1/customers", &[("description", request.description.clone())])
+                    .await?;
+                string_field(&created, "id")?
+            }
+        };
+        let mut params = vec![
+            ("amount", request.amount_cents.to_string()),
+            ("currency", request.currency.clone()),
+            ("customer", customer.clone()),
+            ("payment_method", request.payment_method.clone()),
+            ("description", request.description.clone()),
+            ("confirm", "true".to_string()),
+            ("automatic_payment_methods[enabled]", "true".to_string()),
+            ("automatic_payment_methods[allow_redirects]", "never".to_string()),
+        ];
+        if request.off_session {
+            params.push(("off_session", "true".to_string()));
+        } else {
+            params.push(("setup_future_usage", "off_session".to_string()));
+        }
+
+        let intent = self.post("/v1/payment_intents", &params).await?;
+        let status = intent["status"].as_str().unwrap_or_default();
+        if status != "succeeded" {
+            return Err(PaymentError::Declined(format!("Payment is {status}.")));
+        }
+        Ok(Charge {
+            reference: string_field(&intent, "id")?,
+            payment_method: intent["payment_method"]
+                .as_str()
+                .unwrap_or(&request.payment_method)
+                .to_string(),
+            customer: Some(customer),
+        })
+    }
+
+    async fn refund(&self, charge_reference: &str, amount_cents: i64) -> Result<String, PaymentError> {
+        let refund = self
+            .post(
+                "/v1/refunds",
+                &[
+                    ("payment_intent", charge_reference.to_string()),
+                    ("amount", amount_cents.to_string()),
+                ],
+            )
+            .await?;
+        string_field(&refund, "id")
+    }
+}
+
+impl DepositConfig {
+    pub fn amount_for(&self, quote_cents: i64) -> i64 {
+        (quote_cents * self.percent.clamp(0, 100) + 50) / 100
+    }
+}
+
+struct NewPayment<'a> {
+    kind: &'a str,
+    amount_cents: i64,
+    provider: &'a str,
+    reference: Option<&'a str>,
+    charge_reference: Option<&'a str>,
+    charge: Option<&'a Charge>,
+    error: Option<String>,
+}
+
+async fn record(pool: &SqlitePool, appointment_id: &str, payment: NewPayment<'_>) -> Result<(), sqlx::Error> {
+    let status = if payment.error.is_some() {
+        PAYMENT_FAILED
+    } else {
+        PAYMENT_SUCCEEDED
+    };
+    sqlx::query(
+        r#"INSERT INTO payments
+           (id, appointment_id, kind, status, amount_cents, currency, provider, reference, charge_reference,
+            payment_method, customer_reference, error, created_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(new_id())
+    .bind(appointment_id)
+    .bind(payment.kind)
+    .bind(status)
+    .bind(payment.amount_cents)
+    .bind(CURRENCY_CODE)
+    .bind(payment.provider)
+    .bind(payment.reference.or(payment.charge.map(|charge| charge.reference.as_str())))
+    .bind(payment.charge_reference)
+    .bind(payment.charge.map(|charge| charge.payment_method.as_str()))
+    .bind(payment.charge.and_then(|charge| charge.customer.as_deref()))
+    .bind(payment.error)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn record_deposit(
+    pool: &SqlitePool,
+    provider: &str,
+    appointment_id: &str,
+    amount_cents: i64,
+    charge: &Charge,
+) -> Result<(), sqlx::Error> {
+    record(
+        pool,
+        appointment_id,
+        NewPayment {
+            kind: PAYMENT_DEPOSIT,
+            amount_cents,
+            provider,
+            reference: None,
+            charge_reference: None,
+            charge: Some(charge),
+            error: None,
+        },
+    )
+    .await
+}
+
+pub async fn fetch_ledger(pool: &SqlitePool, appointment_id: &str) -> Vec<PaymentRow> {
+    sqlx::query_as::<_, PaymentRow>(
+        r#"SELECT kind, status, amount_cents, provider, reference, error, created_at
+           FROM payments
+           WHERE appointment_id = ?
+           ORDER BY created_at"#,
+    )
+    .bind(appointment_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// Charged minus refunded, counting only successful entries.
+pub async fn net_paid(pool: &SqlitePool, appointment_id: &str) -> i64 {
+    sqlx::query_scalar::<_, i64>(
+        r#"SELECT COALESCE(SUM(CASE WHEN kind = ? THEN -amount_cents ELSE amount_cents END), 0)
+           FROM payments
+           WHERE appointment_id = ? AND status = ?"#,
+    )
+    .bind(PAYMENT_REFUND)
+    .bind(appointment_id)
+    .bind(PAYMENT_SUCCEEDED)
+    .fetch_one(pool)
+    .await
+    .unwrap_or(0)
+}
+
+/// The card saved with the deposit, falling back to the first visit of the series it belongs to.
+async fn saved_payment_method(pool: &SqlitePool, appointment_id: &str) -> Option<(String, Option<String>)> {
+    sqlx::query_as::<_, (String, Option<String>)>(
+        r#"SELECT p.payment_method, p.customer_reference
+           FROM payments p
+           JOIN appointments a ON a.id = p.appointment_id
+           WHERE p.status = ? AND p.payment_method IS NOT NULL
+             AND (p.appointment_id = ?
+                  OR a.series_id = (SELECT series_id FROM appointments WHERE id = ? AND series_id IS NOT NULL))
+           ORDER BY p.appointment_id = ? DESC, p.created_at
+           LIMIT 1"#,
+    )
+    .bind(PAYMENT_SUCCEEDED)
+    .bind(appointment_id)
+    .bind(appointment_id)
+    .bind(appointment_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+/// Charges the card saved with the deposit without the client present.
+async fn charge_saved_card(state: &AppState, appointment_id: &str, kind: &str, amount_cents: i64) {
+    let Some(provider) = state.payments.clone() else {
+        return;
+    };
+    let outcome = match saved_payment_method(&state.db, appointment_id).await {
+        Some((payment_method, customer)) => {
+            provider
+                .charge(&ChargeRequest {
+                    amount_cents,
+                    currency: CURRENCY_CODE.to_string(),
+                    payment_method,
+                    customer,
+                    description: format!("Barber2Go appointment {appointment_id}"),
+                    off_session: true,
+                })
+                .await
+        }
+        None => Err(PaymentError::Declined("No saved payment method.".to_string())),
+    };
+
+    let error = outcome.as_ref().err().map(|err| err.to_string());
+    let _ = record(
+        &state.db,
+        appointment_id,
+        NewPayment {
+            kind,
+            amount_cents,
+            provider: provider.name(),
+            reference: None,
+            charge_reference: None,
+            charge: outcome.as_ref().ok(),
+            error: error.clone(),
+        },
+    )
+    .await;
+    if let Some(error) = error {
+        let what = if kind == PAYMENT_FEE {
+            "Cancellation fee"
+        } else {
+            "Final payment"
+        };
+        log_activity(
+            &state.db,
+            "payment_failed",
+            &format!("{what} for appointment {appointment_id} failed: {error}"),
+            None,
+            Some(appointment_id),
+        )
+        .await;
+    }
+}
+
+/// Charges whatever the quote still owes once the visit is completed.
+async fn capture_balance(state: &AppState, appointment_id: &str) {
+    let Some(quote) = pricing::fetch_quote(&state.db, appointment_id).await else {
+        return;
+    };
+    let balance = quote.quote_cents - net_paid(&state.db, appointment_id).await;
+    if balance > 0 {
+        charge_saved_card(state, appointment_id, PAYMENT_CAPTURE, balance).await;
+    }
+}
+
+/// Refunds everything held beyond `keep_cents`, newest charge first, and charges the saved card
+/// for a fee the deposit doesn't cover.
+async fn retain(state: &AppState, appointment_id: &str, keep_cents: i64) {
+    let Some(provider) = state.payments.clone() else {
+        return;
+    };
+    let charges = sqlx::query_as::<_, (String, i64)>(
+        r#"SELECT reference, amount_cents FROM payments
+           WHERE appointment_id = ? AND kind IN (?, ?, ?) AND status = ? AND reference IS NOT NULL
+           ORDER BY created_at DESC"#,
+    )
+    .bind(appointment_id)
+    .bind(PAYMENT_DEPOSIT)
+    .bind(PAYMENT_CAPTURE)
+    .bind(PAYMENT_FEE)
+    .bind(PAYMENT_SUCCEEDED)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let mut held = Vec::new();
+    for (reference, amount_cents) in charges {
+        let refunded = sqlx::query_scalar::<_, i64>(
+            "SELECT COALESCE(SUM(amount_cents), 0) FROM payments WHERE charge_reference = ? AND kind = ? AND status = ?",
+        )
+        .bind(&reference)
+        .bind(PAYMENT_REFUND)
+        .bind(PAYMENT_SUCCEEDED)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+        let remaining = amount_cents - refunded;
+        if remaining > 0 {
+            held.push((reference, remaining));
+        }
+    }
+
+    let total_held: i64 = held.iter().map(|(_, remaining)| remaining).sum();
+    if total_held < keep_cents {
+        charge_saved_card(state, appointment_id, PAYMENT_FEE, keep_cents - total_held).await;
+        return;
+    }
+
+    let mut to_refund = total_held - keep_cents;
+    for (reference, remaining) in held {
+        if to_refund <= 0 {
+            break;
+        }
+        let amount_cents = remaining.min(to_refund);
+        let outcome = provider.refund(&reference, amount_cents).await;
+        let error = outcome.as_ref().err().map(|err| err.to_string());
+        let _ = record(
+            &state.db,
+            appointment_id,
+            NewPayment {
+                kind: PAYMENT_REFUND,
+                amount_cents,
+                provider: provider.name(),
+                reference: outcome.as_deref().ok(),
+                charge_reference: Some(&reference),
+                charge: None,
+                error: error.clone(),
+            },
+        )
+        .await;
+        match error {
+            None => to_refund -= amount_cents,
+            Some(error) => {
+                log_activity(
+                    &state.db,
+                    "payment_failed",
+                    &format!("Refund for appointment {appointment_id} failed: {error}"),
+                    None,
+                    Some(appointment_id),
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Brings the ledger in line with the appointment's status: completed visits are charged in full,
+/// cancelled, declined or missed ones refunded except for any fee a client strike keeps.
+/// Safe to call after every status change.
+pub async fn settle(state: &AppState, appointment_id: &str) {
+    if state.payments.is_none() {
+        return;
+    }
+    let status = sqlx::query_scalar::<_, String>("SELECT status FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None)
+        .unwrap_or_default();
+    match status.as_str() {
+        STATUS_COMPLETED => capture_balance(state, appointment_id).await,
+        STATUS_CANCELLED | STATUS_DECLINED | STATUS_NO_SHOW => {
+            let keep_cents = policy::retained_cents(&state.db, appointment_id).await;
+            retain(state, appointment_id, keep_cents).await
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use chrono_tz::Tz;
+use sqlx::SqlitePool;
+
+use crate::{
+    line_items,
+    models::{format_decimal, service_price_cents, STATUS_COMPLETED},
+    pricing, schedule,
+    state::PayoutConfig,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            Some("month") => Period::Month,
+            _ => Period::Week,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Period::Week => "week",
+            Period::Month => "month",
+        }
+    }
+
+    /// Weeks start on Monday.
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            Period::Week => format!("Week of {}", start.format("%Y-%m-%d")),
+            Period::Month => start.format("%B %Y").to_string(),
+        }
+    }
+
+    /// The report covers the current period and the three before it unless a range is given.
+    pub fn default_from(&self, today: NaiveDate) -> NaiveDate {
+        let start = self.start_of(today);
+        match self {
+            Period::Week => start - Duration::weeks(3),
+            Period::Month => start.checked_sub_months(Months::new(3)).unwrap_or(start),
+        }
+    }
+}
+
+/// Report range from the query string; missing or unreadable dates fall back to the last few periods.
+pub fn parse_range(period: Period, from: Option<&str>, to: Option<&str>, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let parse = |value: Option<&str>| value.and_then(|value| NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok());
+    let to = parse(to).unwrap_or(today);
+    let from = parse(from).unwrap_or_else(|| period.default_from(to)).min(to);
+    (from, to)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Earnings {
+    pub visits: i64,
+    pub gross_cents: i64,
+    pub commission_cents: i64,
+    pub tips_cents: i64,
+}
+
+impl Earnings {
+    /// What the barber is paid: their services minus the business's commission, plus every tip.
+    pub fn net_cents(&self) -> i64 {
+        self.gross_cents - self.commission_cents + self.tips_cents
+    }
+
+    fn add(&mut self, other: &Earnings) {
+        self.visits += other.visits;
+        self.gross_cents += other.gross_cents;
+        self.commission_cents += other.commission_cents;
+        self.tips_cents += other.tips_cents;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PayoutLine {
+    pub period_start: NaiveDate,
+    pub period_label: String,
+    pub barber_name: String,
+    pub commission_percent: f64,
+    pub earnings: Earnings,
+}
+
+#[derive(Clone, Debug)]
+pub struct PayoutReport {
+    pub period: Period,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub lines: Vec<PayoutLine>,
+    pub total: Earnings,
+}
+
+/// Completed visits dated between `from` and `to` in `zone`, grouped by period and barber.
+/// Each person's service counts for the barber who did it; add-ons, travel and tips go to the booking's barber.
+pub async fn build_report(
+    pool: &SqlitePool,
+    config: &PayoutConfig,
+    period: Period,
+    from: NaiveDate,
+    to: NaiveDate,
+    barber_id: Option<&str>,
+    zone: Tz,
+) -> PayoutReport {
+    let barbers = sqlx::query_as::<_, (String, String, Option<f64>)>(
+        "SELECT id, display_name, commission_percent FROM users",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(id, name, percent)| {
+        let percent = percent.unwrap_or(config.default_commission_percent).clamp(0.0, 100.0);
+        (id, (name, percent))
+    })
+    .collect::<HashMap<_, _>>();
+
+    let appointments = sqlx::query_as::<_, (String, String, Option<String>, i64)>(
+        r#"SELECT id, scheduled_for, barber_id, tip_cents
+           FROM appointments
+           WHERE status = ? AND scheduled_for >= ? AND scheduled_for < ?
+           ORDER BY scheduled_for"#,
+    )
+    .bind(STATUS_COMPLETED)
+    .bind(schedule::day_bounds(from, zone).0)
+    .bind(schedule::day_bounds(to, zone).1)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+
+    let mut grouped: BTreeMap<(NaiveDate, String), Earnings> = BTreeMap::new();
+    for (appointment_id, scheduled_for, appointment_barber, tip_cents) in appointments {
+        let Some(date) = schedule::local_date(&scheduled_for, zone) else {
+            continue;
+        };
+        let start = period.start_of(date);
+
+        let mut shares: HashMap<String, i64> = HashMap::new();
+        for item in line_items::fetch_items(pool, &appointment_id).await {
+            let Some(barber) = item.barber_id.clone().or_else(|| appointment_barber.clone()) else {
+                continue;
+            };
+            let price = item.price_cents.unwrap_or_else(|| service_price_cents(&item.service));
+            *shares.entry(barber).or_default() += price;
+        }
+        if let Some(barber) = &appointment_barber {
+            let extras = pricing::fetch_quote(pool, &appointment_id)
+                .await
+                .map(|quote| quote.addons_cents + quote.travel_cents)
+                .unwrap_or(0);
+            *shares.entry(barber.clone()).or_default() += extras;
+        }
+
+        for (barber, gross_cents) in shares {
+            if barber_id.is_some_and(|wanted| wanted != barber) {
+                continue;
+            }
+            let percent = barbers.get(&barber).map(|(_, percent)| *percent).unwrap_or(0.0);
+            let tips_cents = if appointment_barber.as_deref() == Some(barber.as_str()) {
+                tip_cents
+            } else {
+                0
+            };
+            grouped.entry((start, barber)).or_default().add(&Earnings {
+                visits: 1,
+                gross_cents,
+                commission_cents: (gross_cents as f64 * percent / 100.0).round() as i64,
+                tips_cents,
+            });
+        }
+    }
+
+    let mut total = Earnings::default();
+    let mut lines: Vec<PayoutLine> = grouped
+        .into_iter()
+        .map(|((period_start, barber_id), earnings)| {
+            total.add(&earnings);
+            let (barber_name, commission_percent) = barbers
+                .get(&barber_id)
+                .cloned()
+                .unwrap_or_else(|| ("Former barber".to_string(), 0.0));
+            PayoutLine {
+                period_start,
+                period_label: period.label(period_start),
+                barber_name,
+                commission_percent,
+                earnings,
+            }
+        })
+        .collect();
+    lines.sort_by(|a, b| {
+        b.period_start
+            .cmp(&a.period_start)
+            .then_with(|| a.barber_name.cmp(&b.barber_name))
+    });
+
+    PayoutReport {
+        period,
+        from,
+        to,
+        lines,
+        total,
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl PayoutReport {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("period_start,period,barber,visits,gross,commission_percent,commission,tips,net\n");
+        for line in &self.lines {
+            let earnings = &line.earnings;
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                line.period_start.format("%Y-%m-%d"),
+                csv_field(&line.period_label),
+                csv_field(&line.barber_name),
+                earnings.visits,
+                format_decimal(earnings.gross_cents),
+                line.commission_percent,
+                format_decimal(earnings.commission_cents),
+                format_decimal(earnings.tips_cents),
+                format_decimal(earnings.net_cents()),
+            ));
+        }
+        csv
+    }
+
+    pub fn filename(&self, prefix: &str) -> String {
+        format!(
+            "{prefix}-{}-{}.csv",
+            self.from.format("%Y-%m-%d"),
+            self.to.format("%Y-%m-%d")
+        )
+    }
+}
+
+/// Stores the tip and carries it onto the receipt if one was already issued.
+pub async fn record_tip(pool: &SqlitePool, appointment_id: &str, tip_cents: i64) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE appointments SET tip_cents = ? WHERE id = ?")
+        .bind(tip_cents)
+        .bind(appointment_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE invoices SET tip_cents = ?, total_cents = subtotal_cents + ? WHERE appointment_id = ?")
+        .bind(tip_cents)
+        .bind(tip_cents)
+        .bind(appointment_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await
+}
+
+pub async fn fetch_tip(pool: &SqlitePool, appointment_id: &str) -> i64 {
+    sqlx::query_scalar::<_, i64>("SELECT tip_cents FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .unwrap_or(0)
+}
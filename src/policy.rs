@@ -0,0 +1,149 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    db::log_activity,
+    i18n::Locale,
+    models::{format_money, ClientStrikeRow, STRIKE_LATE_CANCELLATION, STRIKE_NO_SHOW},
+    payments,
+    schedule::parse_scheduled_for,
+    state::{AppState, Penalty, PolicyConfig},
+};
+
+impl PolicyConfig {
+    /// Cancelling after the cutoff, or once the visit has started, is late.
+    pub fn is_late(&self, scheduled_for: &str, now: DateTime<Utc>) -> bool {
+        parse_scheduled_for(scheduled_for)
+            .is_some_and(|start| start - Duration::hours(self.cutoff_hours) <= now)
+    }
+
+    /// One sentence for the booking page, e.g. "Cancellations less than 24 hours ahead cost €15.00."
+    pub fn summary(&self, locale: Locale) -> Option<String> {
+        let hours = self.cutoff_hours;
+        match self.penalty {
+            Penalty::None => None,
+            Penalty::Fee(cents) => Some(locale.format(
+                "Cancellations less than {hours} hours ahead and missed visits cost {fee}.",
+                &[("hours", &hours), ("fee", &format_money(cents))],
+            )),
+            Penalty::Deposit => Some(locale.format(
+                "Cancellations less than {hours} hours ahead and missed visits forfeit the deposit.",
+                &[("hours", &hours)],
+            )),
+        }
+    }
+}
+
+pub fn strike_label(kind: &str, locale: Locale) -> &'static str {
+    locale.t(match kind {
+        STRIKE_NO_SHOW => "No-show",
+        STRIKE_LATE_CANCELLATION => "Late cancellation",
+        _ => "Strike",
+    })
+}
+
+/// Late cancellations cut by the client; an appointment only ever carries one strike.
+pub async fn late_cancellations(state: &AppState, appointment_ids: &[String]) -> Vec<String> {
+    let now = Utc::now();
+    let mut late = Vec::new();
+    for id in appointment_ids {
+        let scheduled_for = sqlx::query_scalar::<_, String>("SELECT scheduled_for FROM appointments WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&state.db)
+            .await
+            .unwrap_or(None);
+        if scheduled_for.is_some_and(|value| state.policy.is_late(&value, now)) {
+            late.push(id.clone());
+        }
+    }
+    late
+}
+
+/// Records the strike and the fee the business keeps for it. Call before `payments::settle`,
+/// which collects or refunds the difference.
+pub async fn record_strike(state: &AppState, appointment_id: &str, kind: &str) -> Result<(), sqlx::Error> {
+    let client = sqlx::query_as::<_, (Option<String>, String)>("SELECT client_id, client_name FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(&state.db)
+        .await?;
+    let Some((client_id, client_name)) = client else {
+        return Ok(());
+    };
+    let fee_cents = match state.policy.penalty {
+        Penalty::None => 0,
+        Penalty::Fee(cents) => cents,
+        Penalty::Deposit => payments::net_paid(&state.db, appointment_id).await.max(0),
+    };
+
+    let inserted = sqlx::query(
+        r#"INSERT INTO client_strikes (id, client_id, appointment_id, kind, fee_cents, created_at)
+           VALUES (?, ?, ?, ?, ?, ?)
+           ON CONFLICT(appointment_id) DO NOTHING"#,
+    )
+    .bind(new_id())
+    .bind(&client_id)
+    .bind(appointment_id)
+    .bind(kind)
+    .bind(fee_cents)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await?;
+    if inserted.rows_affected() == 0 {
+        return Ok(());
+    }
+
+    let message = if fee_cents > 0 {
+        format!("{} for {client_name}; {} kept.", strike_label(kind, Locale::default()), format_money(fee_cents))
+    } else {
+        format!("{} for {client_name}.", strike_label(kind, Locale::default()))
+    };
+    log_activity(&state.db, "client_strike", &message, None, Some(appointment_id)).await;
+    Ok(())
+}
+
+/// Drops the strike when the appointment no longer warrants it, e.g. a no-show corrected by an admin.
+pub async fn clear_strike(pool: &SqlitePool, appointment_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM client_strikes WHERE appointment_id = ?")
+        .bind(appointment_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// The fee a strike lets the business keep; zero when the appointment has none.
+pub async fn retained_cents(pool: &SqlitePool, appointment_id: &str) -> i64 {
+    sqlx::query_scalar::<_, i64>("SELECT fee_cents FROM client_strikes WHERE appointment_id = ?")
+        .bind(appointment_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .unwrap_or(0)
+}
+
+pub async fn fetch_strikes(pool: &SqlitePool, client_id: &str) -> Vec<ClientStrikeRow> {
+    sqlx::query_as::<_, ClientStrikeRow>(
+        r#"SELECT s.id, s.appointment_id, s.kind, s.fee_cents, s.created_at, a.scheduled_for
+           FROM client_strikes s
+           JOIN appointments a ON a.id = s.appointment_id
+           WHERE s.client_id = ?
+           ORDER BY s.created_at DESC"#,
+    )
+    .bind(client_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn no_show_count(pool: &SqlitePool, client_id: &str) -> i64 {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM client_strikes WHERE client_id = ? AND kind = ?")
+        .bind(client_id)
+        .bind(STRIKE_NO_SHOW)
+        .fetch_one(pool)
+        .await
+        .unwrap_or(0)
+}
+
+pub async fn requires_prepayment(pool: &SqlitePool, config: &PolicyConfig, client_id: &str) -> bool {
+    config.max_no_shows > 0 && no_show_count(pool, client_id).await >= config.max_no_shows
+}
@@ -0,0 +1,142 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    geo::{self, haversine_km},
+    line_items::NewItem,
+    models::{format_money, service_price_cents, AddOnOption, QuoteRow},
+    state::PricingConfig,
+};
+
+#[derive(Clone, Debug)]
+pub struct Quote {
+    pub services_cents: i64,
+    pub addons_cents: i64,
+    pub travel_km: Option<f64>,
+    pub travel_cents: i64,
+    pub total_cents: i64,
+}
+
+impl PricingConfig {
+    /// The first `travel_free_km` are included; every started kilometre after that is charged, up to the cap.
+    pub fn travel_surcharge(&self, distance_km: Option<f64>) -> i64 {
+        let Some(distance_km) = distance_km else {
+            return 0;
+        };
+        let billable_km = (distance_km - self.travel_free_km).max(0.0).ceil() as i64;
+        (billable_km * self.travel_per_km_cents).min(self.travel_max_cents)
+    }
+}
+
+/// Distance from the preferred barber's base, or from the closest barber who travels there.
+/// `None` when the address or every base is unknown, in which case no surcharge applies.
+pub async fn travel_distance_km(
+    pool: &SqlitePool,
+    preferred_barber: Option<&str>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> Option<f64> {
+    let destination = latitude.zip(longitude)?;
+    let reach = geo::fetch_barber_reach(pool).await.unwrap_or_default();
+    reach
+        .iter()
+        .filter(|barber| preferred_barber.is_none_or(|id| barber.id == id))
+        .filter(|barber| barber.covers(destination.0, destination.1))
+        .filter_map(|barber| barber.base_latitude.zip(barber.base_longitude))
+        .map(|base| haversine_km(base, destination))
+        .min_by(|a, b| a.total_cmp(b))
+        .map(|km| (km * 10.0).round() / 10.0)
+}
+
+pub fn build_quote(
+    config: &PricingConfig,
+    items: &[NewItem],
+    addons: &[AddOnOption],
+    travel_km: Option<f64>,
+) -> Quote {
+    let services_cents = items.iter().map(|item| service_price_cents(&item.service)).sum();
+    let addons_cents = addons.iter().map(|addon| addon.price_cents).sum();
+    let travel_cents = config.travel_surcharge(travel_km);
+    Quote {
+        services_cents,
+        addons_cents,
+        travel_km,
+        travel_cents,
+        total_cents: services_cents + addons_cents + travel_cents,
+    }
+}
+
+pub async fn save_quote(
+    pool: &SqlitePool,
+    appointment_id: &str,
+    quote: &Quote,
+    addons: &[AddOnOption],
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now().to_rfc3339();
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"UPDATE appointments
+           SET services_cents = ?, addons_cents = ?, travel_km = ?, travel_cents = ?, quote_cents = ?
+           WHERE id = ?"#,
+    )
+    .bind(quote.services_cents)
+    .bind(quote.addons_cents)
+    .bind(quote.travel_km)
+    .bind(quote.travel_cents)
+    .bind(quote.total_cents)
+    .bind(appointment_id)
+    .execute(&mut *tx)
+    .await?;
+    for addon in addons {
+        sqlx::query(
+            r#"INSERT INTO appointment_addons (id, appointment_id, addon_key, name, price_cents, created_at)
+               VALUES (?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(new_id())
+        .bind(appointment_id)
+        .bind(addon.key)
+        .bind(addon.name)
+        .bind(addon.price_cents)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+/// `None` for appointments booked before pricing existed.
+pub async fn fetch_quote(pool: &SqlitePool, appointment_id: &str) -> Option<QuoteRow> {
+    sqlx::query_as::<_, QuoteRow>(
+        r#"SELECT services_cents, addons_cents, travel_km, travel_cents, quote_cents
+           FROM appointments
+           WHERE id = ? AND quote_cents IS NOT NULL"#,
+    )
+    .bind(appointment_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+pub async fn fetch_addon_names(pool: &SqlitePool, appointment_id: &str) -> Vec<String> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT name FROM appointment_addons WHERE appointment_id = ? ORDER BY name",
+    )
+    .bind(appointment_id)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// One-line summary for cards, e.g. "€53.00 (incl. €8.00 travel)".
+pub fn summary(quote: &QuoteRow) -> String {
+    if quote.travel_cents > 0 {
+        format!(
+            "{} (incl. {} travel)",
+            format_money(quote.quote_cents),
+            format_money(quote.travel_cents)
+        )
+    } else {
+        format_money(quote.quote_cents)
+    }
+}
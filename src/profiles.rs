@@ -0,0 +1,138 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::models::{BarberProfileRow, ProfileDraftRow, PROFILE_PENDING, PROFILE_REJECTED};
+
+pub const MAX_BIO_CHARS: usize = 1200;
+
+const PROFILE_COLUMNS: &str = "bio, specialties, languages, services, service_area, photo_path";
+
+/// Comma- or newline-separated profile lists, e.g. specialties and languages.
+pub fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn join_list(items: &[String]) -> Option<String> {
+    let items: Vec<&str> = items
+        .iter()
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .collect();
+    (!items.is_empty()).then(|| items.join(", "))
+}
+
+/// The approved profile clients see.
+pub async fn fetch_published(pool: &SqlitePool, barber_id: &str) -> Option<BarberProfileRow> {
+    sqlx::query_as::<_, BarberProfileRow>(&format!(
+        "SELECT {PROFILE_COLUMNS} FROM barber_profiles WHERE barber_id = ?"
+    ))
+    .bind(barber_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+pub async fn fetch_draft(pool: &SqlitePool, barber_id: &str) -> Option<ProfileDraftRow> {
+    sqlx::query_as::<_, ProfileDraftRow>(&format!(
+        "SELECT {PROFILE_COLUMNS}, status, review_note, submitted_at FROM barber_profile_drafts WHERE barber_id = ?"
+    ))
+    .bind(barber_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+/// Barbers with profile edits waiting for approval, oldest first.
+pub async fn pending_barbers(pool: &SqlitePool) -> Vec<(String, String)> {
+    sqlx::query_as::<_, (String, String)>(
+        r#"SELECT d.barber_id, u.display_name
+           FROM barber_profile_drafts d
+           JOIN users u ON u.id = d.barber_id
+           WHERE d.status = ?
+           ORDER BY d.submitted_at"#,
+    )
+    .bind(PROFILE_PENDING)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// Replaces the barber's draft; it stays off the public page until an admin approves it.
+pub async fn submit_draft(pool: &SqlitePool, barber_id: &str, profile: &BarberProfileRow) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"INSERT INTO barber_profile_drafts
+           (barber_id, bio, specialties, languages, services, service_area, photo_path, status, review_note, submitted_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, NULL, ?)
+           ON CONFLICT(barber_id) DO UPDATE SET
+             bio = excluded.bio, specialties = excluded.specialties, languages = excluded.languages,
+             services = excluded.services, service_area = excluded.service_area, photo_path = excluded.photo_path,
+             status = excluded.status, review_note = NULL, submitted_at = excluded.submitted_at"#,
+    )
+    .bind(barber_id)
+    .bind(&profile.bio)
+    .bind(&profile.specialties)
+    .bind(&profile.languages)
+    .bind(&profile.services)
+    .bind(&profile.service_area)
+    .bind(&profile.photo_path)
+    .bind(PROFILE_PENDING)
+    .bind(Utc::now().to_rfc3339())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Publishes the pending draft. Returns the photo it replaced, if any, so the caller can delete the file.
+pub async fn approve(pool: &SqlitePool, barber_id: &str) -> Result<Option<String>, sqlx::Error> {
+    let Some(draft) = fetch_draft(pool, barber_id).await.filter(|draft| draft.status == PROFILE_PENDING) else {
+        return Ok(None);
+    };
+    let previous_photo = fetch_published(pool, barber_id).await.and_then(|profile| profile.photo_path);
+    let profile = draft.profile;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO barber_profiles
+           (barber_id, bio, specialties, languages, services, service_area, photo_path, approved_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+           ON CONFLICT(barber_id) DO UPDATE SET
+             bio = excluded.bio, specialties = excluded.specialties, languages = excluded.languages,
+             services = excluded.services, service_area = excluded.service_area, photo_path = excluded.photo_path,
+             approved_at = excluded.approved_at"#,
+    )
+    .bind(barber_id)
+    .bind(&profile.bio)
+    .bind(&profile.specialties)
+    .bind(&profile.languages)
+    .bind(&profile.services)
+    .bind(&profile.service_area)
+    .bind(&profile.photo_path)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM barber_profile_drafts WHERE barber_id = ?")
+        .bind(barber_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(previous_photo.filter(|photo| profile.photo_path.as_deref() != Some(photo.as_str())))
+}
+
+/// Sends the draft back to the barber with a note; the published profile is untouched.
+pub async fn reject(pool: &SqlitePool, barber_id: &str, note: Option<&str>) -> Result<bool, sqlx::Error> {
+    sqlx::query("UPDATE barber_profile_drafts SET status = ?, review_note = ? WHERE barber_id = ? AND status = ?")
+        .bind(PROFILE_REJECTED)
+        .bind(note.map(str::trim).filter(|value| !value.is_empty()))
+        .bind(barber_id)
+        .bind(PROFILE_PENDING)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
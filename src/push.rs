@@ -98,6 +98,34 @@ pub async fn notify_appointment(
     }
 }
 
+/// Sends to a subscription kept outside `push_subscriptions`, e.g. one saved with a waitlist entry.
+pub async fn notify_subscription(state: &AppState, raw_subscription: &str, title: &str, body: &str, url: &str) {
+    if !state.push.enabled() {
+        return;
+    }
+    let subscription: PushSubscriptionInput = match serde_json::from_str(raw_subscription) {
+        Ok(value) => value,
+        Err(err) => {
+            log::warn!("Invalid push subscription payload: {err}");
+            return;
+        }
+    };
+    let row = PushSubscriptionRow {
+        endpoint: subscription.endpoint,
+        p256dh: subscription.keys.p256dh,
+        auth: subscription.keys.auth,
+    };
+    let payload = serde_json::json!({
+        "title": title,
+        "body": body,
+        "url": url
+    })
+    .to_string();
+    if let Err(err) = send_push(&state.push, row, &payload).await {
+        log::warn!("Push send failed: {err}");
+    }
+}
+
 async fn send_push(
     config: &PushConfig,
     row: PushSubscriptionRow,
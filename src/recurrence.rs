@@ -0,0 +1,380 @@
+use chrono::{DateTime, Duration, Months, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::new_id,
+    i18n::Locale,
+    line_items,
+    models::{STATUS_ACCEPTED, STATUS_CANCELLED, STATUS_PENDING},
+    schedule::{self, parse_scheduled_for},
+};
+
+/// Series are generated up front, so they are capped to keep the calendar manageable.
+pub const MAX_OCCURRENCES: u32 = 26;
+pub const MAX_SERIES_MONTHS: u32 = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Weekly,
+    Biweekly,
+    Monthly,
+}
+
+impl Frequency {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "weekly" => Some(Frequency::Weekly),
+            "biweekly" => Some(Frequency::Biweekly),
+            "monthly" => Some(Frequency::Monthly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Weekly => "weekly",
+            Frequency::Biweekly => "biweekly",
+            Frequency::Monthly => "monthly",
+        }
+    }
+
+    fn step(&self, start: NaiveDateTime, index: u32) -> Option<NaiveDateTime> {
+        match self {
+            Frequency::Weekly => start.checked_add_signed(Duration::weeks(index as i64)),
+            Frequency::Biweekly => start.checked_add_signed(Duration::weeks(2 * index as i64)),
+            // Months are added to the original start so a series on the 31st doesn't drift to the 28th.
+            Frequency::Monthly => start.checked_add_months(Months::new(index)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum RecurrenceEnd {
+    Until(NaiveDate),
+    Count(u32),
+}
+
+/// Which occurrences an edit or cancellation applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Occurrence,
+    Series,
+}
+
+impl Scope {
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            Some("series") => Scope::Series,
+            _ => Scope::Occurrence,
+        }
+    }
+}
+
+/// Every start time in the series, including `start` itself. Works on wall-clock times, so a
+/// 10:00 visit stays at 10:00 when the clocks change between occurrences.
+pub fn occurrences(start: NaiveDateTime, frequency: Frequency, end: RecurrenceEnd) -> Vec<NaiveDateTime> {
+    let limit = match end {
+        RecurrenceEnd::Count(count) => count.min(MAX_OCCURRENCES),
+        RecurrenceEnd::Until(_) => MAX_OCCURRENCES,
+    };
+    let horizon = start.checked_add_months(Months::new(MAX_SERIES_MONTHS));
+    (0..limit)
+        .map_while(|index| frequency.step(start, index))
+        .take_while(|when| match end {
+            RecurrenceEnd::Until(until) => when.date() <= until,
+            RecurrenceEnd::Count(_) => true,
+        })
+        .take_while(|when| horizon.is_none_or(|horizon| *when <= horizon))
+        .collect()
+}
+
+/// Turns an existing appointment into the first occurrence of a series and books the rest, repeating
+/// the wall-clock time in `zone`. Returns the ids of the generated appointments.
+pub async fn create_series(
+    pool: &SqlitePool,
+    appointment_id: &str,
+    frequency: Frequency,
+    end: RecurrenceEnd,
+    zone: Tz,
+) -> Result<Vec<String>, sqlx::Error> {
+    let Some((scheduled_for, client_id)) = sqlx::query_as::<_, (String, Option<String>)>(
+        "SELECT scheduled_for, client_id FROM appointments WHERE id = ?",
+    )
+    .bind(appointment_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(Vec::new());
+    };
+    let Some(start) = parse_scheduled_for(&scheduled_for) else {
+        return Ok(Vec::new());
+    };
+    let start = schedule::wall_clock(start, zone);
+
+    let series_id = new_id();
+    let now = Utc::now().to_rfc3339();
+    let (until, count) = match end {
+        RecurrenceEnd::Until(until) => (Some(until.to_string()), None),
+        RecurrenceEnd::Count(count) => (None, Some(count as i64)),
+    };
+
+    let subscriptions = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT endpoint, p256dh, auth FROM push_subscriptions WHERE appointment_id = ?",
+    )
+    .bind(appointment_id)
+    .fetch_all(pool)
+    .await?;
+
+    let items = line_items::fetch_items(pool, appointment_id).await;
+    let addons = sqlx::query_as::<_, (String, String, i64)>(
+        "SELECT addon_key, name, price_cents FROM appointment_addons WHERE appointment_id = ?",
+    )
+    .bind(appointment_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        r#"INSERT INTO appointment_series (id, client_id, frequency, until_date, occurrence_count, created_at)
+           VALUES (?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&series_id)
+    .bind(client_id)
+    .bind(frequency.as_str())
+    .bind(until)
+    .bind(count)
+    .bind(&now)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE appointments SET series_id = ?, series_index = 0 WHERE id = ?")
+        .bind(&series_id)
+        .bind(appointment_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut created = Vec::new();
+    for (index, when) in occurrences(start, frequency, end).into_iter().enumerate().skip(1) {
+        let child_id = new_id();
+        sqlx::query(
+            r#"INSERT INTO appointments
+               (id, client_name, client_phone, client_email, address, service, notes, requested_at,
+                scheduled_for, status, barber_id, latitude, longitude, outside_area, client_id,
+                location_id, series_id, series_index, duration_minutes,
+                services_cents, addons_cents, travel_km, travel_cents, quote_cents, locale)
+               SELECT ?, client_name, client_phone, client_email, address, service, notes, ?,
+                      ?, ?, barber_id, latitude, longitude, outside_area, client_id,
+                      location_id, series_id, ?, duration_minutes,
+                      services_cents, addons_cents, travel_km, travel_cents, quote_cents, locale
+               FROM appointments
+               WHERE id = ?"#,
+        )
+        .bind(&child_id)
+        .bind(&now)
+        .bind(schedule::to_stored(schedule::localize(when, zone)))
+        .bind(STATUS_PENDING)
+        .bind(index as i64)
+        .bind(appointment_id)
+        .execute(&mut *tx)
+        .await?;
+
+        for (addon_key, name, price_cents) in &addons {
+            sqlx::query(
+                r#"INSERT INTO appointment_addons (id, appointment_id, addon_key, name, price_cents, created_at)
+                   VALUES (?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(new_id())
+            .bind(&child_id)
+            .bind(addon_key)
+            .bind(name)
+            .bind(price_cents)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for (position, item) in items.iter().enumerate() {
+            sqlx::query(
+                r#"INSERT INTO appointment_items
+                   (id, appointment_id, position, person_name, service, duration_minutes, price_cents, barber_id,
+                    created_at)
+                   VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(new_id())
+            .bind(&child_id)
+            .bind(position as i64)
+            .bind(&item.person_name)
+            .bind(&item.service)
+            .bind(item.duration_minutes)
+            .bind(item.price_cents)
+            .bind(&item.barber_id)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Reuse the browser push subscription from the original booking.
+        for (endpoint, p256dh, auth) in &subscriptions {
+            sqlx::query(
+                r#"INSERT INTO push_subscriptions (id, appointment_id, endpoint, p256dh, auth, created_at)
+                   VALUES (?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(new_id())
+            .bind(&child_id)
+            .bind(endpoint)
+            .bind(p256dh)
+            .bind(auth)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        created.push(child_id);
+    }
+    tx.commit().await?;
+
+    Ok(created)
+}
+
+/// The appointment itself, or it plus every later occurrence that hasn't happened or been closed yet.
+pub async fn affected_ids(pool: &SqlitePool, appointment_id: &str, scope: Scope) -> Vec<String> {
+    if scope == Scope::Occurrence {
+        return vec![appointment_id.to_string()];
+    }
+    let series = sqlx::query_as::<_, (Option<String>, Option<i64>)>(
+        "SELECT series_id, series_index FROM appointments WHERE id = ?",
+    )
+    .bind(appointment_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None);
+    let Some((Some(series_id), Some(series_index))) = series else {
+        return vec![appointment_id.to_string()];
+    };
+
+    let mut ids = sqlx::query_scalar::<_, String>(
+        r#"SELECT id FROM appointments
+           WHERE series_id = ? AND series_index >= ? AND id != ? AND status IN (?, ?)
+           ORDER BY series_index"#,
+    )
+    .bind(&series_id)
+    .bind(series_index)
+    .bind(appointment_id)
+    .bind(STATUS_PENDING)
+    .bind(STATUS_ACCEPTED)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+    ids.insert(0, appointment_id.to_string());
+    ids
+}
+
+pub async fn cancel(pool: &SqlitePool, appointment_id: &str, scope: Scope) -> Result<Vec<String>, sqlx::Error> {
+    let ids = affected_ids(pool, appointment_id, scope).await;
+    for id in &ids {
+        sqlx::query("UPDATE appointments SET status = ? WHERE id = ?")
+            .bind(STATUS_CANCELLED)
+            .bind(id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(ids)
+}
+
+/// Where each visit `reschedule` would touch ends up, without saving anything.
+pub async fn planned_moves(
+    pool: &SqlitePool,
+    appointment_id: &str,
+    new_start: DateTime<Utc>,
+    scope: Scope,
+    zone: Tz,
+) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error> {
+    let current = sqlx::query_scalar::<_, String>("SELECT scheduled_for FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(pool)
+        .await?
+        .and_then(|value| parse_scheduled_for(&value));
+    let Some(current) = current else {
+        return Ok(Vec::new());
+    };
+    let delta = schedule::wall_clock(new_start, zone) - schedule::wall_clock(current, zone);
+
+    let mut moves = Vec::new();
+    for id in affected_ids(pool, appointment_id, scope).await {
+        let Some(start) = sqlx::query_scalar::<_, String>("SELECT scheduled_for FROM appointments WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(pool)
+            .await?
+            .and_then(|value| parse_scheduled_for(&value))
+        else {
+            continue;
+        };
+        moves.push((id, schedule::localize(schedule::wall_clock(start, zone) + delta, zone)));
+    }
+    Ok(moves)
+}
+
+/// Moves the appointment to `new_start`; with `Scope::Series`, later occurrences shift by the same
+/// amount of wall-clock time in `zone`, so they keep their local hour across clock changes.
+pub async fn reschedule(
+    pool: &SqlitePool,
+    appointment_id: &str,
+    new_start: DateTime<Utc>,
+    scope: Scope,
+    reset_status: bool,
+    zone: Tz,
+) -> Result<Vec<String>, sqlx::Error> {
+    let moves = planned_moves(pool, appointment_id, new_start, scope, zone).await?;
+    let mut ids = Vec::new();
+    for (id, moved) in moves {
+        let moved = schedule::to_stored(moved);
+        if reset_status {
+            sqlx::query("UPDATE appointments SET scheduled_for = ?, status = ? WHERE id = ?")
+                .bind(moved)
+                .bind(STATUS_PENDING)
+                .bind(&id)
+                .execute(pool)
+                .await?;
+        } else {
+            sqlx::query("UPDATE appointments SET scheduled_for = ? WHERE id = ?")
+                .bind(moved)
+                .bind(&id)
+                .execute(pool)
+                .await?;
+        }
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+pub async fn reassign(
+    pool: &SqlitePool,
+    appointment_id: &str,
+    barber_id: Option<&str>,
+    scope: Scope,
+) -> Result<Vec<String>, sqlx::Error> {
+    let ids = affected_ids(pool, appointment_id, scope).await;
+    for id in &ids {
+        sqlx::query("UPDATE appointments SET barber_id = ? WHERE id = ?")
+            .bind(barber_id)
+            .bind(id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(ids)
+}
+
+pub fn describe(frequency: &str, until_date: Option<&str>, count: Option<i64>, locale: Locale) -> String {
+    let label = locale.t(match frequency {
+        "weekly" => "Every week",
+        "biweekly" => "Every two weeks",
+        "monthly" => "Every month",
+        other => other,
+    });
+    match (until_date, count) {
+        (Some(until), _) => locale.format("{label} until {until}", &[("label", &label), ("until", &until)]),
+        (None, Some(count)) => locale.format("{label}, {count} visits", &[("label", &label), ("count", &count)]),
+        _ => label.to_string(),
+    }
+}
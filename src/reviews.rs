@@ -0,0 +1,200 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    auth::new_id,
+    db::log_activity,
+    i18n,
+    messaging::{Channel, OutboundMessage},
+    models::{ReviewRow, REVIEW_APPROVED, REVIEW_PENDING, REVIEW_REQUESTED, STATUS_COMPLETED},
+    state::AppState,
+};
+
+pub const MAX_COMMENT_CHARS: usize = 1000;
+
+const REVIEW_COLUMNS: &str = r#"r.id, r.appointment_id, u.display_name as barber_name, r.client_name, r.rating, r.comment,
+    r.status, r.submitted_at"#;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RatingSummary {
+    pub average: f64,
+    pub count: i64,
+}
+
+impl RatingSummary {
+    /// e.g. "4.7 ★ (12 reviews)"; empty when nobody has been rated yet.
+    pub fn label(&self) -> String {
+        match self.count {
+            0 => String::new(),
+            1 => format!("{:.1} ★ (1 review)", self.average),
+            count => format!("{:.1} ★ ({count} reviews)", self.average),
+        }
+    }
+}
+
+pub fn stars(rating: i64) -> String {
+    let rating = rating.clamp(0, 5) as usize;
+    format!("{}{}", "★".repeat(rating), "☆".repeat(5 - rating))
+}
+
+/// Sends the client a review link once the visit is completed. Safe to call after every status change.
+pub async fn request(state: &AppState, appointment_id: &str) {
+    let appointment = sqlx::query_as::<_, (String, String, Option<String>, String, Option<String>)>(
+        "SELECT status, client_name, client_email, client_phone, barber_id FROM appointments WHERE id = ?",
+    )
+    .bind(appointment_id)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    let Some((status, client_name, client_email, client_phone, Some(barber_id))) = appointment else {
+        return;
+    };
+    if status != STATUS_COMPLETED {
+        return;
+    }
+
+    let token = Uuid::new_v4().simple().to_string();
+    let inserted = sqlx::query(
+        r#"INSERT INTO reviews (id, appointment_id, barber_id, client_name, token, status, requested_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?)
+           ON CONFLICT(appointment_id) DO NOTHING"#,
+    )
+    .bind(new_id())
+    .bind(appointment_id)
+    .bind(&barber_id)
+    .bind(&client_name)
+    .bind(&token)
+    .bind(REVIEW_REQUESTED)
+    .bind(Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map(|result| result.rows_affected() > 0)
+    .unwrap_or(false);
+    if !inserted {
+        return;
+    }
+
+    let (channel, to) = match client_email.filter(|email| !email.trim().is_empty()) {
+        Some(email) => (Channel::Email, email),
+        None => (Channel::Sms, client_phone),
+    };
+    let Some(base_url) = state.portal.base_url.as_deref() else {
+        log::error!("PUBLIC_BASE_URL is not set, so the review request for {appointment_id} wasn't sent");
+        return;
+    };
+    let locale = i18n::appointment_locale(&state.db, appointment_id).await;
+    let message = OutboundMessage {
+        channel,
+        to,
+        subject: locale.t("How was your Barber2Go visit?").to_string(),
+        body: locale.format(
+            "Hi {name}, thanks for your visit. Tell us how it went: {link}",
+            &[("name", &client_name), ("link", &format!("{base_url}/review/{token}"))],
+        ),
+    };
+    if let Err(err) = state.messages.send(&message).await {
+        log::warn!("Sending review request for {appointment_id} failed: {err}");
+    }
+}
+
+pub async fn fetch_by_token(pool: &SqlitePool, token: &str) -> Option<ReviewRow> {
+    sqlx::query_as::<_, ReviewRow>(&format!(
+        "SELECT {REVIEW_COLUMNS} FROM reviews r LEFT JOIN users u ON u.id = r.barber_id WHERE r.token = ?"
+    ))
+    .bind(token)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)
+}
+
+/// Stores the client's rating for moderation. Returns false when the link was already used.
+pub async fn submit(state: &AppState, token: &str, rating: i64, comment: Option<&str>) -> Result<bool, sqlx::Error> {
+    let appointment_id = sqlx::query_scalar::<_, String>(
+        r#"UPDATE reviews SET rating = ?, comment = ?, status = ?, submitted_at = ?
+           WHERE token = ? AND status = ?
+           RETURNING appointment_id"#,
+    )
+    .bind(rating.clamp(1, 5))
+    .bind(comment.map(str::trim).filter(|value| !value.is_empty()))
+    .bind(REVIEW_PENDING)
+    .bind(Utc::now().to_rfc3339())
+    .bind(token)
+    .bind(REVIEW_REQUESTED)
+    .fetch_optional(&state.db)
+    .await?;
+    let Some(appointment_id) = appointment_id else {
+        return Ok(false);
+    };
+    log_activity(
+        &state.db,
+        "review_submitted",
+        &format!("A {rating}-star review is waiting for moderation."),
+        None,
+        Some(&appointment_id),
+    )
+    .await;
+    Ok(true)
+}
+
+/// Submitted reviews, newest first, optionally limited to one status.
+pub async fn list(pool: &SqlitePool, status: Option<&str>) -> Vec<ReviewRow> {
+    sqlx::query_as::<_, ReviewRow>(&format!(
+        r#"SELECT {REVIEW_COLUMNS}
+           FROM reviews r
+           LEFT JOIN users u ON u.id = r.barber_id
+           WHERE r.status != ? AND (? IS NULL OR r.status = ?)
+           ORDER BY r.submitted_at DESC"#
+    ))
+    .bind(REVIEW_REQUESTED)
+    .bind(status)
+    .bind(status)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn for_barber(pool: &SqlitePool, barber_id: &str, status: Option<&str>, limit: i64) -> Vec<ReviewRow> {
+    sqlx::query_as::<_, ReviewRow>(&format!(
+        r#"SELECT {REVIEW_COLUMNS}
+           FROM reviews r
+           LEFT JOIN users u ON u.id = r.barber_id
+           WHERE r.barber_id = ? AND r.status != ? AND (? IS NULL OR r.status = ?)
+           ORDER BY r.submitted_at DESC
+           LIMIT ?"#
+    ))
+    .bind(barber_id)
+    .bind(REVIEW_REQUESTED)
+    .bind(status)
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+pub async fn set_status(pool: &SqlitePool, review_id: &str, status: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query("UPDATE reviews SET status = ? WHERE id = ? AND status != ?")
+        .bind(status)
+        .bind(review_id)
+        .bind(REVIEW_REQUESTED)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected() > 0)
+}
+
+/// Average of the barber's approved ratings.
+pub async fn summary_for(pool: &SqlitePool, barber_id: &str) -> RatingSummary {
+    sqlx::query_as::<_, (Option<f64>, i64)>(
+        "SELECT AVG(rating), COUNT(*) FROM reviews WHERE barber_id = ? AND status = ?",
+    )
+    .bind(barber_id)
+    .bind(REVIEW_APPROVED)
+    .fetch_one(pool)
+    .await
+    .map(|(average, count)| RatingSummary {
+        average: average.unwrap_or(0.0),
+        count,
+    })
+    .unwrap_or_default()
+}
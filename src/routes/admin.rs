@@ -1,20 +1,43 @@
-use actix_web::{http::header, middleware::from_fn, web, HttpResponse, Result};
+use actix_multipart::form::{bytes::Bytes, text::Text, MultipartForm};
+use actix_web::{http::header, middleware::from_fn, web, HttpRequest, HttpResponse, Result};
 use actix_web_httpauth::middleware::HttpAuthentication;
 use askama::Template;
+use chrono_tz::Tz;
 use serde::Deserialize;
 
 use crate::{
     auth::{admin_validator, hash_password, logout_guard, new_id, AuthUser},
+    cms::{self, BlockContent},
     db::{fetch_appointment_event, log_activity},
+    geo,
+    i18n::{self, Locale},
+    invoices, line_items,
+    media::{self, MediaItem},
     models::{
-        ActivityRow, AppointmentRow, CmsBlockRow, ServiceOption, UserRow, ROLE_ADMIN,
-        ROLE_BARBER, STATUS_ACCEPTED, STATUS_COMPLETED, STATUS_DECLINED, STATUS_PENDING,
+        format_decimal, format_money, parse_money, service_minutes, ActivityRow, AppointmentRow, BarberProfileRow, ClientRow, CmsBlockRow, CmsPageRow, CmsRevisionRow, ReviewRow, ServiceAreaRow, UserRow,
+        AREA_KIND_POLYGON, AREA_KIND_RADIUS, CMS_FORMAT_HTML, CMS_FORMAT_MARKDOWN, REVIEW_APPROVED, REVIEW_PENDING, REVIEW_REJECTED, ROLE_ADMIN, ROLE_BARBER, STATUS_ACCEPTED,
+        STATUS_CANCELLED, STATUS_COMPLETED, STATUS_DECLINED, STATUS_NO_SHOW, STATUS_PENDING, STRIKE_NO_SHOW,
     },
-    push,
+    pages::{self, PageInput},
+    payments, policy,
+    payouts::{self, Period},
+    pricing, profiles, push,
+    recurrence::{self, Scope},
+    reviews,
+    routes::LanguageLink,
+    sanitize::sanitize_html,
+    schedule,
     state::{AppState, ServerEvent},
     templates::render,
+    validation, waitlist,
 };
 
+/// Default `src` for images inserted into CMS blocks; `srcset` lets browsers pick another copy.
+const CMS_IMAGE_WIDTH: i64 = 640;
+
+/// Revisions listed under each block in the CMS editor.
+const CMS_HISTORY_LIMIT: i64 = 20;
+
 #[derive(Clone, Debug)]
 struct StatCard {
     label: String,
@@ -78,11 +101,69 @@ struct AdminAppointmentsTemplate {
 #[template(path = "admin_appointment_detail.html")]
 struct AdminAppointmentDetailTemplate {
     appointment: AppointmentView,
+    /// The visit time for the reschedule input, in the business's zone.
+    scheduled_input: String,
+    zone: String,
     barbers: Vec<BarberView>,
     statuses: Vec<StatusOption>,
+    outside_area: bool,
+    offers: Vec<OfferView>,
+    client_id: String,
+    series_summary: String,
+    series: Vec<SeriesOccurrenceView>,
+    items: Vec<LineItemView>,
+    duration_minutes: i64,
+    quote_lines: Vec<QuoteLineView>,
+    quote_total: String,
+    payments: Vec<PaymentView>,
+    invoice_number: String,
+    invoice_total: String,
+    tip: String,
     is_admin: bool,
 }
 
+#[derive(Clone, Debug)]
+struct PaymentView {
+    kind: String,
+    status: String,
+    amount: String,
+    provider: String,
+    reference: String,
+    error: String,
+    created_at: String,
+}
+
+#[derive(Clone, Debug)]
+struct LineItemView {
+    id: String,
+    person_name: String,
+    service: String,
+    minutes: i64,
+    price: String,
+    barber_id: String,
+}
+
+#[derive(Clone, Debug)]
+struct QuoteLineView {
+    label: String,
+    amount: String,
+}
+
+#[derive(Clone, Debug)]
+struct SeriesOccurrenceView {
+    id: String,
+    scheduled_for: String,
+    status: String,
+    current: bool,
+}
+
+#[derive(Clone, Debug)]
+struct OfferView {
+    barber_name: String,
+    status: String,
+    offered_at: String,
+}
+
 #[derive(Clone, Debug)]
 struct StatusOption {
     value: &'static str,
@@ -93,6 +174,7 @@ struct StatusOption {
 #[template(path = "admin_barbers.html")]
 struct AdminBarbersTemplate {
     barbers: Vec<BarberView>,
+    pending_profiles: Vec<(String, String)>,
     errors: Vec<String>,
     success: String,
     has_success: bool,
@@ -105,16 +187,359 @@ struct AdminBarberStatsTemplate {
     barber: BarberView,
     stats: Vec<StatCard>,
     recent: Vec<AppointmentView>,
+    travel: TravelView,
+    commission_percent: String,
+    default_commission: String,
+    timezone: String,
+    business_timezone: String,
+    timezones: Vec<&'static str>,
+    rating: String,
+    reviews: Vec<ReviewView>,
+    profile: ProfileView,
+    profile_draft: Option<ProfileDraftView>,
+    is_admin: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct ProfileView {
+    bio: String,
+    specialties: String,
+    languages: String,
+    services: String,
+    service_area: String,
+    photo_path: String,
+}
+
+impl From<BarberProfileRow> for ProfileView {
+    fn from(profile: BarberProfileRow) -> Self {
+        Self {
+            bio: profile.bio.unwrap_or_default(),
+            specialties: profile.specialties.unwrap_or_default(),
+            languages: profile.languages.unwrap_or_default(),
+            services: profile.services.unwrap_or_default(),
+            service_area: profile.service_area.unwrap_or_default(),
+            photo_path: profile.photo_path.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ProfileDraftView {
+    profile: ProfileView,
+    status: String,
+    review_note: String,
+    submitted_on: String,
+}
+
+#[derive(Deserialize)]
+struct ProfileRejectForm {
+    note: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct TravelView {
+    base_latitude: String,
+    base_longitude: String,
+    travel_radius_km: String,
+}
+
+#[derive(Clone, Debug)]
+struct ServiceAreaView {
+    id: String,
+    name: String,
+    kind: String,
+    summary: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin_areas.html")]
+struct AdminAreasTemplate {
+    areas: Vec<ServiceAreaView>,
+    errors: Vec<String>,
+    success: String,
+    has_success: bool,
+    is_admin: bool,
+}
+
+#[derive(Clone, Debug)]
+struct ClientView {
+    id: String,
+    name: String,
+    phone: String,
+    email: String,
+    address: String,
+    notes: String,
+    created_at: String,
+    visits: i64,
+    last_visit: String,
+    strikes: i64,
+}
+
+#[derive(Clone, Debug)]
+struct StrikeView {
+    id: String,
+    appointment_id: String,
+    label: &'static str,
+    scheduled_for: String,
+    fee: String,
+    recorded_at: String,
+}
+
+#[derive(Clone, Debug)]
+struct InvoiceView {
+    number: String,
+    appointment_id: String,
+    client_name: String,
+    barber_name: String,
+    issued_on: String,
+    total: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin_invoices.html")]
+struct AdminInvoicesTemplate {
+    invoices: Vec<InvoiceView>,
+    month: String,
+    invoiced_total: String,
+    is_admin: bool,
+}
+
+#[derive(Clone, Debug)]
+struct WaitlistEntryView {
+    id: String,
+    client_name: String,
+    contact: String,
+    service: String,
+    wanted_date: String,
+    barber_name: String,
+    offer_expires_at: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin_waitlist.html")]
+struct AdminWaitlistTemplate {
+    entries: Vec<WaitlistEntryView>,
+    claim_minutes: i64,
+    is_admin: bool,
+}
+
+#[derive(Clone, Debug)]
+struct ReviewView {
+    id: String,
+    appointment_id: String,
+    barber_name: String,
+    client_name: String,
+    stars: String,
+    comment: String,
+    status: String,
+    submitted_on: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin_reviews.html")]
+struct AdminReviewsTemplate {
+    reviews: Vec<ReviewView>,
+    status: String,
+    is_admin: bool,
+}
+
+#[derive(Deserialize)]
+struct ReviewFilter {
+    status: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReviewStatusForm {
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct InvoiceFilter {
+    month: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct PayoutLineView {
+    period: String,
+    barber_name: String,
+    visits: i64,
+    gross: String,
+    commission: String,
+    tips: String,
+    net: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin_payouts.html")]
+struct AdminPayoutsTemplate {
+    lines: Vec<PayoutLineView>,
+    total: PayoutLineView,
+    period: String,
+    from: String,
+    to: String,
+    barbers: Vec<BarberView>,
+    csv_query: String,
+    is_admin: bool,
+}
+
+#[derive(Deserialize)]
+struct PayoutQuery {
+    period: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    barber_id: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin_clients.html")]
+struct AdminClientsTemplate {
+    clients: Vec<ClientView>,
+    search: String,
+    is_admin: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin_client_detail.html")]
+struct AdminClientDetailTemplate {
+    client: ClientView,
+    stats: Vec<StatCard>,
+    lifetime_value: String,
+    appointments: Vec<AppointmentView>,
+    strikes: Vec<StrikeView>,
+    requires_prepayment: bool,
+    is_admin: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin_media.html")]
+struct AdminMediaTemplate {
+    items: Vec<MediaItem>,
+    errors: Vec<String>,
+    max_upload_mb: usize,
     is_admin: bool,
 }
 
+#[derive(MultipartForm)]
+struct MediaUpload {
+    file: Bytes,
+    alt_text: Option<Text<String>>,
+}
+
+#[derive(Deserialize)]
+struct MediaAltForm {
+    alt_text: String,
+}
+
 #[derive(Template)]
 #[template(path = "admin_cms.html")]
 struct AdminCmsTemplate {
-    blocks: Vec<CmsBlockRow>,
+    blocks: Vec<CmsBlockView>,
+    languages: Vec<LanguageLink>,
+    translating: bool,
+    errors: Vec<String>,
+    is_admin: bool,
+}
+
+struct CmsBlockView {
+    key: String,
+    title: String,
+    /// What the editor opens with: the draft when there is one, otherwise the live copy.
+    html: String,
+    format: String,
+    /// Markdown source for Markdown blocks.
+    source: String,
+    /// A translation that was never published; the editor starts from the original.
+    untranslated: bool,
+    draft: Option<CmsDraftView>,
+    revisions: Vec<CmsRevisionView>,
+    /// The business's zone, which publish times are typed and shown in.
+    zone: &'static str,
+}
+
+struct CmsDraftView {
+    saved: String,
+    publish_at: String,
+    /// `publish_at` in `datetime-local` form.
+    publish_at_input: String,
+}
+
+struct CmsRevisionView {
+    id: String,
+    kind: String,
+    created: String,
+    author: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin_cms_revision.html")]
+struct AdminCmsRevisionTemplate {
+    block_key: String,
+    block_title: String,
+    revision: CmsRevisionView,
+    diff: Vec<cms::DiffLine>,
+    preview: String,
+    is_live: bool,
+    back_url: String,
+    is_admin: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin_pages.html")]
+struct AdminPagesTemplate {
+    pages: Vec<CmsPageRow>,
+    form: PageFormView,
+    errors: Vec<String>,
+    is_admin: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin_page.html")]
+struct AdminPageTemplate {
+    page_id: String,
+    form: PageFormView,
+    blocks: Vec<CmsBlockView>,
+    languages: Vec<LanguageLink>,
+    translating: bool,
+    errors: Vec<String>,
     is_admin: bool,
 }
 
+#[derive(Default)]
+struct PageFormView {
+    title: String,
+    slug: String,
+    meta_description: String,
+    published: bool,
+    show_in_nav: bool,
+    nav_order: String,
+}
+
+#[derive(Deserialize)]
+struct PageForm {
+    title: String,
+    slug: Option<String>,
+    meta_description: Option<String>,
+    published: Option<String>,
+    show_in_nav: Option<String>,
+    nav_order: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CmsLocaleQuery {
+    locale: Option<String>,
+}
+
+impl CmsLocaleQuery {
+    fn locale(&self) -> Locale {
+        self.locale.as_deref().and_then(Locale::parse).unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize)]
+struct CmsScheduleForm {
+    publish_at: String,
+}
+
 #[derive(Deserialize)]
 struct AppointmentFilter {
     status: Option<String>,
@@ -125,6 +550,12 @@ struct AppointmentUpdateForm {
     status: String,
     barber_id: Option<String>,
     scheduled_for: Option<String>,
+    apply_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LineItemBarberForm {
+    barber_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -134,10 +565,63 @@ struct BarberCreateForm {
     password: String,
 }
 
+#[derive(Deserialize)]
+struct ServiceAreaForm {
+    name: String,
+    kind: String,
+    center_latitude: Option<String>,
+    center_longitude: Option<String>,
+    radius_km: Option<String>,
+    polygon: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BarberTravelForm {
+    base_latitude: Option<String>,
+    base_longitude: Option<String>,
+    travel_radius_km: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BarberCommissionForm {
+    commission_percent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BarberTimezoneForm {
+    timezone: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TipForm {
+    tip: String,
+}
+
+#[derive(Deserialize)]
+struct ClientFilter {
+    q: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClientNotesForm {
+    notes: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct CmsUpdatePayload {
     key: String,
+    #[serde(default)]
     html: String,
+    /// "html" (the default) or "markdown"; Markdown blocks send their source in `markdown`.
+    format: Option<String>,
+    markdown: Option<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Deserialize)]
+struct CmsPreviewPayload {
+    markdown: String,
 }
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
@@ -154,10 +638,52 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .route(web::get().to(appointment_detail))
                     .route(web::post().to(update_appointment)),
             )
+            .service(web::resource("/appointments/{id}/tip").route(web::post().to(update_tip)))
+            .service(
+                web::resource("/appointments/{id}/items/{item_id}")
+                    .route(web::post().to(update_line_item)),
+            )
             .service(web::resource("/barbers").route(web::get().to(list_barbers)).route(web::post().to(create_barber)))
             .service(web::resource("/barbers/{id}").route(web::get().to(barber_stats)))
+            .service(web::resource("/barbers/{id}/travel").route(web::post().to(update_barber_travel)))
+            .service(web::resource("/barbers/{id}/commission").route(web::post().to(update_barber_commission)))
+            .service(web::resource("/barbers/{id}/timezone").route(web::post().to(update_barber_timezone)))
+            .service(web::resource("/barbers/{id}/profile/approve").route(web::post().to(approve_barber_profile)))
+            .service(web::resource("/barbers/{id}/profile/reject").route(web::post().to(reject_barber_profile)))
+            .service(web::resource("/clients").route(web::get().to(list_clients)))
+            .service(web::resource("/clients/{id}").route(web::get().to(client_detail)))
+            .service(web::resource("/clients/{id}/notes").route(web::post().to(update_client_notes)))
+            .service(
+                web::resource("/clients/{id}/strikes/{strike_id}/delete")
+                    .route(web::post().to(remove_client_strike)),
+            )
+            .service(web::resource("/waitlist").route(web::get().to(list_waitlist)))
+            .service(web::resource("/waitlist/{id}/delete").route(web::post().to(remove_waitlist_entry)))
+            .service(web::resource("/reviews").route(web::get().to(list_reviews)))
+            .service(web::resource("/reviews/{id}/status").route(web::post().to(moderate_review)))
+            .service(web::resource("/invoices").route(web::get().to(list_invoices)))
+            .service(web::resource("/payouts").route(web::get().to(payouts_report)))
+            .service(web::resource("/payouts.csv").route(web::get().to(payouts_csv)))
+            .service(web::resource("/areas").route(web::get().to(list_areas)).route(web::post().to(create_area)))
+            .service(web::resource("/areas/{id}/delete").route(web::post().to(delete_area)))
             .service(web::resource("/cms").route(web::get().to(cms_editor)))
-            .service(web::resource("/cms/save").route(web::post().to(save_cms))),
+            .service(web::resource("/cms/save").route(web::post().to(save_cms)))
+            .service(web::resource("/cms/preview").route(web::post().to(preview_cms)))
+            .service(web::resource("/pages").route(web::get().to(list_pages)).route(web::post().to(create_page)))
+            .service(web::resource("/pages/{id}").route(web::get().to(show_page)).route(web::post().to(update_page)))
+            .service(web::resource("/pages/{id}/delete").route(web::post().to(delete_page)))
+            .service(web::resource("/pages/{id}/blocks").route(web::post().to(add_page_block)))
+            .service(web::resource("/pages/{id}/blocks/{key}/delete").route(web::post().to(remove_page_block)))
+            .service(web::resource("/cms/{key}/schedule").route(web::post().to(schedule_cms)))
+            .service(web::resource("/cms/{key}/discard").route(web::post().to(discard_cms_draft)))
+            .service(web::resource("/cms/{key}/revisions/{id}").route(web::get().to(show_cms_revision)))
+            .service(
+                web::resource("/cms/{key}/revisions/{id}/restore").route(web::post().to(restore_cms_revision)),
+            )
+            .service(web::resource("/media").route(web::get().to(media_library)).route(web::post().to(upload_media)))
+            .service(web::resource("/media.json").route(web::get().to(media_json)))
+            .service(web::resource("/media/{id}/alt").route(web::post().to(update_media_alt)))
+            .service(web::resource("/media/{id}/delete").route(web::post().to(delete_media))),
     );
 }
 
@@ -221,7 +747,8 @@ async fn dashboard(state: web::Data<AppState>, auth: web::ReqData<AuthUser>) ->
     .await
     .unwrap_or_default();
 
-    let upcoming = upcoming_rows.into_iter().map(to_view).collect();
+    let zone = state.time.business_zone;
+    let upcoming = upcoming_rows.into_iter().map(|row| to_view(row, zone)).collect();
 
     let activity_rows = sqlx::query_as::<_, ActivityRow>(
         "SELECT message, created_at FROM activities ORDER BY created_at DESC LIMIT 10",
@@ -282,7 +809,8 @@ async fn list_appointments(
         .unwrap_or_default()
     };
 
-    let appointments = rows.into_iter().map(to_view).collect();
+    let zone = state.time.business_zone;
+    let appointments = rows.into_iter().map(|row| to_view(row, zone)).collect();
 
     Ok(render(AdminAppointmentsTemplate {
         appointments,
@@ -311,8 +839,13 @@ async fn appointment_detail(
     .await
     .unwrap_or(None);
 
+    let zone = state.time.business_zone;
+    let scheduled_input = row
+        .as_ref()
+        .map(|row| schedule::input_value(&row.scheduled_for, zone))
+        .unwrap_or_default();
     let appointment = match row {
-        Some(row) => to_view(row),
+        Some(row) => to_view(row, zone),
         None => {
             return Ok(HttpResponse::NotFound().body("Appointment not found"));
         }
@@ -339,12 +872,173 @@ async fn appointment_detail(
             value: STATUS_COMPLETED,
             selected: appointment.status == STATUS_COMPLETED,
         },
+        StatusOption {
+            value: STATUS_CANCELLED,
+            selected: appointment.status == STATUS_CANCELLED,
+        },
+        StatusOption {
+            value: STATUS_NO_SHOW,
+            selected: appointment.status == STATUS_NO_SHOW,
+        },
     ];
 
+    let outside_area = sqlx::query_scalar::<_, i64>("SELECT outside_area FROM appointments WHERE id = ?")
+        .bind(&appointment.id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0)
+        == 1;
+
+    let client_id = sqlx::query_scalar::<_, Option<String>>("SELECT client_id FROM appointments WHERE id = ?")
+        .bind(&appointment.id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(None)
+        .unwrap_or_default();
+
+    let series_info = sqlx::query_as::<_, (String, String, Option<String>, Option<i64>)>(
+        r#"SELECT s.id, s.frequency, s.until_date, s.occurrence_count
+           FROM appointments a
+           JOIN appointment_series s ON s.id = a.series_id
+           WHERE a.id = ?"#,
+    )
+    .bind(&appointment.id)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    let (series_summary, series) = match series_info {
+        Some((series_id, frequency, until_date, count)) => {
+            let occurrences = sqlx::query_as::<_, (String, String, String)>(
+                "SELECT id, scheduled_for, status FROM appointments WHERE series_id = ? ORDER BY series_index",
+            )
+            .bind(&series_id)
+            .fetch_all(&state.db)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, scheduled_for, status)| SeriesOccurrenceView {
+                current: id == appointment.id,
+                id,
+                scheduled_for: schedule::display(&scheduled_for, zone),
+                status,
+            })
+            .collect();
+            (
+                recurrence::describe(&frequency, until_date.as_deref(), count, Locale::default()),
+                occurrences,
+            )
+        }
+        None => (String::new(), Vec::new()),
+    };
+
+    let line_items = line_items::fetch_items(&state.db, &appointment.id).await;
+    let duration_minutes = line_items::chair_minutes(
+        &line_items,
+        Some(appointment.barber_id.as_str()).filter(|id| !id.is_empty()),
+    );
+    let items = line_items
+        .into_iter()
+        .map(|item| LineItemView {
+            minutes: item.minutes(),
+            price: item.price_cents.map(format_money).unwrap_or_default(),
+            id: item.id,
+            person_name: item.person_name,
+            service: item.service,
+            barber_id: item.barber_id.unwrap_or_default(),
+        })
+        .collect();
+
+    let quote = pricing::fetch_quote(&state.db, &appointment.id).await;
+    let mut quote_lines = Vec::new();
+    if let Some(quote) = &quote {
+        quote_lines.push(QuoteLineView {
+            label: "Services".to_string(),
+            amount: format_money(quote.services_cents),
+        });
+        let names = pricing::fetch_addon_names(&state.db, &appointment.id).await;
+        if !names.is_empty() {
+            quote_lines.push(QuoteLineView {
+                label: format!("Add-ons ({})", names.join(", ")),
+                amount: format_money(quote.addons_cents),
+            });
+        }
+        quote_lines.push(QuoteLineView {
+            label: match quote.travel_km {
+                Some(km) => format!("Travel ({km:.1} km)"),
+                None => "Travel (distance unknown)".to_string(),
+            },
+            amount: format_money(quote.travel_cents),
+        });
+    }
+    let quote_total = quote
+        .map(|quote| format_money(quote.quote_cents))
+        .unwrap_or_default();
+
+    let payments = payments::fetch_ledger(&state.db, &appointment.id)
+        .await
+        .into_iter()
+        .map(|payment| PaymentView {
+            amount: format_money(payment.amount_cents),
+            kind: payment.kind,
+            status: payment.status,
+            provider: payment.provider,
+            reference: payment.reference.unwrap_or_default(),
+            error: payment.error.unwrap_or_default(),
+            created_at: payment.created_at,
+        })
+        .collect();
+    let (invoice_number, invoice_total) = invoices::fetch_invoice(&state.db, &appointment.id)
+        .await
+        .map(|invoice| {
+            (
+                state.invoices.display_number(invoice.number),
+                format_money(invoice.total_cents),
+            )
+        })
+        .unwrap_or_default();
+    let tip = match payouts::fetch_tip(&state.db, &appointment.id).await {
+        0 => String::new(),
+        cents => format_decimal(cents),
+    };
+
+    let offers = sqlx::query_as::<_, (String, String, String)>(
+        r#"SELECT u.display_name, o.status, o.offered_at
+           FROM dispatch_offers o
+           JOIN users u ON u.id = o.barber_id
+           WHERE o.appointment_id = ?
+           ORDER BY o.offered_at"#,
+    )
+    .bind(&appointment.id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(barber_name, status, offered_at)| OfferView {
+        barber_name,
+        status,
+        offered_at,
+    })
+    .collect();
+
     Ok(render(AdminAppointmentDetailTemplate {
         appointment,
+        scheduled_input,
+        zone: zone.name().to_string(),
         barbers,
         statuses,
+        outside_area,
+        offers,
+        client_id,
+        series_summary,
+        series,
+        items,
+        duration_minutes,
+        quote_lines,
+        quote_total,
+        payments,
+        invoice_number,
+        invoice_total,
+        tip,
         is_admin: true,
     }))
 }
@@ -358,6 +1052,7 @@ async fn update_appointment(
     let appointment_id = path.into_inner();
     let form = form.into_inner();
     let status = form.status.clone();
+    let scope = Scope::parse(form.apply_to.as_deref());
     let barber_id = form.barber_id.as_ref().and_then(|value| {
         if value.trim().is_empty() {
             None
@@ -365,28 +1060,52 @@ async fn update_appointment(
             Some(value.trim().to_string())
         }
     });
-
-    if let Some(scheduled_for) = form.scheduled_for.as_ref() {
-        if !scheduled_for.trim().is_empty() {
-            sqlx::query(
-                "UPDATE appointments SET status = ?, barber_id = ?, scheduled_for = ? WHERE id = ?",
-            )
+    // Admins see and type times in the business's zone.
+    let new_start = form
+        .scheduled_for
+        .as_deref()
+        .and_then(|value| schedule::parse_local(value, state.time.business_zone));
+
+    let mut affected = vec![appointment_id.clone()];
+    if scope == Scope::Series {
+        // Series edits shift every later open occurrence by the same amount and reassign them together.
+        if let Some(new_start) = new_start {
+            let series_zone = schedule::appointment_zone(&state.db, &state.time, &appointment_id).await;
+            affected.extend(
+                recurrence::reschedule(&state.db, &appointment_id, new_start, Scope::Series, false, series_zone)
+                    .await
+                    .map_err(actix_web::error::ErrorInternalServerError)?,
+            );
+        }
+        affected.extend(
+            recurrence::reassign(&state.db, &appointment_id, barber_id.as_deref(), Scope::Series)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?,
+        );
+        if status == STATUS_CANCELLED {
+            affected.extend(
+                recurrence::cancel(&state.db, &appointment_id, Scope::Series)
+                    .await
+                    .map_err(actix_web::error::ErrorInternalServerError)?,
+            );
+        }
+        sqlx::query("UPDATE appointments SET status = ? WHERE id = ?")
             .bind(&form.status)
-            .bind(&barber_id)
-            .bind(scheduled_for.trim())
             .bind(&appointment_id)
             .execute(&state.db)
             .await
             .map_err(actix_web::error::ErrorInternalServerError)?;
-        } else {
-            sqlx::query("UPDATE appointments SET status = ?, barber_id = ? WHERE id = ?")
-                .bind(&form.status)
-                .bind(&barber_id)
-                .bind(&appointment_id)
-                .execute(&state.db)
-                .await
-                .map_err(actix_web::error::ErrorInternalServerError)?;
-        }
+    } else if let Some(new_start) = new_start {
+        sqlx::query(
+            "UPDATE appointments SET status = ?, barber_id = ?, scheduled_for = ? WHERE id = ?",
+        )
+        .bind(&form.status)
+        .bind(&barber_id)
+        .bind(schedule::to_stored(new_start))
+        .bind(&appointment_id)
+        .execute(&state.db)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
     } else {
         sqlx::query("UPDATE appointments SET status = ?, barber_id = ? WHERE id = ?")
             .bind(&form.status)
@@ -396,26 +1115,97 @@ async fn update_appointment(
             .await
             .map_err(actix_web::error::ErrorInternalServerError)?;
     }
+    if status == STATUS_NO_SHOW {
+        policy::record_strike(&state, &appointment_id, STRIKE_NO_SHOW)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    } else if status != STATUS_CANCELLED {
+        policy::clear_strike(&state.db, &appointment_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    affected.sort();
+    affected.dedup();
+    for id in &affected {
+        line_items::recompute_duration(&state.db, id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        payments::settle(&state, id).await;
+        invoices::issue(&state, id).await;
+        reviews::request(&state, id).await;
+        if status == STATUS_CANCELLED || status == STATUS_DECLINED {
+            waitlist::slot_freed(&state, id).await;
+        }
+    }
 
     log_activity(
         &state.db,
         "appointment_updated",
-        &format!("{} updated appointment {}.", auth.display_name, appointment_id),
+        &if affected.len() > 1 {
+            format!(
+                "{} updated appointment {} and {} later visits in its series.",
+                auth.display_name,
+                appointment_id,
+                affected.len() - 1
+            )
+        } else {
+            format!("{} updated appointment {}.", auth.display_name, appointment_id)
+        },
         Some(&auth.id),
         Some(&appointment_id),
     )
     .await;
 
     let status_url = format!("/status/{appointment_id}");
+    let locale = i18n::appointment_locale(&state.db, &appointment_id).await;
     push::notify_appointment(
         &state,
         &appointment_id,
-        "Appointment updated",
-        &format!("Status changed to {}.", status),
+        locale.t("Appointment updated"),
+        &locale.format("Status changed to {status}.", &[("status", &locale.t(&status))]),
         Some(status_url.as_str()),
     )
     .await;
 
+    for id in &affected {
+        if let Some(row) = fetch_appointment_event(&state.db, id).await {
+            let _ = state
+                .events
+                .send(ServerEvent::from_row("appointment_updated", row));
+        }
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/appointments/{appointment_id}")))
+        .finish())
+}
+
+async fn update_line_item(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    form: web::Form<LineItemBarberForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let (appointment_id, item_id) = path.into_inner();
+    let barber_id = form
+        .barber_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+
+    line_items::assign_barber(&state.db, &appointment_id, &item_id, barber_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "appointment_split",
+        &format!("{} reassigned part of group appointment {}.", auth.display_name, appointment_id),
+        Some(&auth.id),
+        Some(&appointment_id),
+    )
+    .await;
+
     if let Some(row) = fetch_appointment_event(&state.db, &appointment_id).await {
         let _ = state
             .events
@@ -427,10 +1217,55 @@ async fn update_appointment(
         .finish())
 }
 
+async fn update_tip(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<TipForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let appointment_id = path.into_inner();
+    let Some(tip_cents) = parse_money(&form.tip) else {
+        return Ok(HttpResponse::BadRequest().body("Invalid tip amount"));
+    };
+    let status = sqlx::query_scalar::<_, String>("SELECT status FROM appointments WHERE id = ?")
+        .bind(&appointment_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None);
+    match status.as_deref() {
+        None => return Ok(HttpResponse::NotFound().body("Appointment not found")),
+        Some(STATUS_COMPLETED) => {}
+        Some(_) => return Ok(HttpResponse::Conflict().body("Tips can only be recorded for completed appointments")),
+    }
+
+    payouts::record_tip(&state.db, &appointment_id, tip_cents)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "tip_recorded",
+        &format!(
+            "{} recorded a {} tip for appointment {}.",
+            auth.display_name,
+            format_money(tip_cents),
+            appointment_id
+        ),
+        Some(&auth.id),
+        Some(&appointment_id),
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/appointments/{appointment_id}")))
+        .finish())
+}
+
 async fn list_barbers(state: web::Data<AppState>) -> Result<HttpResponse> {
     let barbers = fetch_barbers(&state).await.unwrap_or_default();
     Ok(render(AdminBarbersTemplate {
         barbers,
+        pending_profiles: profiles::pending_barbers(&state.db).await,
         errors: Vec::new(),
         success: String::new(),
         has_success: false,
@@ -459,6 +1294,7 @@ async fn create_barber(
         let barbers = fetch_barbers(&state).await.unwrap_or_default();
         return Ok(render(AdminBarbersTemplate {
             barbers,
+            pending_profiles: profiles::pending_barbers(&state.db).await,
             errors,
             success: String::new(),
             has_success: false,
@@ -487,6 +1323,7 @@ async fn create_barber(
         let barbers = fetch_barbers(&state).await.unwrap_or_default();
         return Ok(render(AdminBarbersTemplate {
             barbers,
+            pending_profiles: profiles::pending_barbers(&state.db).await,
             errors: vec![format!("Failed to create barber: {err}")],
             success: String::new(),
             has_success: false,
@@ -506,6 +1343,7 @@ async fn create_barber(
     let barbers = fetch_barbers(&state).await.unwrap_or_default();
     Ok(render(AdminBarbersTemplate {
         barbers,
+        pending_profiles: profiles::pending_barbers(&state.db).await,
         errors: Vec::new(),
         success: "Barber created successfully.".to_string(),
         has_success: true,
@@ -598,159 +1436,1659 @@ async fn barber_stats(
     .await
     .unwrap_or_default();
 
-    let recent = rows.into_iter().map(to_view).collect();
+    let zone = state.time.business_zone;
+    let recent = rows.into_iter().map(|row| to_view(row, zone)).collect();
+
+    let travel = sqlx::query_as::<_, (Option<f64>, Option<f64>, Option<f64>)>(
+        "SELECT base_latitude, base_longitude, travel_radius_km FROM users WHERE id = ?",
+    )
+    .bind(&barber.id)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None)
+    .map(|(lat, lon, radius)| TravelView {
+        base_latitude: lat.map(|value| value.to_string()).unwrap_or_default(),
+        base_longitude: lon.map(|value| value.to_string()).unwrap_or_default(),
+        travel_radius_km: radius.map(|value| value.to_string()).unwrap_or_default(),
+    })
+    .unwrap_or_default();
+
+    let commission_percent = sqlx::query_scalar::<_, Option<f64>>("SELECT commission_percent FROM users WHERE id = ?")
+        .bind(&barber.id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None)
+        .flatten()
+        .map(|percent| percent.to_string())
+        .unwrap_or_default();
+    let timezone = sqlx::query_scalar::<_, Option<String>>("SELECT timezone FROM users WHERE id = ?")
+        .bind(&barber.id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None)
+        .flatten()
+        .unwrap_or_default();
 
     Ok(render(AdminBarberStatsTemplate {
         barber,
         stats,
         recent,
+        travel,
+        commission_percent,
+        default_commission: state.payouts.default_commission_percent.to_string(),
+        timezone,
+        business_timezone: state.time.business_zone.name().to_string(),
+        timezones: chrono_tz::TZ_VARIANTS.iter().map(|zone| zone.name()).collect(),
+        rating: reviews::summary_for(&state.db, &barber_id).await.label(),
+        reviews: reviews::for_barber(&state.db, &barber_id, None, 10)
+            .await
+            .into_iter()
+            .map(review_view)
+            .collect(),
+        profile: profiles::fetch_published(&state.db, &barber_id)
+            .await
+            .map(ProfileView::from)
+            .unwrap_or_default(),
+        profile_draft: profiles::fetch_draft(&state.db, &barber_id)
+            .await
+            .map(|draft| ProfileDraftView {
+                profile: draft.profile.into(),
+                status: draft.status,
+                review_note: draft.review_note.unwrap_or_default(),
+                submitted_on: schedule::display(&draft.submitted_at, state.time.business_zone),
+            }),
         is_admin: true,
     }))
 }
 
-async fn cms_editor(state: web::Data<AppState>) -> Result<HttpResponse> {
-    let blocks = sqlx::query_as::<_, CmsBlockRow>(
-        "SELECT key, title, html FROM cms_blocks ORDER BY key",
+async fn update_barber_travel(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<BarberTravelForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let barber_id = path.into_inner();
+    let is_barber = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE id = ? AND role = ?")
+        .bind(&barber_id)
+        .bind(ROLE_BARBER)
+        .fetch_one(&state.db)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        > 0;
+    if !is_barber {
+        return Ok(HttpResponse::NotFound().body("Barber not found"));
+    }
+
+    let form = form.into_inner();
+    let filled = |value: &Option<String>| value.as_deref().is_some_and(|value| !value.trim().is_empty());
+    let base = match (
+        parse_optional_f64(form.base_latitude.as_deref()),
+        parse_optional_f64(form.base_longitude.as_deref()),
+    ) {
+        (Some(lat), Some(lon)) if validation::is_valid_point(lat, lon) => Some((lat, lon)),
+        _ if !filled(&form.base_latitude) && !filled(&form.base_longitude) => None,
+        _ => return Ok(HttpResponse::BadRequest().body("Invalid base location")),
+    };
+    // Blank or zero means the barber has no travel limit.
+    let travel_radius_km = match parse_optional_f64(form.travel_radius_km.as_deref()) {
+        Some(radius) if radius < 0.0 => {
+            return Ok(HttpResponse::BadRequest().body("Travel radius can't be negative"));
+        }
+        None if filled(&form.travel_radius_km) => {
+            return Ok(HttpResponse::BadRequest().body("Invalid travel radius"));
+        }
+        radius => radius.filter(|radius| *radius > 0.0),
+    };
+    if travel_radius_km.is_some() && base.is_none() {
+        return Ok(HttpResponse::BadRequest().body("A travel radius needs a base location"));
+    }
+
+    sqlx::query(
+        "UPDATE users SET base_latitude = ?, base_longitude = ?, travel_radius_km = ? WHERE id = ?",
     )
-    .fetch_all(&state.db)
+    .bind(base.map(|(lat, _)| lat))
+    .bind(base.map(|(_, lon)| lon))
+    .bind(travel_radius_km)
+    .bind(&barber_id)
+    .execute(&state.db)
     .await
-    .unwrap_or_default();
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "barber_travel_updated",
+        &format!("{} updated travel radius for barber {}.", auth.display_name, barber_id),
+        Some(&auth.id),
+        None,
+    )
+    .await;
 
-    Ok(render(AdminCmsTemplate { blocks, is_admin: true }))
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/barbers/{barber_id}")))
+        .finish())
 }
 
-async fn save_cms(
+async fn update_barber_commission(
     state: web::Data<AppState>,
-    payload: web::Json<CmsUpdatePayload>,
+    path: web::Path<String>,
+    form: web::Form<BarberCommissionForm>,
     auth: web::ReqData<AuthUser>,
 ) -> Result<HttpResponse> {
-    let payload = payload.into_inner();
-    let now = chrono::Utc::now().to_rfc3339();
-    sqlx::query(
-        r#"INSERT INTO cms_blocks (key, title, html, updated_at, updated_by)
-           VALUES (?, ?, ?, ?, ?)
-           ON CONFLICT(key) DO UPDATE SET html = excluded.html, updated_at = excluded.updated_at, updated_by = excluded.updated_by"#,
-    )
-    .bind(&payload.key)
-    .bind(&payload.key)
-    .bind(&payload.html)
-    .bind(&now)
-    .bind(&auth.id)
-    .execute(&state.db)
-    .await
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+    let barber_id = path.into_inner();
+    // Blank falls back to `COMMISSION_PERCENT`.
+    let commission_percent = parse_optional_f64(form.commission_percent.as_deref())
+        .map(|percent| percent.clamp(0.0, 100.0));
+
+    sqlx::query("UPDATE users SET commission_percent = ? WHERE id = ?")
+        .bind(commission_percent)
+        .bind(&barber_id)
+        .execute(&state.db)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
     log_activity(
         &state.db,
-        "cms_updated",
-        &format!("{} updated CMS block {}.", auth.display_name, payload.key),
+        "barber_commission_updated",
+        &format!("{} updated commission for barber {}.", auth.display_name, barber_id),
         Some(&auth.id),
         None,
     )
     .await;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({ "ok": true })))
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/barbers/{barber_id}")))
+        .finish())
 }
 
-async fn fetch_barbers(state: &web::Data<AppState>) -> Result<Vec<BarberView>, sqlx::Error> {
-    let rows = sqlx::query_as::<_, UserRow>(
-        "SELECT id, username, display_name, role, password_hash, active, created_at FROM users WHERE role IN (?, ?) ORDER BY display_name",
+async fn update_barber_timezone(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<BarberTimezoneForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let barber_id = path.into_inner();
+    // Blank falls back to `BUSINESS_TIMEZONE`.
+    let value = form.timezone.as_deref().map(str::trim).filter(|value| !value.is_empty());
+    let timezone = match value {
+        Some(value) => match schedule::parse_zone(value) {
+            Some(zone) => Some(zone.name()),
+            None => return Ok(HttpResponse::BadRequest().body("Unknown time zone")),
+        },
+        None => None,
+    };
+
+    sqlx::query("UPDATE users SET timezone = ? WHERE id = ?")
+        .bind(timezone)
+        .bind(&barber_id)
+        .execute(&state.db)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "barber_timezone_updated",
+        &format!(
+            "{} set the time zone for barber {} to {}.",
+            auth.display_name,
+            barber_id,
+            timezone.unwrap_or("the business default")
+        ),
+        Some(&auth.id),
+        None,
     )
-    .bind(ROLE_BARBER)
-    .bind(ROLE_ADMIN)
-    .fetch_all(&state.db)
-    .await?;
+    .await;
 
-    Ok(rows
-        .into_iter()
-        .map(|user| BarberView {
-            id: user.id,
-            username: user.username,
-            display_name: user.display_name,
-            role: user.role,
-            active: user.active == 1,
-            selected: false,
-        })
-        .collect())
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/barbers/{barber_id}")))
+        .finish())
 }
 
-fn to_view(row: AppointmentRow) -> AppointmentView {
-    let notes = row.notes.unwrap_or_default();
-    let client_email = row.client_email.unwrap_or_default();
-    AppointmentView {
-        id: row.id,
-        client_name: row.client_name,
-        client_phone: row.client_phone,
-        client_email: client_email.clone(),
-        has_email: !client_email.trim().is_empty(),
-        address: row.address,
-        service: row.service,
-        notes: notes.clone(),
-        has_notes: !notes.trim().is_empty(),
-        scheduled_for: row.scheduled_for,
-        status: row.status,
-        barber_id: row.barber_id.unwrap_or_default(),
-        barber_name: row.barber_name.unwrap_or_else(|| "Unassigned".to_string()),
-        latitude: row.latitude,
-        longitude: row.longitude,
+async fn approve_barber_profile(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let barber_id = path.into_inner();
+    let replaced_photo = profiles::approve(&state.db, &barber_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if let Some(photo) = replaced_photo {
+        media::delete_by_url(&state, &photo)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
     }
+    log_activity(
+        &state.db,
+        "profile_approved",
+        &format!("{} approved the profile of barber {}.", auth.display_name, barber_id),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/barbers/{barber_id}")))
+        .finish())
 }
 
-fn count(query: &str, state: &web::Data<AppState>) -> CountQuery {
-    CountQuery {
-        query: query.to_string(),
-        state: state.clone(),
+async fn reject_barber_profile(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<ProfileRejectForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let barber_id = path.into_inner();
+    let rejected = profiles::reject(&state.db, &barber_id, form.note.as_deref())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if rejected {
+        log_activity(
+            &state.db,
+            "profile_rejected",
+            &format!("{} sent profile changes back to barber {}.", auth.display_name, barber_id),
+            Some(&auth.id),
+            None,
+        )
+        .await;
     }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/barbers/{barber_id}")))
+        .finish())
 }
 
-struct CountQuery {
-    query: String,
-    state: web::Data<AppState>,
+async fn list_areas(state: web::Data<AppState>) -> Result<HttpResponse> {
+    Ok(render(AdminAreasTemplate {
+        areas: fetch_areas(&state).await,
+        errors: Vec::new(),
+        success: String::new(),
+        has_success: false,
+        is_admin: true,
+    }))
 }
 
-impl CountQuery {
-    async fn run(self) -> i64 {
-        sqlx::query_scalar::<_, i64>(&self.query)
-            .fetch_one(&self.state.db)
-            .await
-            .unwrap_or(0)
+async fn create_area(
+    state: web::Data<AppState>,
+    form: web::Form<ServiceAreaForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let mut errors = Vec::new();
+    if form.name.trim().is_empty() {
+        errors.push("Area name is required.".to_string());
     }
 
-    async fn run_with_param(self, param: &str) -> i64 {
-        sqlx::query_scalar::<_, i64>(&self.query)
-            .bind(param)
-            .fetch_one(&self.state.db)
-            .await
-            .unwrap_or(0)
+    let mut center_latitude = None;
+    let mut center_longitude = None;
+    let mut radius_km = None;
+    let mut polygon = None;
+    match form.kind.as_str() {
+        AREA_KIND_RADIUS => {
+            center_latitude = parse_optional_f64(form.center_latitude.as_deref());
+            center_longitude = parse_optional_f64(form.center_longitude.as_deref());
+            radius_km = parse_optional_f64(form.radius_km.as_deref()).filter(|value| *value > 0.0);
+            let valid_center = matches!(
+                (center_latitude, center_longitude),
+                (Some(lat), Some(lon)) if geo::valid_coordinates(lat, lon)
+            );
+            if !valid_center {
+                errors.push("Enter a valid center latitude and longitude.".to_string());
+            }
+            if radius_km.is_none() {
+                errors.push("Radius must be a positive number of kilometres.".to_string());
+            }
+        }
+        AREA_KIND_POLYGON => {
+            match geo::parse_polygon_lines(form.polygon.as_deref().unwrap_or_default()) {
+                Some(points) => polygon = Some(geo::polygon_to_json(&points)),
+                None => errors.push(
+                    "Polygons need at least three \"lat, lon\" lines with valid coordinates.".to_string(),
+                ),
+            }
+        }
+        _ => errors.push("Choose a radius or polygon area.".to_string()),
     }
-}
 
-#[allow(dead_code)]
-fn service_catalog() -> Vec<ServiceOption> {
-    vec![
-        ServiceOption {
-            name: "Signature Cut",
-            duration: "45 min",
-            description: "Precision cut, styling, and lineup.",
-            selected: false,
-        },
-        ServiceOption {
-            name: "Fade & Line-Up",
-            duration: "35 min",
-            description: "Skin fade with sharp finishing touches.",
-            selected: false,
-        },
-        ServiceOption {
-            name: "Beard Sculpt",
-            duration: "25 min",
-            description: "Shape, trim, and conditioning for the beard.",
-            selected: false,
-        },
-        ServiceOption {
-            name: "Full Grooming",
-            duration: "60 min",
-            description: "Cut, beard, and grooming refresh.",
-            selected: false,
-        },
-    ]
+    if !errors.is_empty() {
+        return Ok(render(AdminAreasTemplate {
+            areas: fetch_areas(&state).await,
+            errors,
+            success: String::new(),
+            has_success: false,
+            is_admin: true,
+        }));
+    }
+
+    sqlx::query(
+        r#"INSERT INTO service_areas
+           (id, name, kind, center_latitude, center_longitude, radius_km, polygon, active, created_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, 1, ?)"#,
+    )
+    .bind(new_id())
+    .bind(form.name.trim())
+    .bind(&form.kind)
+    .bind(center_latitude)
+    .bind(center_longitude)
+    .bind(radius_km)
+    .bind(polygon)
+    .bind(chrono::Utc::now().to_rfc3339())
+    .execute(&state.db)
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "service_area_created",
+        &format!("{} added service area {}.", auth.display_name, form.name.trim()),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(render(AdminAreasTemplate {
+        areas: fetch_areas(&state).await,
+        errors: Vec::new(),
+        success: "Service area added.".to_string(),
+        has_success: true,
+        is_admin: true,
+    }))
+}
+
+async fn delete_area(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let area_id = path.into_inner();
+    sqlx::query("DELETE FROM service_areas WHERE id = ?")
+        .bind(&area_id)
+        .execute(&state.db)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "service_area_deleted",
+        &format!("{} removed a service area.", auth.display_name),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/areas"))
+        .finish())
+}
+
+async fn fetch_areas(state: &web::Data<AppState>) -> Vec<ServiceAreaView> {
+    sqlx::query_as::<_, ServiceAreaRow>(
+        r#"SELECT id, name, kind, center_latitude, center_longitude, radius_km, polygon, active
+           FROM service_areas
+           ORDER BY name"#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|area| {
+        let summary = if area.kind == AREA_KIND_RADIUS {
+            format!(
+                "{:.1} km around {:.5}, {:.5}",
+                area.radius_km.unwrap_or_default(),
+                area.center_latitude.unwrap_or_default(),
+                area.center_longitude.unwrap_or_default()
+            )
+        } else {
+            let points = area
+                .polygon
+                .as_deref()
+                .and_then(geo::parse_polygon)
+                .map(|points| points.len())
+                .unwrap_or(0);
+            format!("Polygon with {points} points")
+        };
+        ServiceAreaView {
+            id: area.id,
+            name: area.name,
+            kind: area.kind,
+            summary,
+        }
+    })
+    .collect()
+}
+
+fn parse_optional_f64(value: Option<&str>) -> Option<f64> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .and_then(|value| value.parse::<f64>().ok())
+}
+
+async fn list_clients(
+    state: web::Data<AppState>,
+    query: web::Query<ClientFilter>,
+) -> Result<HttpResponse> {
+    let search = query.q.clone().unwrap_or_default().trim().to_string();
+    let pattern = format!("%{}%", search.to_lowercase());
+    let rows = sqlx::query_as::<_, (String, String, Option<String>, Option<String>, Option<String>, String, i64, Option<String>, i64)>(
+        r#"SELECT c.id, c.name, c.phone, c.email, c.address, c.created_at,
+                  COUNT(a.id) as visits,
+                  MAX(a.scheduled_for) as last_visit,
+                  (SELECT COUNT(*) FROM client_strikes s WHERE s.client_id = c.id) as strikes
+           FROM clients c
+           LEFT JOIN appointments a ON a.client_id = c.id
+           WHERE ? = '' OR lower(c.name) LIKE ? OR lower(coalesce(c.email, '')) LIKE ? OR coalesce(c.phone, '') LIKE ?
+           GROUP BY c.id
+           ORDER BY last_visit DESC, c.name
+           LIMIT 200"#,
+    )
+    .bind(&search)
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&pattern)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let clients = rows
+        .into_iter()
+        .map(|(id, name, phone, email, address, created_at, visits, last_visit, strikes)| ClientView {
+            id,
+            name,
+            phone: phone.unwrap_or_default(),
+            email: email.unwrap_or_default(),
+            address: address.unwrap_or_default(),
+            notes: String::new(),
+            created_at,
+            visits,
+            last_visit: last_visit
+                .map(|value| schedule::display(&value, state.time.business_zone))
+                .unwrap_or_default(),
+            strikes,
+        })
+        .collect();
+
+    Ok(render(AdminClientsTemplate {
+        clients,
+        search,
+        is_admin: true,
+    }))
+}
+
+async fn list_waitlist(state: web::Data<AppState>) -> Result<HttpResponse> {
+    let today = schedule::today(state.time.business_zone);
+    let entries = waitlist::list_entries(&state.db, today)
+        .await
+        .into_iter()
+        .map(|entry| WaitlistEntryView {
+            id: entry.id,
+            client_name: entry.client_name,
+            contact: match entry.client_email.filter(|email| !email.is_empty()) {
+                Some(email) => format!("{} · {email}", entry.client_phone),
+                None => entry.client_phone,
+            },
+            service: entry.service,
+            wanted_date: entry.wanted_date,
+            barber_name: entry.barber_name.unwrap_or_else(|| "Any barber".to_string()),
+            offer_expires_at: entry
+                .offer_expires_at
+                .map(|value| schedule::display(&value, state.time.business_zone))
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(render(AdminWaitlistTemplate {
+        entries,
+        claim_minutes: state.waitlist.claim_minutes,
+        is_admin: true,
+    }))
+}
+
+async fn remove_waitlist_entry(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let entry_id = path.into_inner();
+    waitlist::remove(&state, &entry_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    log_activity(
+        &state.db,
+        "waitlist_removed",
+        &format!("{} removed waitlist entry {}.", auth.display_name, entry_id),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/waitlist"))
+        .finish())
+}
+
+async fn list_reviews(
+    state: web::Data<AppState>,
+    query: web::Query<ReviewFilter>,
+) -> Result<HttpResponse> {
+    let status = query.status.clone().unwrap_or_else(|| REVIEW_PENDING.to_string());
+    let filter = (status != "all").then_some(status.as_str());
+    let reviews = reviews::list(&state.db, filter)
+        .await
+        .into_iter()
+        .map(review_view)
+        .collect();
+
+    Ok(render(AdminReviewsTemplate {
+        reviews,
+        status,
+        is_admin: true,
+    }))
+}
+
+async fn moderate_review(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<ReviewStatusForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let review_id = path.into_inner();
+    let status = form.into_inner().status;
+    if status != REVIEW_APPROVED && status != REVIEW_REJECTED && status != REVIEW_PENDING {
+        return Ok(HttpResponse::BadRequest().body("Unknown review status"));
+    }
+    let updated = reviews::set_status(&state.db, &review_id, &status)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if updated {
+        log_activity(
+            &state.db,
+            "review_moderated",
+            &format!("{} marked review {} as {}.", auth.display_name, review_id, status),
+            Some(&auth.id),
+            None,
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/reviews"))
+        .finish())
+}
+
+fn review_view(review: ReviewRow) -> ReviewView {
+    ReviewView {
+        id: review.id,
+        appointment_id: review.appointment_id,
+        barber_name: review.barber_name.unwrap_or_else(|| "Former barber".to_string()),
+        client_name: review.client_name,
+        stars: reviews::stars(review.rating.unwrap_or(0)),
+        comment: review.comment.unwrap_or_default(),
+        status: review.status,
+        submitted_on: review
+            .submitted_at
+            .and_then(|value| chrono::DateTime::parse_from_rfc3339(&value).ok())
+            .map(|value| value.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+    }
+}
+
+async fn list_invoices(
+    state: web::Data<AppState>,
+    query: web::Query<InvoiceFilter>,
+) -> Result<HttpResponse> {
+    // `month` comes from an `<input type="month">`, e.g. "2026-03".
+    let month = query.month.clone().unwrap_or_default().trim().to_string();
+    let rows: Vec<_> = invoices::list_invoices(&state.db)
+        .await
+        .into_iter()
+        .filter(|invoice| month.is_empty() || invoice.issued_at.starts_with(&month))
+        .collect();
+    let invoiced_total = format_money(rows.iter().map(|invoice| invoice.total_cents).sum());
+
+    let invoices = rows
+        .into_iter()
+        .map(|invoice| InvoiceView {
+            number: state.invoices.display_number(invoice.number),
+            issued_on: invoices::issued_on(&invoice).to_string(),
+            total: format_money(invoice.total_cents),
+            appointment_id: invoice.appointment_id,
+            client_name: invoice.client_name,
+            barber_name: invoice.barber_name.unwrap_or_else(|| "Unassigned".to_string()),
+        })
+        .collect();
+
+    Ok(render(AdminInvoicesTemplate {
+        invoices,
+        month,
+        invoiced_total,
+        is_admin: true,
+    }))
+}
+
+async fn build_payouts(state: &AppState, query: &PayoutQuery) -> payouts::PayoutReport {
+    let period = Period::parse(query.period.as_deref());
+    let zone = state.time.business_zone;
+    let today = schedule::today(zone);
+    let (from, to) = payouts::parse_range(period, query.from.as_deref(), query.to.as_deref(), today);
+    let barber_id = query.barber_id.as_deref().map(str::trim).filter(|id| !id.is_empty());
+    payouts::build_report(&state.db, &state.payouts, period, from, to, barber_id, zone).await
+}
+
+fn payout_line_view(period: String, barber_name: String, earnings: &payouts::Earnings) -> PayoutLineView {
+    PayoutLineView {
+        period,
+        barber_name,
+        visits: earnings.visits,
+        gross: format_money(earnings.gross_cents),
+        commission: format_money(earnings.commission_cents),
+        tips: format_money(earnings.tips_cents),
+        net: format_money(earnings.net_cents()),
+    }
+}
+
+async fn payouts_report(
+    state: web::Data<AppState>,
+    query: web::Query<PayoutQuery>,
+) -> Result<HttpResponse> {
+    let report = build_payouts(&state, &query).await;
+    let barber_id = query.barber_id.clone().unwrap_or_default();
+    let mut barbers = fetch_barbers(&state).await.unwrap_or_default();
+    for barber in &mut barbers {
+        barber.selected = barber.id == barber_id;
+    }
+    let lines = report
+        .lines
+        .iter()
+        .map(|line| {
+            let commission = format!("{} ({}%)", format_money(line.earnings.commission_cents), line.commission_percent);
+            PayoutLineView {
+                commission,
+                ..payout_line_view(line.period_label.clone(), line.barber_name.clone(), &line.earnings)
+            }
+        })
+        .collect();
+    let from = report.from.format("%Y-%m-%d").to_string();
+    let to = report.to.format("%Y-%m-%d").to_string();
+    let csv_query = format!("period={}&from={from}&to={to}&barber_id={barber_id}", report.period.as_str());
+
+    Ok(render(AdminPayoutsTemplate {
+        lines,
+        total: payout_line_view("Total".to_string(), String::new(), &report.total),
+        period: report.period.as_str().to_string(),
+        from,
+        to,
+        barbers,
+        csv_query,
+        is_admin: true,
+    }))
+}
+
+async fn payouts_csv(
+    state: web::Data<AppState>,
+    query: web::Query<PayoutQuery>,
+) -> Result<HttpResponse> {
+    let report = build_payouts(&state, &query).await;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", report.filename("payouts")),
+        ))
+        .body(report.to_csv()))
+}
+
+async fn client_detail(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let client_id = path.into_inner();
+    let zone = state.time.business_zone;
+    let row = sqlx::query_as::<_, ClientRow>(
+        "SELECT id, name, phone, email, address, notes, created_at FROM clients WHERE id = ?",
+    )
+    .bind(&client_id)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    let Some(row) = row else {
+        return Ok(HttpResponse::NotFound().body("Client not found"));
+    };
+
+    let rows = sqlx::query_as::<_, AppointmentRow>(
+        r#"SELECT a.id, a.client_name, a.client_phone, a.client_email, a.address, a.service,
+                  a.notes, a.requested_at, a.scheduled_for, a.status, a.barber_id,
+                  a.latitude, a.longitude,
+                  u.display_name as barber_name
+           FROM appointments a
+           LEFT JOIN users u ON a.barber_id = u.id
+           WHERE a.client_id = ?
+           ORDER BY a.scheduled_for DESC"#,
+    )
+    .bind(&row.id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let completed: Vec<&AppointmentRow> = rows
+        .iter()
+        .filter(|appointment| appointment.status == STATUS_COMPLETED)
+        .collect();
+    let durations = line_items::durations(&state.db, &rows).await;
+    let chair_minutes: i64 = completed
+        .iter()
+        .map(|appointment| {
+            durations
+                .get(&appointment.id)
+                .copied()
+                .unwrap_or_else(|| service_minutes(&appointment.service))
+        })
+        .sum();
+    let stats = vec![
+        StatCard {
+            label: "Bookings".to_string(),
+            value: rows.len() as i64,
+        },
+        StatCard {
+            label: "Completed visits".to_string(),
+            value: completed.len() as i64,
+        },
+        StatCard {
+            label: "Declined".to_string(),
+            value: rows
+                .iter()
+                .filter(|appointment| appointment.status == STATUS_DECLINED)
+                .count() as i64,
+        },
+        StatCard {
+            label: "No-shows".to_string(),
+            value: policy::no_show_count(&state.db, &row.id).await,
+        },
+        StatCard {
+            label: "Minutes in chair".to_string(),
+            value: chair_minutes,
+        },
+    ];
+
+    // Lifetime value counts the quoted total of completed visits; older visits without a quote count as zero.
+    let mut lifetime_cents = 0;
+    for appointment in &completed {
+        if let Some(quote) = pricing::fetch_quote(&state.db, &appointment.id).await {
+            lifetime_cents += quote.quote_cents;
+        }
+    }
+
+    let strikes: Vec<StrikeView> = policy::fetch_strikes(&state.db, &row.id)
+        .await
+        .into_iter()
+        .map(|strike| StrikeView {
+            id: strike.id,
+            appointment_id: strike.appointment_id,
+            label: policy::strike_label(&strike.kind, Locale::default()),
+            scheduled_for: schedule::display(&strike.scheduled_for, zone),
+            fee: format_money(strike.fee_cents),
+            recorded_at: strike.created_at,
+        })
+        .collect();
+    let requires_prepayment = policy::requires_prepayment(&state.db, &state.policy, &row.id).await;
+
+    let client = ClientView {
+        id: row.id,
+        name: row.name,
+        phone: row.phone.unwrap_or_default(),
+        email: row.email.unwrap_or_default(),
+        address: row.address.unwrap_or_default(),
+        notes: row.notes.unwrap_or_default(),
+        created_at: row.created_at,
+        visits: completed.len() as i64,
+        last_visit: rows
+            .first()
+            .map(|appointment| schedule::display(&appointment.scheduled_for, zone))
+            .unwrap_or_default(),
+        strikes: strikes.len() as i64,
+    };
+
+    Ok(render(AdminClientDetailTemplate {
+        client,
+        stats,
+        lifetime_value: format_money(lifetime_cents),
+        appointments: rows.into_iter().map(|row| to_view(row, zone)).collect(),
+        strikes,
+        requires_prepayment,
+        is_admin: true,
+    }))
+}
+
+/// Forgives a strike; any fee it kept is refunded.
+async fn remove_client_strike(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let (client_id, strike_id) = path.into_inner();
+    let appointment_id = sqlx::query_scalar::<_, String>(
+        "DELETE FROM client_strikes WHERE id = ? AND client_id = ? RETURNING appointment_id",
+    )
+    .bind(&strike_id)
+    .bind(&client_id)
+    .fetch_optional(&state.db)
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if let Some(appointment_id) = appointment_id {
+        payments::settle(&state, &appointment_id).await;
+        log_activity(
+            &state.db,
+            "client_strike_removed",
+            &format!("{} removed a strike for client {}.", auth.display_name, client_id),
+            Some(&auth.id),
+            Some(&appointment_id),
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/clients/{client_id}")))
+        .finish())
+}
+
+async fn update_client_notes(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<ClientNotesForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let client_id = path.into_inner();
+    let notes = form
+        .into_inner()
+        .notes
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+
+    sqlx::query("UPDATE clients SET notes = ?, updated_at = ? WHERE id = ?")
+        .bind(notes)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .bind(&client_id)
+        .execute(&state.db)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "client_notes_updated",
+        &format!("{} updated notes for client {}.", auth.display_name, client_id),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/clients/{client_id}")))
+        .finish())
+}
+
+async fn cms_editor(state: web::Data<AppState>, query: web::Query<CmsLocaleQuery>) -> Result<HttpResponse> {
+    Ok(render(cms_template(&state, query.locale(), Vec::new()).await))
+}
+
+async fn cms_template(state: &AppState, locale: Locale, errors: Vec<String>) -> AdminCmsTemplate {
+    let blocks = sqlx::query_as::<_, CmsBlockRow>(
+        "SELECT key, title, html, format, source FROM cms_blocks WHERE page_id IS NULL AND variant_of IS NULL ORDER BY key",
+    )
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    AdminCmsTemplate {
+        blocks: cms_block_views(state, blocks, locale).await,
+        languages: cms_languages("/admin/cms", locale),
+        translating: locale != Locale::default(),
+        errors,
+        is_admin: true,
+    }
+}
+
+/// Editor views of `blocks`, or of their translations into `locale`.
+async fn cms_block_views(state: &AppState, blocks: Vec<CmsBlockRow>, locale: Locale) -> Vec<CmsBlockView> {
+    let mut drafts = cms::drafts(&state.db).await;
+    let mut views = Vec::with_capacity(blocks.len());
+    for mut block in blocks {
+        let mut untranslated = false;
+        if locale != Locale::default() {
+            let key = cms::variant_key(&block.key, locale);
+            let title = format!("{} ({})", block.title, locale.name());
+            match fetch_cms_block(state, &key).await.filter(|variant| !variant.html.is_empty()) {
+                Some(variant) => block = variant,
+                None => untranslated = true,
+            }
+            block.key = key;
+            block.title = title;
+        }
+        let revisions = cms::revisions(&state.db, &block.key, CMS_HISTORY_LIMIT)
+            .await
+            .into_iter()
+            .map(|revision| cms_revision_view(revision, state.time.business_zone))
+            .collect();
+        let draft = drafts.remove(&block.key);
+        let content = match &draft {
+            Some(draft) => BlockContent::from(draft.clone()),
+            None => BlockContent {
+                format: block.format,
+                source: block.source,
+                html: block.html,
+            },
+        };
+        views.push(CmsBlockView {
+            html: content.html,
+            format: content.format,
+            source: content.source.unwrap_or_default(),
+            untranslated: untranslated && draft.is_none(),
+            draft: draft.map(|draft| CmsDraftView {
+                saved: format!(
+                    "{} by {}",
+                    schedule::display(&draft.updated_at, state.time.business_zone),
+                    draft.author.as_deref().unwrap_or("a former user")
+                ),
+                publish_at: draft
+                    .publish_at
+                    .as_deref()
+                    .map(|value| schedule::display(value, state.time.business_zone))
+                    .unwrap_or_default(),
+                publish_at_input: draft
+                    .publish_at
+                    .as_deref()
+                    .map(|value| schedule::input_value(value, state.time.business_zone))
+                    .unwrap_or_default(),
+            }),
+            key: block.key,
+            title: block.title,
+            revisions,
+            zone: state.time.business_zone.name(),
+        });
+    }
+    views
+}
+
+async fn fetch_cms_block(state: &AppState, key: &str) -> Option<CmsBlockRow> {
+    sqlx::query_as::<_, CmsBlockRow>("SELECT key, title, html, format, source FROM cms_blocks WHERE key = ?")
+        .bind(key)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None)
+}
+
+/// Links between the editor's language versions.
+fn cms_languages(path: &str, current: Locale) -> Vec<LanguageLink> {
+    Locale::ALL
+        .into_iter()
+        .map(|locale| LanguageLink {
+            code: locale.code(),
+            name: locale.name(),
+            href: if locale == Locale::default() {
+                path.to_string()
+            } else {
+                format!("{path}?locale={}", locale.code())
+            },
+            current: locale == current,
+        })
+        .collect()
+}
+
+/// The editor a block lives in: its page's editor, or the home blocks on `/admin/cms`, in the block's language.
+async fn cms_editor_url(state: &AppState, key: &str) -> String {
+    let (base, locale) = cms::split_variant(key);
+    let query = locale.map(|locale| format!("?locale={}", locale.code())).unwrap_or_default();
+    match pages::page_for_block(&state.db, base).await {
+        Some(page_id) => format!("/admin/pages/{page_id}{query}#cms-{key}"),
+        None => format!("/admin/cms{query}#cms-{key}"),
+    }
+}
+
+async fn cms_editor_with_errors(state: &AppState, key: &str, errors: Vec<String>) -> HttpResponse {
+    let (base, locale) = cms::split_variant(key);
+    let locale = locale.unwrap_or_default();
+    match pages::page_for_block(&state.db, base).await {
+        Some(page_id) => match page_editor(state, &page_id, locale, None, errors).await {
+            Some(template) => render(template),
+            None => HttpResponse::NotFound().finish(),
+        },
+        None => render(cms_template(state, locale, errors).await),
+    }
+}
+
+fn cms_revision_view(revision: CmsRevisionRow, zone: Tz) -> CmsRevisionView {
+    CmsRevisionView {
+        id: revision.id,
+        kind: revision.kind,
+        created: schedule::display(&revision.created_at, zone),
+        author: revision.author.unwrap_or_else(|| "System".to_string()),
+    }
+}
+
+async fn save_cms(
+    state: web::Data<AppState>,
+    payload: web::Json<CmsUpdatePayload>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let payload = payload.into_inner();
+    cms::ensure_variant(&state.db, &payload.key)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let (content, removed) = BlockContent::prepare(
+        payload.format.as_deref().unwrap_or(CMS_FORMAT_HTML),
+        &payload.html,
+        payload.markdown.as_deref().unwrap_or_default(),
+    );
+    let saved = if payload.draft {
+        cms::save_draft(&state.db, &payload.key, &content, Some(&auth.id)).await
+    } else {
+        cms::publish(&state.db, &payload.key, &content, Some(&auth.id)).await
+    };
+    saved.map_err(actix_web::error::ErrorInternalServerError)?;
+
+    let verb = if payload.draft { "saved a draft of" } else { "published" };
+    let mut message = format!("{} {verb} CMS block {}.", auth.display_name, payload.key);
+    if !removed.is_empty() {
+        message.push_str(&format!(" Removed: {}.", removed.join(", ")));
+    }
+    log_activity(&state.db, "cms_updated", &message, Some(&auth.id), None).await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "ok": true,
+        "draft": payload.draft,
+        "format": content.format,
+        "html": content.html,
+        "removed": removed,
+    })))
+}
+
+/// Server-rendered Markdown for the editor's live preview; nothing is stored.
+async fn preview_cms(payload: web::Json<CmsPreviewPayload>) -> Result<HttpResponse> {
+    let rendered = cms::render_markdown(&payload.markdown);
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "html": rendered.html,
+        "removed": rendered.removed,
+    })))
+}
+
+async fn schedule_cms(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<CmsScheduleForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let key = path.into_inner();
+    let value = form.publish_at.trim();
+    let publish_at = if value.is_empty() {
+        None
+    } else {
+        match schedule::parse_local(value, state.time.business_zone) {
+            Some(at) if at > chrono::Utc::now() => Some(at),
+            Some(_) => {
+                let errors = vec!["Choose a publish time in the future.".to_string()];
+                return Ok(cms_editor_with_errors(&state, &key, errors).await);
+            }
+            None => {
+                let errors = vec!["Enter the publish time as a date and time.".to_string()];
+                return Ok(cms_editor_with_errors(&state, &key, errors).await);
+            }
+        }
+    };
+
+    let scheduled = cms::schedule(&state.db, &key, publish_at)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !scheduled {
+        let errors = vec!["Save a draft before scheduling it.".to_string()];
+        return Ok(cms_editor_with_errors(&state, &key, errors).await);
+    }
+
+    let message = match publish_at {
+        Some(at) => format!(
+            "{} scheduled CMS block {key} to publish at {}.",
+            auth.display_name,
+            schedule::display(&schedule::to_stored(at), state.time.business_zone)
+        ),
+        None => format!("{} unscheduled the draft of CMS block {key}.", auth.display_name),
+    };
+    log_activity(&state.db, "cms_scheduled", &message, Some(&auth.id), None).await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, cms_editor_url(&state, &key).await))
+        .finish())
+}
+
+async fn discard_cms_draft(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let key = path.into_inner();
+    let discarded = cms::discard_draft(&state.db, &key)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if discarded {
+        log_activity(
+            &state.db,
+            "cms_draft_discarded",
+            &format!("{} discarded the draft of CMS block {key}.", auth.display_name),
+            Some(&auth.id),
+            None,
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, cms_editor_url(&state, &key).await))
+        .finish())
+}
+
+async fn show_cms_revision(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse> {
+    let (key, revision_id) = path.into_inner();
+    let Some(block) = fetch_cms_block(&state, &key).await else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let Some(revision) = cms::fetch_revision(&state.db, &key, &revision_id).await else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    // Markdown reads better than the HTML rendered from it, so compare sources when both have one.
+    let diff = match (&block.source, &revision.source) {
+        (Some(live), Some(restored)) if block.format == CMS_FORMAT_MARKDOWN && revision.format == CMS_FORMAT_MARKDOWN => {
+            cms::diff_text(live, restored)
+        }
+        _ => cms::diff(&block.html, &revision.html),
+    };
+
+    Ok(render(AdminCmsRevisionTemplate {
+        diff,
+        preview: sanitize_html(&revision.html).html,
+        is_live: revision.html == block.html,
+        back_url: cms_editor_url(&state, &block.key).await,
+        revision: cms_revision_view(revision, state.time.business_zone),
+        block_key: block.key,
+        block_title: block.title,
+        is_admin: true,
+    }))
+}
+
+async fn restore_cms_revision(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let (key, revision_id) = path.into_inner();
+    let Some(revision) = cms::fetch_revision(&state.db, &key, &revision_id).await else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let created_at = revision.created_at.clone();
+    let (content, _) = BlockContent::prepare(
+        &revision.format,
+        &revision.html,
+        revision.source.as_deref().unwrap_or_default(),
+    );
+    cms::publish(&state.db, &key, &content, Some(&auth.id))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "cms_updated",
+        &format!(
+            "{} restored CMS block {key} to the version from {}.",
+            auth.display_name,
+            schedule::display(&created_at, state.time.business_zone)
+        ),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, cms_editor_url(&state, &key).await))
+        .finish())
+}
+
+async fn list_pages(state: web::Data<AppState>) -> Result<HttpResponse> {
+    Ok(render(AdminPagesTemplate {
+        pages: pages::list(&state.db).await,
+        form: PageFormView {
+            nav_order: "0".to_string(),
+            ..PageFormView::default()
+        },
+        errors: Vec::new(),
+        is_admin: true,
+    }))
+}
+
+async fn create_page(
+    state: web::Data<AppState>,
+    form: web::Form<PageForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let input = match page_input(&state, &form, None).await {
+        Ok(input) => input,
+        Err(errors) => {
+            return Ok(render(AdminPagesTemplate {
+                pages: pages::list(&state.db).await,
+                form: page_form_view(&form),
+                errors,
+                is_admin: true,
+            }));
+        }
+    };
+    let page_id = pages::create(&state.db, &input, Some(&auth.id))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "page_created",
+        &format!("{} created the page /p/{}.", auth.display_name, input.slug),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/pages/{page_id}")))
+        .finish())
+}
+
+async fn show_page(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<CmsLocaleQuery>,
+) -> Result<HttpResponse> {
+    match page_editor(&state, &path.into_inner(), query.locale(), None, Vec::new()).await {
+        Some(template) => Ok(render(template)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+async fn update_page(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<PageForm>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let page_id = path.into_inner();
+    let form = form.into_inner();
+    let input = match page_input(&state, &form, Some(&page_id)).await {
+        Ok(input) => input,
+        Err(errors) => {
+            return Ok(match page_editor(&state, &page_id, Locale::default(), Some(page_form_view(&form)), errors).await {
+                Some(template) => render(template),
+                None => HttpResponse::NotFound().finish(),
+            });
+        }
+    };
+    let updated = pages::update(&state.db, &page_id, &input)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if !updated {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    log_activity(
+        &state.db,
+        "page_updated",
+        &format!("{} updated the settings of /p/{}.", auth.display_name, input.slug),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/pages/{page_id}")))
+        .finish())
+}
+
+async fn delete_page(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let page_id = path.into_inner();
+    if let Some(page) = pages::fetch(&state.db, &page_id).await {
+        pages::delete(&state.db, &page_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        log_activity(
+            &state.db,
+            "page_deleted",
+            &format!("{} deleted the page /p/{}.", auth.display_name, page.slug),
+            Some(&auth.id),
+            None,
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/pages"))
+        .finish())
+}
+
+async fn add_page_block(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let page_id = path.into_inner();
+    if pages::fetch(&state.db, &page_id).await.is_none() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let key = pages::add_block(&state.db, &page_id, "<p></p>", Some(&auth.id))
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/pages/{page_id}#cms-{key}")))
+        .finish())
+}
+
+async fn remove_page_block(
+    state: web::Data<AppState>,
+    path: web::Path<(String, String)>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let (page_id, key) = path.into_inner();
+    let removed = pages::remove_block(&state.db, &page_id, &key)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if removed {
+        log_activity(
+            &state.db,
+            "page_updated",
+            &format!("{} removed a section from a page.", auth.display_name),
+            Some(&auth.id),
+            None,
+        )
+        .await;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/admin/pages/{page_id}")))
+        .finish())
+}
+
+async fn page_editor(
+    state: &AppState,
+    page_id: &str,
+    locale: Locale,
+    form: Option<PageFormView>,
+    errors: Vec<String>,
+) -> Option<AdminPageTemplate> {
+    let page = pages::fetch(&state.db, page_id).await?;
+    let blocks = pages::blocks(&state.db, page_id).await;
+    Some(AdminPageTemplate {
+        form: form.unwrap_or_else(|| PageFormView {
+            title: page.title,
+            slug: page.slug,
+            meta_description: page.meta_description.unwrap_or_default(),
+            published: page.published,
+            show_in_nav: page.show_in_nav,
+            nav_order: page.nav_order.to_string(),
+        }),
+        languages: cms_languages(&format!("/admin/pages/{}", page.id), locale),
+        translating: locale != Locale::default(),
+        page_id: page.id,
+        blocks: cms_block_views(state, blocks, locale).await,
+        errors,
+        is_admin: true,
+    })
+}
+
+fn page_form_view(form: &PageForm) -> PageFormView {
+    PageFormView {
+        title: form.title.clone(),
+        slug: form.slug.clone().unwrap_or_default(),
+        meta_description: form.meta_description.clone().unwrap_or_default(),
+        published: form.published.is_some(),
+        show_in_nav: form.show_in_nav.is_some(),
+        nav_order: form.nav_order.clone().unwrap_or_default(),
+    }
+}
+
+/// Validates the page settings form. The slug defaults to one made from the title.
+async fn page_input(state: &AppState, form: &PageForm, page_id: Option<&str>) -> Result<PageInput, Vec<String>> {
+    let mut errors = Vec::new();
+    let title = form.title.trim().to_string();
+    if title.is_empty() {
+        errors.push("Enter a page title.".to_string());
+    }
+    let slug = pages::slugify(
+        form.slug
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(&title),
+    );
+    if slug.is_empty() {
+        errors.push("The address needs at least one letter or digit.".to_string());
+    } else if pages::slug_taken(&state.db, &slug, page_id).await {
+        errors.push(format!("Another page already uses /p/{slug}."));
+    }
+    let meta_description = form
+        .meta_description
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
+    if meta_description
+        .as_ref()
+        .is_some_and(|value| value.chars().count() > pages::MAX_META_DESCRIPTION_CHARS)
+    {
+        errors.push(format!(
+            "Keep the description under {} characters.",
+            pages::MAX_META_DESCRIPTION_CHARS
+        ));
+    }
+    let nav_order = match form.nav_order.as_deref().map(str::trim).filter(|value| !value.is_empty()) {
+        Some(value) => value.parse::<i64>().unwrap_or_else(|_| {
+            errors.push("Menu position must be a whole number.".to_string());
+            0
+        }),
+        None => 0,
+    };
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(PageInput {
+        slug,
+        title,
+        meta_description,
+        published: form.published.is_some(),
+        show_in_nav: form.show_in_nav.is_some(),
+        nav_order,
+    })
+}
+
+async fn media_library(state: web::Data<AppState>) -> Result<HttpResponse> {
+    Ok(render(AdminMediaTemplate {
+        items: media::list(&state.db).await,
+        errors: Vec::new(),
+        max_upload_mb: state.uploads.max_bytes / (1024 * 1024),
+        is_admin: true,
+    }))
+}
+
+/// Used by the image picker in the CMS editor.
+async fn media_json(state: web::Data<AppState>) -> Result<HttpResponse> {
+    let items: Vec<_> = media::list(&state.db).await.iter().map(media_payload).collect();
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// Form uploads redirect back to the library; the CMS image picker asks for JSON instead.
+async fn upload_media(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    form: MultipartForm<MediaUpload>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let wants_json = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("application/json"));
+    let name = form.file.file_name.clone().unwrap_or_else(|| "upload".to_string());
+    let alt_text = form.alt_text.map(|value| value.into_inner());
+
+    let item = match media::upload(&state, form.file.data.to_vec(), &name, alt_text.as_deref(), Some(&auth.id)).await {
+        Ok(item) => item,
+        Err(err) if wants_json => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": err.to_string() })));
+        }
+        Err(err) => {
+            return Ok(render(AdminMediaTemplate {
+                items: media::list(&state.db).await,
+                errors: vec![err.to_string()],
+                max_upload_mb: state.uploads.max_bytes / (1024 * 1024),
+                is_admin: true,
+            }));
+        }
+    };
+    log_activity(
+        &state.db,
+        "media_uploaded",
+        &format!("{} uploaded {} to the media library.", auth.display_name, item.original_name),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    if wants_json {
+        return Ok(HttpResponse::Ok().json(media_payload(&item)));
+    }
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/media"))
+        .finish())
+}
+
+async fn update_media_alt(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<MediaAltForm>,
+) -> Result<HttpResponse> {
+    media::update_alt_text(&state.db, &path.into_inner(), &form.alt_text)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/media"))
+        .finish())
+}
+
+async fn delete_media(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    auth: web::ReqData<AuthUser>,
+) -> Result<HttpResponse> {
+    let media_id = path.into_inner();
+    let deleted = media::delete(&state, &media_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if deleted {
+        log_activity(
+            &state.db,
+            "media_deleted",
+            &format!("{} deleted media {}.", auth.display_name, media_id),
+            Some(&auth.id),
+            None,
+        )
+        .await;
+    }
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/admin/media"))
+        .finish())
+}
+
+fn media_payload(item: &MediaItem) -> serde_json::Value {
+    serde_json::json!({
+        "id": item.id,
+        "name": item.original_name,
+        "src": item.url_for(CMS_IMAGE_WIDTH),
+        "thumb": item.url_for(320),
+        "srcset": item.srcset(),
+        "alt": item.alt_text,
+        "width": item.width,
+        "height": item.height,
+    })
+}
+
+async fn fetch_barbers(state: &web::Data<AppState>) -> Result<Vec<BarberView>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, UserRow>(
+        "SELECT id, username, display_name, role, password_hash, active, created_at FROM users WHERE role IN (?, ?) ORDER BY display_name",
+    )
+    .bind(ROLE_BARBER)
+    .bind(ROLE_ADMIN)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|user| BarberView {
+            id: user.id,
+            username: user.username,
+            display_name: user.display_name,
+            role: user.role,
+            active: user.active == 1,
+            selected: false,
+        })
+        .collect())
+}
+
+fn to_view(row: AppointmentRow, zone: Tz) -> AppointmentView {
+    let notes = row.notes.unwrap_or_default();
+    let client_email = row.client_email.unwrap_or_default();
+    AppointmentView {
+        id: row.id,
+        client_name: row.client_name,
+        client_phone: row.client_phone,
+        client_email: client_email.clone(),
+        has_email: !client_email.trim().is_empty(),
+        address: row.address,
+        service: row.service,
+        notes: notes.clone(),
+        has_notes: !notes.trim().is_empty(),
+        scheduled_for: schedule::display(&row.scheduled_for, zone),
+        status: row.status,
+        barber_id: row.barber_id.unwrap_or_default(),
+        barber_name: row.barber_name.unwrap_or_else(|| "Unassigned".to_string()),
+        latitude: row.latitude,
+        longitude: row.longitude,
+    }
+}
+
+fn count(query: &str, state: &web::Data<AppState>) -> CountQuery {
+    CountQuery {
+        query: query.to_string(),
+        state: state.clone(),
+    }
+}
+
+struct CountQuery {
+    query: String,
+    state: web::Data<AppState>,
+}
+
+impl CountQuery {
+    async fn run(self) -> i64 {
+        sqlx::query_scalar::<_, i64>(&self.query)
+            .fetch_one(&self.state.db)
+            .await
+            .unwrap_or(0)
+    }
+
+    async fn run_with_param(self, param: &str) -> i64 {
+        sqlx::query_scalar::<_, i64>(&self.query)
+            .bind(param)
+            .fetch_one(&self.state.db)
+            .await
+            .unwrap_or(0)
+    }
 }
@@ -1,17 +1,27 @@
+use actix_multipart::form::{bytes::Bytes, text::Text, MultipartForm};
 use actix_web::{http::header, middleware::from_fn, web, HttpResponse, Result};
 use actix_web_httpauth::middleware::HttpAuthentication;
 use askama::Template;
+use chrono_tz::Tz;
 use serde::Deserialize;
 
 use crate::{
     auth::{barber_validator, logout_guard, AuthUser},
     db::{fetch_appointment_event, log_activity},
+    dispatch, i18n, invoices,
+    itinerary::Itinerary,
+    line_items, media,
     models::{
-        AppointmentRow, STATUS_ACCEPTED, STATUS_COMPLETED, STATUS_DECLINED, STATUS_PENDING,
+        format_decimal, format_money, parse_money, service_catalog, AppointmentRow, BarberProfileRow, OFFER_OPEN, PROFILE_PENDING, STATUS_ACCEPTED, STATUS_CANCELLED, STATUS_COMPLETED,
+        STATUS_DECLINED, STATUS_NO_SHOW, STATUS_PENDING, STRIKE_NO_SHOW,
     },
-    push,
+    payments, policy,
+    payouts::{self, Period},
+    pricing, profiles, push, reviews,
+    schedule::{self, parse_scheduled_for},
     state::{AppState, ServerEvent},
     templates::render,
+    waitlist,
 };
 
 #[derive(Clone, Debug)]
@@ -29,6 +39,36 @@ struct AppointmentView {
     status: String,
     latitude: Option<f64>,
     longitude: Option<f64>,
+    offer_expires_at: String,
+    has_offer: bool,
+    location_label: String,
+    access_notes: String,
+    gate_code: String,
+    items: Vec<ItemView>,
+    has_party: bool,
+    duration_minutes: i64,
+    shared: bool,
+    quote: String,
+    tip: String,
+}
+
+#[derive(Clone, Debug)]
+struct EarningsLineView {
+    period: String,
+    visits: i64,
+    gross: String,
+    commission: String,
+    tips: String,
+    net: String,
+}
+
+#[derive(Clone, Debug)]
+struct ItemView {
+    person_name: String,
+    service: String,
+    minutes: i64,
+    barber_name: String,
+    mine: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -46,6 +86,30 @@ struct BarberDashboardTemplate {
     is_admin: bool,
 }
 
+#[derive(Template)]
+#[template(path = "barber_earnings.html")]
+struct BarberEarningsTemplate {
+    lines: Vec<EarningsLineView>,
+    total: EarningsLineView,
+    commission_percent: String,
+    period: String,
+    from: String,
+    to: String,
+    is_admin: bool,
+}
+
+#[derive(Deserialize)]
+struct EarningsQuery {
+    period: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TipForm {
+    tip: String,
+}
+
 #[derive(Template)]
 #[template(path = "barber_appointments.html")]
 struct BarberAppointmentsTemplate {
@@ -54,11 +118,84 @@ struct BarberAppointmentsTemplate {
     is_admin: bool,
 }
 
+#[derive(Clone, Debug)]
+struct RouteStopView {
+    order: usize,
+    appointment_id: String,
+    client_name: String,
+    address: String,
+    service: String,
+    scheduled_for: String,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    leg: Option<RouteLegView>,
+}
+
+#[derive(Clone, Debug)]
+struct RouteLegView {
+    distance: String,
+    drive: String,
+    gap: String,
+    feasible: bool,
+}
+
+#[derive(Template)]
+#[template(path = "barber_route.html")]
+struct BarberRouteTemplate {
+    date: String,
+    stops: Vec<RouteStopView>,
+    total_distance: String,
+    conflicts: usize,
+    is_admin: bool,
+}
+
+#[derive(Deserialize)]
+struct RouteQuery {
+    date: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct AppointmentStatusForm {
     status: String,
 }
 
+#[derive(Clone, Debug)]
+struct ServiceChoice {
+    name: String,
+    selected: bool,
+}
+
+#[derive(Template)]
+#[template(path = "barber_profile.html")]
+struct BarberProfileTemplate {
+    bio: String,
+    specialties: String,
+    languages: String,
+    service_area: String,
+    photo_path: String,
+    services: Vec<ServiceChoice>,
+    review_status: String,
+    review_note: String,
+    errors: Vec<String>,
+    saved: bool,
+    max_bio_chars: usize,
+    is_admin: bool,
+}
+
+/// Avatars are shown at up to 96px, so the 320px copy covers high-density screens.
+const PROFILE_PHOTO_WIDTH: i64 = 320;
+
+#[derive(MultipartForm)]
+struct ProfileUpload {
+    bio: Option<Text<String>>,
+    specialties: Option<Text<String>>,
+    languages: Option<Text<String>>,
+    service_area: Option<Text<String>>,
+    services: Vec<Text<String>>,
+    remove_photo: Option<Text<String>>,
+    photo: Option<Bytes>,
+}
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/barber")
@@ -68,6 +205,14 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .service(web::resource("/").route(web::get().to(index)))
             .service(web::resource("/dashboard").route(web::get().to(dashboard)))
             .service(web::resource("/appointments").route(web::get().to(list_appointments)))
+            .service(web::resource("/route").route(web::get().to(route_page)))
+            .service(web::resource("/route.json").route(web::get().to(route_json)))
+            .service(web::resource("/route.geojson").route(web::get().to(route_geojson)))
+            .service(web::resource("/route.gpx").route(web::get().to(route_gpx)))
+            .service(web::resource("/earnings").route(web::get().to(earnings_page)))
+            .service(web::resource("/earnings.csv").route(web::get().to(earnings_csv)))
+            .service(web::resource("/profile").route(web::get().to(show_profile)).route(web::post().to(update_profile)))
+            .service(web::resource("/appointments/{id}/tip").route(web::post().to(update_tip)))
             .service(
                 web::resource("/appointments/{id}/status")
                     .route(web::post().to(update_status)),
@@ -141,7 +286,8 @@ async fn dashboard(state: web::Data<AppState>, auth: web::ReqData<AuthUser>) ->
     .await
     .unwrap_or_default();
 
-    let upcoming = rows.into_iter().map(to_view).collect();
+    let zone = schedule::staff_zone(&state.db, &state.time, &auth).await;
+    let upcoming = rows.into_iter().map(|row| to_view(row, zone)).collect();
 
     Ok(render(BarberDashboardTemplate {
         barber_name: auth.display_name.clone(),
@@ -159,15 +305,88 @@ async fn list_appointments(state: web::Data<AppState>, auth: web::ReqData<AuthUs
                   NULL as barber_name
            FROM appointments a
            WHERE a.barber_id = ? OR (a.barber_id IS NULL AND a.status = ?)
+              OR a.id IN (SELECT appointment_id FROM appointment_items WHERE barber_id = ?)
            ORDER BY a.requested_at DESC"#,
     )
     .bind(&auth.id)
     .bind(STATUS_PENDING)
+    .bind(&auth.id)
     .fetch_all(&state.db)
     .await
     .unwrap_or_default();
 
-    let appointments = rows.into_iter().map(to_view).collect();
+    let offers = sqlx::query_as::<_, (String, String)>(
+        "SELECT appointment_id, expires_at FROM dispatch_offers WHERE barber_id = ? AND status = ?",
+    )
+    .bind(&auth.id)
+    .bind(OFFER_OPEN)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    // Saved-location details are only for the assigned barber, and the gate code only once accepted.
+    let locations = sqlx::query_as::<_, (String, String, String, Option<String>, Option<String>)>(
+        r#"SELECT a.id, a.status, l.label, l.access_notes, l.gate_code
+           FROM appointments a
+           JOIN client_locations l ON l.id = a.location_id
+           WHERE a.barber_id = ?"#,
+    )
+    .bind(&auth.id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let durations = line_items::durations(&state.db, &rows).await;
+    let zone = schedule::staff_zone(&state.db, &state.time, &auth).await;
+    let mut appointments = Vec::new();
+    for row in rows {
+        // Barbers who only cover part of a group booking see it, but the booking's own barber runs it.
+        let shared = row.barber_id.as_deref().is_some_and(|barber_id| barber_id != auth.id);
+        let items = line_items::fetch_items(&state.db, &row.id).await;
+        let mut view = to_view(row, zone);
+        view.duration_minutes = durations.get(&view.id).copied().unwrap_or_default();
+        view.shared = shared;
+        view.has_party = items.len() > 1;
+        view.quote = pricing::fetch_quote(&state.db, &view.id)
+            .await
+            .map(|quote| pricing::summary(&quote))
+            .unwrap_or_default();
+        view.tip = match payouts::fetch_tip(&state.db, &view.id).await {
+            0 => String::new(),
+            cents => format_decimal(cents),
+        };
+        view.items = items
+            .into_iter()
+            .map(|item| ItemView {
+                minutes: item.minutes(),
+                mine: item.barber_id.as_deref() == Some(auth.id.as_str())
+                    || (item.barber_id.is_none() && !shared),
+                barber_name: item.barber_name.unwrap_or_default(),
+                person_name: item.person_name,
+                service: item.service,
+            })
+            .collect();
+        appointments.push(view);
+    }
+    let appointments = appointments
+        .into_iter()
+        .map(|mut view| {
+            if let Some((_, expires_at)) = offers.iter().find(|(id, _)| *id == view.id) {
+                view.offer_expires_at = expires_at.clone();
+                view.has_offer = true;
+            }
+            if let Some((_, status, label, access_notes, gate_code)) =
+                locations.iter().find(|(id, ..)| *id == view.id)
+            {
+                view.location_label = label.clone();
+                view.access_notes = access_notes.clone().unwrap_or_default();
+                if status == STATUS_ACCEPTED {
+                    view.gate_code = gate_code.clone().unwrap_or_default();
+                }
+            }
+            view
+        })
+        .collect();
 
     Ok(render(BarberAppointmentsTemplate {
         appointments,
@@ -185,28 +404,62 @@ async fn update_status(
     let appointment_id = path.into_inner();
     let form = form.into_inner();
     let status = form.status;
-    let allowed = [STATUS_ACCEPTED, STATUS_DECLINED, STATUS_COMPLETED, STATUS_PENDING];
+    let allowed = [STATUS_ACCEPTED, STATUS_DECLINED, STATUS_COMPLETED, STATUS_PENDING, STATUS_NO_SHOW];
     if !allowed.contains(&status.as_str()) {
         return Ok(HttpResponse::BadRequest().body("Invalid status"));
     }
 
-    let current = sqlx::query_as::<_, (Option<String>, String)>(
-        "SELECT barber_id, status FROM appointments WHERE id = ?",
+    let current = sqlx::query_as::<_, (Option<String>, String, String)>(
+        "SELECT barber_id, status, scheduled_for FROM appointments WHERE id = ?",
     )
     .bind(&appointment_id)
     .fetch_optional(&state.db)
     .await
     .unwrap_or(None);
 
-    let (barber_id, _current_status) = match current {
+    let (barber_id, current_status, scheduled_for) = match current {
         Some(row) => row,
         None => return Ok(HttpResponse::NotFound().body("Appointment not found")),
     };
+    if current_status == STATUS_CANCELLED {
+        return Ok(HttpResponse::Conflict().body("Appointment was cancelled"));
+    }
 
     let can_edit = barber_id.as_deref().is_none() || barber_id.as_deref() == Some(&auth.id);
     if !can_edit {
         return Ok(HttpResponse::Forbidden().body("Not allowed"));
     }
+    // Completing settles payment and issues the invoice, so it needs a visit this barber took on.
+    if status == STATUS_COMPLETED && (current_status != STATUS_ACCEPTED || barber_id.as_deref() != Some(&auth.id)) {
+        return Ok(HttpResponse::Conflict().body("Only your accepted appointments can be marked as completed"));
+    }
+    if status == STATUS_NO_SHOW {
+        if current_status != STATUS_ACCEPTED || barber_id.as_deref() != Some(&auth.id) {
+            return Ok(HttpResponse::Conflict().body("Only your accepted appointments can be marked as a no-show"));
+        }
+        let now = chrono::Utc::now();
+        if parse_scheduled_for(&scheduled_for).is_some_and(|start| start > now) {
+            return Ok(HttpResponse::Conflict().body("The visit hasn't started yet"));
+        }
+    }
+
+    if status == STATUS_DECLINED && dispatch::respond(&state.db, &appointment_id, &auth.id, false).await {
+        dispatch::release(&state, &appointment_id, &auth.id).await;
+        log_activity(
+            &state.db,
+            "dispatch_declined",
+            &format!("{} passed on offered appointment {}.", auth.display_name, appointment_id),
+            Some(&auth.id),
+            Some(&appointment_id),
+        )
+        .await;
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/barber/appointments"))
+            .finish());
+    }
+    if status == STATUS_ACCEPTED {
+        dispatch::respond(&state.db, &appointment_id, &auth.id, true).await;
+    }
 
     let assigned = if status == STATUS_ACCEPTED {
         Some(auth.id.clone())
@@ -221,6 +474,24 @@ async fn update_status(
         .execute(&state.db)
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
+    line_items::recompute_duration(&state.db, &appointment_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    if status == STATUS_NO_SHOW {
+        policy::record_strike(&state, &appointment_id, STRIKE_NO_SHOW)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    } else {
+        policy::clear_strike(&state.db, &appointment_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    payments::settle(&state, &appointment_id).await;
+    invoices::issue(&state, &appointment_id).await;
+    reviews::request(&state, &appointment_id).await;
+    if status == STATUS_DECLINED {
+        waitlist::slot_freed(&state, &appointment_id).await;
+    }
 
     log_activity(
         &state.db,
@@ -232,11 +503,12 @@ async fn update_status(
     .await;
 
     let status_url = format!("/status/{appointment_id}");
+    let locale = i18n::appointment_locale(&state.db, &appointment_id).await;
     push::notify_appointment(
         &state,
         &appointment_id,
-        "Appointment updated",
-        &format!("Status changed to {}.", status),
+        locale.t("Appointment updated"),
+        &locale.format("Status changed to {status}.", &[("status", &locale.t(&status))]),
         Some(status_url.as_str()),
     )
     .await;
@@ -252,7 +524,362 @@ async fn update_status(
         .finish())
 }
 
-fn to_view(row: AppointmentRow) -> AppointmentView {
+async fn show_profile(state: web::Data<AppState>, auth: web::ReqData<AuthUser>) -> Result<HttpResponse> {
+    let draft = profiles::fetch_draft(&state.db, &auth.id).await;
+    let (review_status, review_note) = draft
+        .as_ref()
+        .map(|draft| (draft.status.clone(), draft.review_note.clone().unwrap_or_default()))
+        .unwrap_or_default();
+    let profile = match draft {
+        Some(draft) => draft.profile,
+        None => profiles::fetch_published(&state.db, &auth.id).await.unwrap_or_default(),
+    };
+    Ok(render(profile_template(profile, review_status, review_note, Vec::new(), false)))
+}
+
+async fn update_profile(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    form: MultipartForm<ProfileUpload>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let text = |field: Option<Text<String>>| field.map(|value| value.into_inner().trim().to_string()).filter(|value| !value.is_empty());
+    let published_photo = profiles::fetch_published(&state.db, &auth.id).await.and_then(|profile| profile.photo_path);
+    let draft_photo = profiles::fetch_draft(&state.db, &auth.id).await.and_then(|draft| draft.profile.photo_path);
+    let current_photo = draft_photo.clone().or(published_photo.clone());
+
+    let catalog = service_catalog();
+    let services: Vec<String> = form
+        .services
+        .into_iter()
+        .map(|service| service.into_inner())
+        .filter(|service| catalog.iter().any(|option| option.name == service))
+        .collect();
+    let mut profile = BarberProfileRow {
+        bio: text(form.bio),
+        specialties: profiles::join_list(&profiles::split_list(text(form.specialties).as_deref())),
+        languages: profiles::join_list(&profiles::split_list(text(form.languages).as_deref())),
+        services: profiles::join_list(&services),
+        service_area: text(form.service_area),
+        photo_path: current_photo.clone(),
+    };
+
+    let mut errors = Vec::new();
+    if profile.bio.as_deref().is_some_and(|bio| bio.chars().count() > profiles::MAX_BIO_CHARS) {
+        errors.push(format!("Please keep your bio under {} characters.", profiles::MAX_BIO_CHARS));
+    }
+    let upload = form.photo.filter(|photo| !photo.data.is_empty());
+    if errors.is_empty() {
+        if let Some(upload) = upload {
+            let name = upload.file_name.clone().unwrap_or_else(|| "photo".to_string());
+            let alt_text = format!("Photo of {}", auth.display_name);
+            match media::upload(&state, upload.data.to_vec(), &name, Some(&alt_text), Some(&auth.id)).await {
+                Ok(item) => profile.photo_path = Some(item.url_for(PROFILE_PHOTO_WIDTH).to_string()),
+                Err(err) => errors.push(err.to_string()),
+            }
+        } else if form.remove_photo.is_some() {
+            profile.photo_path = None;
+        }
+    }
+    if !errors.is_empty() {
+        return Ok(render(profile_template(profile, String::new(), String::new(), errors, false)));
+    }
+
+    profiles::submit_draft(&state.db, &auth.id, &profile)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    // A photo from an earlier, never approved draft is no longer referenced anywhere.
+    if let Some(photo) = draft_photo
+        && profile.photo_path.as_ref() != Some(&photo)
+        && published_photo.as_ref() != Some(&photo)
+    {
+        media::delete_by_url(&state, &photo)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    log_activity(
+        &state.db,
+        "profile_submitted",
+        &format!("{} submitted profile changes for approval.", auth.display_name),
+        Some(&auth.id),
+        None,
+    )
+    .await;
+
+    Ok(render(profile_template(profile, PROFILE_PENDING.to_string(), String::new(), Vec::new(), true)))
+}
+
+fn profile_template(
+    profile: BarberProfileRow,
+    review_status: String,
+    review_note: String,
+    errors: Vec<String>,
+    saved: bool,
+) -> BarberProfileTemplate {
+    let offered = profiles::split_list(profile.services.as_deref());
+    BarberProfileTemplate {
+        bio: profile.bio.unwrap_or_default(),
+        specialties: profile.specialties.unwrap_or_default(),
+        languages: profile.languages.unwrap_or_default(),
+        service_area: profile.service_area.unwrap_or_default(),
+        photo_path: profile.photo_path.unwrap_or_default(),
+        services: service_catalog()
+            .into_iter()
+            .map(|service| ServiceChoice {
+                selected: offered.iter().any(|name| name == service.name),
+                name: service.name.to_string(),
+            })
+            .collect(),
+        review_status,
+        review_note,
+        errors,
+        saved,
+        max_bio_chars: profiles::MAX_BIO_CHARS,
+        is_admin: false,
+    }
+}
+
+async fn update_tip(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    path: web::Path<String>,
+    form: web::Form<TipForm>,
+) -> Result<HttpResponse> {
+    let appointment_id = path.into_inner();
+    let Some(tip_cents) = parse_money(&form.tip) else {
+        return Ok(HttpResponse::BadRequest().body("Invalid tip amount"));
+    };
+    let current = sqlx::query_as::<_, (Option<String>, String)>(
+        "SELECT barber_id, status FROM appointments WHERE id = ?",
+    )
+    .bind(&appointment_id)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    let Some((barber_id, status)) = current else {
+        return Ok(HttpResponse::NotFound().body("Appointment not found"));
+    };
+    if barber_id.as_deref() != Some(auth.id.as_str()) {
+        return Ok(HttpResponse::Forbidden().body("Not allowed"));
+    }
+    if status != STATUS_COMPLETED {
+        return Ok(HttpResponse::Conflict().body("Tips can only be recorded for completed appointments"));
+    }
+
+    payouts::record_tip(&state.db, &appointment_id, tip_cents)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    log_activity(
+        &state.db,
+        "tip_recorded",
+        &format!(
+            "{} recorded a {} tip for appointment {}.",
+            auth.display_name,
+            format_money(tip_cents),
+            appointment_id
+        ),
+        Some(&auth.id),
+        Some(&appointment_id),
+    )
+    .await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, format!("/barber/appointments#appointment-{appointment_id}")))
+        .finish())
+}
+
+async fn build_earnings(state: &AppState, barber_id: &str, query: &EarningsQuery) -> payouts::PayoutReport {
+    let period = Period::parse(query.period.as_deref());
+    // Earnings follow the business's days, so they add up to the payouts report.
+    let zone = state.time.business_zone;
+    let today = schedule::today(zone);
+    let (from, to) = payouts::parse_range(period, query.from.as_deref(), query.to.as_deref(), today);
+    payouts::build_report(&state.db, &state.payouts, period, from, to, Some(barber_id), zone).await
+}
+
+fn earnings_line_view(period: String, earnings: &payouts::Earnings) -> EarningsLineView {
+    EarningsLineView {
+        period,
+        visits: earnings.visits,
+        gross: format_money(earnings.gross_cents),
+        commission: format_money(earnings.commission_cents),
+        tips: format_money(earnings.tips_cents),
+        net: format_money(earnings.net_cents()),
+    }
+}
+
+async fn earnings_page(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    query: web::Query<EarningsQuery>,
+) -> Result<HttpResponse> {
+    let report = build_earnings(&state, &auth.id, &query).await;
+    let commission_percent = sqlx::query_scalar::<_, Option<f64>>("SELECT commission_percent FROM users WHERE id = ?")
+        .bind(&auth.id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None)
+        .flatten()
+        .unwrap_or(state.payouts.default_commission_percent);
+    let lines = report
+        .lines
+        .iter()
+        .map(|line| earnings_line_view(line.period_label.clone(), &line.earnings))
+        .collect();
+
+    Ok(render(BarberEarningsTemplate {
+        lines,
+        total: earnings_line_view("Total".to_string(), &report.total),
+        commission_percent: commission_percent.to_string(),
+        period: report.period.as_str().to_string(),
+        from: report.from.format("%Y-%m-%d").to_string(),
+        to: report.to.format("%Y-%m-%d").to_string(),
+        is_admin: false,
+    }))
+}
+
+async fn earnings_csv(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    query: web::Query<EarningsQuery>,
+) -> Result<HttpResponse> {
+    let report = build_earnings(&state, &auth.id, &query).await;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", report.filename("earnings")),
+        ))
+        .body(report.to_csv()))
+}
+
+async fn route_page(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    query: web::Query<RouteQuery>,
+) -> Result<HttpResponse> {
+    let itinerary = build_itinerary(&state, &auth.id, query.date.as_deref()).await;
+    let conflicts = itinerary.legs.iter().filter(|leg| !leg.feasible).count();
+    let stops = itinerary
+        .stops
+        .iter()
+        .enumerate()
+        .map(|(index, stop)| {
+            // Leg `index - 1` ends at this stop.
+            let leg = index
+                .checked_sub(1)
+                .and_then(|leg_index| itinerary.legs.get(leg_index))
+                .map(|leg| RouteLegView {
+                    distance: leg
+                        .distance_km
+                        .map(|km| format!("{km:.1} km"))
+                        .unwrap_or_else(|| "unknown distance".to_string()),
+                    drive: leg
+                        .drive_minutes
+                        .map(|minutes| format!("~{minutes} min drive"))
+                        .unwrap_or_else(|| "drive time unknown".to_string()),
+                    gap: leg
+                        .gap_minutes
+                        .map(|minutes| format!("{minutes} min free"))
+                        .unwrap_or_else(|| "gap unknown".to_string()),
+                    feasible: leg.feasible,
+                });
+            RouteStopView {
+                order: index + 1,
+                appointment_id: stop.appointment_id.clone(),
+                client_name: stop.client_name.clone(),
+                address: stop.address.clone(),
+                service: stop.service.clone(),
+                scheduled_for: stop.scheduled_for.clone(),
+                latitude: stop.latitude,
+                longitude: stop.longitude,
+                leg,
+            }
+        })
+        .collect();
+
+    Ok(render(BarberRouteTemplate {
+        date: itinerary.date.clone(),
+        stops,
+        total_distance: format!("{:.1} km", itinerary.total_distance_km),
+        conflicts,
+        is_admin: false,
+    }))
+}
+
+async fn route_json(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    query: web::Query<RouteQuery>,
+) -> Result<HttpResponse> {
+    let itinerary = build_itinerary(&state, &auth.id, query.date.as_deref()).await;
+    Ok(HttpResponse::Ok().json(itinerary))
+}
+
+async fn route_geojson(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    query: web::Query<RouteQuery>,
+) -> Result<HttpResponse> {
+    let itinerary = build_itinerary(&state, &auth.id, query.date.as_deref()).await;
+    Ok(HttpResponse::Ok()
+        .content_type("application/geo+json")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"route-{}.geojson\"", itinerary.date),
+        ))
+        .body(itinerary.to_geojson().to_string()))
+}
+
+async fn route_gpx(
+    state: web::Data<AppState>,
+    auth: web::ReqData<AuthUser>,
+    query: web::Query<RouteQuery>,
+) -> Result<HttpResponse> {
+    let itinerary = build_itinerary(&state, &auth.id, query.date.as_deref()).await;
+    Ok(HttpResponse::Ok()
+        .content_type("application/gpx+xml")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"route-{}.gpx\"", itinerary.date),
+        ))
+        .body(itinerary.to_gpx()))
+}
+
+async fn build_itinerary(state: &web::Data<AppState>, barber_id: &str, date: Option<&str>) -> Itinerary {
+    // A route day runs midnight to midnight where the barber works.
+    let zone = schedule::barber_zone(&state.db, &state.time, Some(barber_id)).await;
+    let day = date
+        .and_then(|value| chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok())
+        .unwrap_or_else(|| schedule::today(zone));
+    let (day_start, day_end) = schedule::day_bounds(day, zone);
+    let date = day.format("%Y-%m-%d").to_string();
+
+    let rows = sqlx::query_as::<_, AppointmentRow>(
+        r#"SELECT a.id, a.client_name, a.client_phone, a.client_email, a.address, a.service,
+                  a.notes, a.requested_at, a.scheduled_for, a.status, a.barber_id,
+                  a.latitude, a.longitude,
+                  NULL as barber_name
+           FROM appointments a
+           WHERE (a.barber_id = ? OR a.id IN (SELECT appointment_id FROM appointment_items WHERE barber_id = ?))
+             AND a.status = ? AND a.scheduled_for >= ? AND a.scheduled_for < ?
+           ORDER BY a.scheduled_for"#,
+    )
+    .bind(barber_id)
+    .bind(barber_id)
+    .bind(STATUS_ACCEPTED)
+    .bind(&day_start)
+    .bind(&day_end)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let durations = line_items::durations(&state.db, &rows).await;
+    Itinerary::build(&date, rows, &durations, state.routes.average_speed_kmh, zone)
+}
+
+fn to_view(row: AppointmentRow, zone: Tz) -> AppointmentView {
     let notes = row.notes.unwrap_or_default();
     let client_email = row.client_email.unwrap_or_default();
     AppointmentView {
@@ -265,10 +892,21 @@ fn to_view(row: AppointmentRow) -> AppointmentView {
         service: row.service,
         notes: notes.clone(),
         has_notes: !notes.trim().is_empty(),
-        scheduled_for: row.scheduled_for,
+        scheduled_for: schedule::display(&row.scheduled_for, zone),
         status: row.status,
         latitude: row.latitude,
         longitude: row.longitude,
+        offer_expires_at: String::new(),
+        has_offer: false,
+        location_label: String::new(),
+        access_notes: String::new(),
+        gate_code: String::new(),
+        items: Vec::new(),
+        has_party: false,
+        duration_minutes: 0,
+        shared: false,
+        quote: String::new(),
+        tip: String::new(),
     }
 }
 
@@ -1,11 +1,12 @@
 use actix_files::NamedFile;
-use actix_web::{http::header, middleware::from_fn, web, HttpResponse, Result};
+use actix_web::{http::header, middleware::from_fn, web, HttpRequest, HttpResponse, Result};
 use actix_web_httpauth::middleware::HttpAuthentication;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt;
 
 use crate::{
-    auth::{basic_validator, logout_guard},
+    auth::{basic_validator, logout_guard, AuthUser},
+    schedule,
     state::{AppState, ServerEvent},
 };
 
@@ -26,10 +27,15 @@ async fn service_worker() -> Result<NamedFile> {
     Ok(NamedFile::open("./static/sw.js")?)
 }
 
-async fn stream_events(state: web::Data<AppState>) -> HttpResponse {
+async fn stream_events(state: web::Data<AppState>, auth: web::ReqData<AuthUser>) -> HttpResponse {
+    // Times go out in the zone the subscriber's pages show them in.
+    let zone = schedule::staff_zone(&state.db, &state.time, &auth).await;
     let rx = state.events.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|result| match result {
-        Ok(event) => Some(Ok::<web::Bytes, actix_web::Error>(event_to_bytes(&event))),
+    let stream = BroadcastStream::new(rx).filter_map(move |result| match result {
+        Ok(mut event) => {
+            event.scheduled_for = event.scheduled_for.map(|value| schedule::display(&value, zone));
+            Some(Ok::<web::Bytes, actix_web::Error>(event_to_bytes(&event)))
+        }
         Err(_) => None,
     });
 
@@ -54,10 +60,12 @@ struct PublicStatusEvent {
 }
 
 async fn stream_status_events(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<String>,
 ) -> HttpResponse {
     let appointment_id = path.into_inner();
+    let zone = schedule::client_zone(&req, &state.time);
     let rx = state.events.subscribe();
     let stream = BroadcastStream::new(rx).filter_map(move |result| {
         let event = match result {
@@ -71,7 +79,7 @@ async fn stream_status_events(
             appointment_id: event.appointment_id,
             status: event.status,
             service: event.service,
-            scheduled_for: event.scheduled_for,
+            scheduled_for: event.scheduled_for.map(|value| schedule::display(&value, zone)),
             barber_name: event.barber_name,
         };
         Some(Ok::<web::Bytes, actix_web::Error>(public_event_to_bytes(&public)))
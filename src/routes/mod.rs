@@ -1,4 +1,68 @@
 pub mod admin;
 pub mod barber;
 pub mod events;
+pub mod portal;
 pub mod public;
+
+use std::fmt::Display;
+
+use actix_web::HttpRequest;
+use chrono_tz::Tz;
+
+use crate::{
+    i18n::Locale,
+    pages::{self, NavPage},
+    schedule,
+    state::AppState,
+};
+
+/// What `base.html` needs on every public page.
+pub struct Layout {
+    pub nav_pages: Vec<NavPage>,
+    pub locale: Locale,
+    /// The zone times are shown and typed in.
+    pub zone: Tz,
+    /// Where the language switcher sends visitors back to.
+    pub path: String,
+}
+
+/// An entry in the language switcher.
+pub struct LanguageLink {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub href: String,
+    pub current: bool,
+}
+
+impl Layout {
+    pub async fn load(state: &AppState, req: &HttpRequest) -> Self {
+        Self {
+            nav_pages: pages::nav(&state.db).await,
+            locale: Locale::negotiate(req),
+            zone: schedule::client_zone(req, &state.time),
+            path: req.uri().path_and_query().map(|path| path.as_str()).unwrap_or("/").to_string(),
+        }
+    }
+
+    pub fn t<'a>(&self, text: &'a str) -> &'a str {
+        self.locale.t(text)
+    }
+
+    /// Translates `text` and fills in its one `{name}` placeholder.
+    pub fn t_with(&self, text: &str, name: &str, value: impl Display) -> String {
+        self.locale.format(text, &[(name, &value)])
+    }
+
+    pub fn languages(&self) -> Vec<LanguageLink> {
+        let next: String = url::form_urlencoded::byte_serialize(self.path.as_bytes()).collect();
+        Locale::ALL
+            .into_iter()
+            .map(|locale| LanguageLink {
+                code: locale.code(),
+                name: locale.name(),
+                href: format!("/language/{}?next={next}", locale.code()),
+                current: locale == self.locale,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+
+use actix_web::{http::header, web, HttpRequest, HttpResponse, Result};
+use askama::Template;
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::{
+    auth::{clear_client_session_cookie, client_session_cookie, CLIENT_SESSION_COOKIE},
+    clients::{self, NewLocation},
+    db::{fetch_appointment_event, log_activity},
+    dispatch, geocode,
+    i18n::Locale,
+    messaging::OutboundMessage,
+    models::{AppointmentRow, ClientRow, STATUS_ACCEPTED, STATUS_COMPLETED, STATUS_PENDING, STRIKE_LATE_CANCELLATION},
+    payments, policy,
+    recurrence::{self, Scope},
+    routes::Layout,
+    schedule::{self, parse_scheduled_for},
+    state::{AppState, ServerEvent},
+    templates::render,
+    validation::{self, HorizonError},
+    waitlist,
+};
+
+#[derive(Clone, Debug)]
+struct PortalAppointmentView {
+    id: String,
+    service: String,
+    scheduled_for: String,
+    scheduled_input: String,
+    status: String,
+    barber_name: String,
+    address: String,
+    in_series: bool,
+    /// How the series repeats, e.g. "Every week until 2026-06-30".
+    repeats: String,
+    can_change: bool,
+    reschedule_error: String,
+}
+
+#[derive(Clone, Debug)]
+struct LocationView {
+    id: String,
+    label: String,
+    address: String,
+    access_notes: String,
+    has_gate_code: bool,
+}
+
+#[derive(Template)]
+#[template(path = "account_login.html")]
+struct AccountLoginTemplate {
+    layout: Layout,
+    contact: String,
+    errors: Vec<String>,
+    sent: bool,
+}
+
+#[derive(Template)]
+#[template(path = "account.html")]
+struct AccountTemplate {
+    layout: Layout,
+    client_name: String,
+    upcoming: Vec<PortalAppointmentView>,
+    past: Vec<PortalAppointmentView>,
+    locations: Vec<LocationView>,
+    cancellation_policy: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    contact: String,
+}
+
+#[derive(Deserialize)]
+struct CancelForm {
+    apply_to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RescheduleForm {
+    scheduled_for: String,
+    apply_to: Option<String>,
+    timezone: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LocationForm {
+    label: String,
+    address: String,
+    access_notes: Option<String>,
+    gate_code: Option<String>,
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/account").route(web::get().to(account)))
+        .service(
+            web::resource("/account/login")
+                .route(web::get().to(show_login))
+                .route(web::post().to(request_link)),
+        )
+        .service(web::resource("/account/verify/{token}").route(web::get().to(verify_link)))
+        .service(web::resource("/account/logout").route(web::post().to(logout)))
+        .service(web::resource("/account/appointments/{id}/cancel").route(web::post().to(cancel_appointment)))
+        .service(web::resource("/account/appointments/{id}/reschedule").route(web::post().to(reschedule_appointment)))
+        .service(web::resource("/account/locations").route(web::post().to(create_location)))
+        .service(web::resource("/account/locations/{id}/delete").route(web::post().to(delete_location)));
+}
+
+async fn show_login(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse> {
+    Ok(render(AccountLoginTemplate {
+        layout: Layout::load(&state, &req).await,
+        contact: String::new(),
+        errors: Vec::new(),
+        sent: false,
+    }))
+}
+
+async fn request_link(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    form: web::Form<LoginForm>,
+) -> Result<HttpResponse> {
+    let contact = form.into_inner().contact.trim().to_string();
+    let locale = Locale::negotiate(&req);
+    if contact.is_empty() {
+        return Ok(render(AccountLoginTemplate {
+            layout: Layout::load(&state, &req).await,
+            contact,
+            errors: vec![locale.t("Enter the email or phone number you booked with.").to_string()],
+            sent: false,
+        }));
+    }
+
+    let Some(base_url) = state.portal.base_url.as_deref() else {
+        log::error!("PUBLIC_BASE_URL is not set, so sign-in links can't be sent");
+        return Ok(render(AccountLoginTemplate {
+            layout: Layout::load(&state, &req).await,
+            contact,
+            errors: vec![locale
+                .t("Sign-in links can't be sent right now. Please contact us to manage your bookings.")
+                .to_string()],
+            sent: false,
+        }));
+    };
+
+    // The response is the same whether or not we know the contact, so the form can't be used
+    // to find out who has booked.
+    if let Some((client, channel, destination)) = clients::find_by_contact(&state.db, &contact, state.booking.phone_country_code.as_deref()).await {
+        let token = clients::issue_login_token(
+            &state.db,
+            &client.id,
+            channel,
+            state.portal.link_ttl_minutes,
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+        let message = OutboundMessage {
+            channel,
+            to: destination,
+            subject: locale.t("Your Barber2Go sign-in link").to_string(),
+            body: locale.format(
+                "Hi {name}, open this link to see your bookings: {link}\nIt works once and expires in {minutes} minutes.",
+                &[
+                    ("name", &client.name),
+                    ("link", &format!("{base_url}/account/verify/{token}")),
+                    ("minutes", &state.portal.link_ttl_minutes),
+                ],
+            ),
+        };
+        if let Err(err) = state.messages.send(&message).await {
+            log::warn!("Sending sign-in link to client {} failed: {err}", client.id);
+        }
+    }
+
+    Ok(render(AccountLoginTemplate {
+        layout: Layout::load(&state, &req).await,
+        contact,
+        errors: Vec::new(),
+        sent: true,
+    }))
+}
+
+/// Sent after a booking while none of the client's contacts is verified. Opening the link proves
+/// the contact is theirs, which is what lets it request sign-in links later.
+pub async fn send_contact_confirmation(state: &AppState, client_id: &str, locale: Locale) {
+    let Some((channel, destination, contact)) = clients::unverified_contact(&state.db, client_id).await else {
+        return;
+    };
+    let Some(base_url) = state.portal.base_url.as_deref() else {
+        log::error!("PUBLIC_BASE_URL is not set, so client {client_id} wasn't sent a confirmation link");
+        return;
+    };
+    let token = match clients::issue_confirmation_token(&state.db, client_id, channel, &contact).await {
+        Ok(token) => token,
+        Err(err) => {
+            log::warn!("Creating a contact confirmation for client {client_id} failed: {err}");
+            return;
+        }
+    };
+    let name = sqlx::query_scalar::<_, String>("SELECT name FROM clients WHERE id = ?")
+        .bind(client_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None)
+        .unwrap_or_default();
+    let message = OutboundMessage {
+        channel,
+        to: destination,
+        subject: locale.t("Confirm your Barber2Go booking").to_string(),
+        body: locale.format(
+            "Hi {name}, thanks for your booking. Open this link to confirm it's you and to see your bookings online: {link}",
+            &[
+                ("name", &name),
+                ("link", &format!("{base_url}/account/verify/{token}")),
+            ],
+        ),
+    };
+    if let Err(err) = state.messages.send(&message).await {
+        log::warn!("Sending contact confirmation to client {client_id} failed: {err}");
+    }
+}
+
+async fn verify_link(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let token = path.into_inner();
+    let Some(client_id) = clients::redeem_login_token(&state.db, &token).await else {
+        return Ok(render(AccountLoginTemplate {
+            layout: Layout::load(&state, &req).await,
+            contact: String::new(),
+            errors: vec![Locale::negotiate(&req)
+                .t("That sign-in link has expired or was already used. Request a new one below.")
+                .to_string()],
+            sent: false,
+        }));
+    };
+
+    let session_id = clients::create_session(&state.db, &client_id, state.portal.session_days)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .cookie(client_session_cookie(&req, &session_id, state.portal.session_days))
+        .append_header((header::LOCATION, "/account"))
+        .finish())
+}
+
+async fn logout(req: HttpRequest, state: web::Data<AppState>) -> HttpResponse {
+    if let Some(cookie) = req.cookie(CLIENT_SESSION_COOKIE) {
+        clients::end_session(&state.db, cookie.value()).await;
+    }
+    HttpResponse::SeeOther()
+        .cookie(clear_client_session_cookie(&req))
+        .append_header((header::LOCATION, "/account/login"))
+        .finish()
+}
+
+async fn account(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse> {
+    let Some(client) = clients::current_client(&req, &state.db).await else {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/account/login"))
+            .finish());
+    };
+    render_account(&req, &state, client, None).await
+}
+
+/// The bookings page; `reschedule_error` is shown next to the reschedule form of that appointment.
+async fn render_account(
+    req: &HttpRequest,
+    state: &AppState,
+    client: ClientRow,
+    reschedule_error: Option<(&str, String)>,
+) -> Result<HttpResponse> {
+    let rows = sqlx::query_as::<_, AppointmentRow>(
+        r#"SELECT a.id, a.client_name, a.client_phone, a.client_email, a.address, a.service,
+                  a.notes, a.requested_at, a.scheduled_for, a.status, a.barber_id,
+                  a.latitude, a.longitude,
+                  u.display_name as barber_name
+           FROM appointments a
+           LEFT JOIN users u ON a.barber_id = u.id
+           WHERE a.client_id = ?
+           ORDER BY a.scheduled_for DESC"#,
+    )
+    .bind(&client.id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    let now = chrono::Utc::now();
+    let (mut upcoming, past): (Vec<_>, Vec<_>) = rows.into_iter().partition(|row| {
+        row.status != STATUS_COMPLETED
+            && parse_scheduled_for(&row.scheduled_for).is_none_or(|start| start >= now)
+    });
+    upcoming.reverse();
+
+    let locations = clients::fetch_locations(&state.db, &client.id)
+        .await
+        .into_iter()
+        .map(|location| LocationView {
+            id: location.id,
+            label: location.label,
+            address: location.address,
+            access_notes: location.access_notes.unwrap_or_default(),
+            has_gate_code: location.gate_code.is_some(),
+        })
+        .collect();
+
+    let layout = Layout::load(state, req).await;
+    let locale = layout.locale;
+    let zone = layout.zone;
+    let series_members: HashMap<String, String> = sqlx::query_as::<_, (String, String, Option<String>, Option<i64>)>(
+        r#"SELECT a.id, s.frequency, s.until_date, s.occurrence_count
+           FROM appointments a
+           JOIN appointment_series s ON s.id = a.series_id
+           WHERE a.client_id = ?"#,
+    )
+    .bind(&client.id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default()
+    .into_iter()
+    .map(|(id, frequency, until_date, count)| {
+        (id, recurrence::describe(&frequency, until_date.as_deref(), count, locale))
+    })
+    .collect();
+
+    let mut upcoming: Vec<_> = upcoming
+        .into_iter()
+        .map(|row| to_view(row, &series_members, locale, zone))
+        .collect();
+    if let Some((appointment_id, message)) = reschedule_error
+        && let Some(view) = upcoming.iter_mut().find(|view| view.id == appointment_id)
+    {
+        view.reschedule_error = message;
+    }
+    Ok(render(AccountTemplate {
+        layout,
+        client_name: client.name,
+        upcoming,
+        past: past
+            .into_iter()
+            .map(|row| to_view(row, &series_members, locale, zone))
+            .collect(),
+        locations,
+        cancellation_policy: state.policy.summary(locale),
+    }))
+}
+
+async fn create_location(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    form: web::Form<LocationForm>,
+) -> Result<HttpResponse> {
+    let Some(client) = clients::current_client(&req, &state.db).await else {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/account/login"))
+            .finish());
+    };
+    let form = form.into_inner();
+    if form.address.trim().is_empty() {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/account"))
+            .finish());
+    }
+
+    let point = match state.geocoder.as_deref() {
+        Some(geocoder) => geocode::lookup(&state.db, geocoder, &form.address)
+            .await
+            .unwrap_or(None),
+        None => None,
+    };
+    clients::create_location(
+        &state.db,
+        &client.id,
+        NewLocation {
+            label: &form.label,
+            address: &form.address,
+            latitude: point.as_ref().map(|point| point.latitude),
+            longitude: point.as_ref().map(|point| point.longitude),
+            access_notes: form.access_notes.as_deref(),
+            gate_code: form.gate_code.as_deref(),
+        },
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/account"))
+        .finish())
+}
+
+async fn delete_location(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    if let Some(client) = clients::current_client(&req, &state.db).await {
+        clients::archive_location(&state.db, &client.id, &path.into_inner())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/account"))
+        .finish())
+}
+
+fn to_view(
+    row: AppointmentRow,
+    series_members: &HashMap<String, String>,
+    locale: Locale,
+    zone: Tz,
+) -> PortalAppointmentView {
+    PortalAppointmentView {
+        scheduled_for: schedule::display(&row.scheduled_for, zone),
+        scheduled_input: schedule::input_value(&row.scheduled_for, zone),
+        in_series: series_members.contains_key(&row.id),
+        repeats: series_members.get(&row.id).cloned().unwrap_or_default(),
+        can_change: row.status == STATUS_PENDING || row.status == STATUS_ACCEPTED,
+        reschedule_error: String::new(),
+        id: row.id,
+        service: row.service,
+        status: row.status,
+        barber_name: row.barber_name.unwrap_or_else(|| locale.t("Unassigned").to_string()),
+        address: row.address,
+    }
+}
+
+/// Whether the appointment belongs to the signed-in client and can still be changed.
+async fn owned_open_appointment(state: &web::Data<AppState>, client_id: &str, appointment_id: &str) -> bool {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM appointments WHERE id = ? AND client_id = ? AND status IN (?, ?)",
+    )
+    .bind(appointment_id)
+    .bind(client_id)
+    .bind(STATUS_PENDING)
+    .bind(STATUS_ACCEPTED)
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(0)
+        > 0
+}
+
+async fn broadcast_updates(state: &web::Data<AppState>, appointment_ids: &[String]) {
+    for id in appointment_ids {
+        if let Some(row) = fetch_appointment_event(&state.db, id).await {
+            let _ = state
+                .events
+                .send(ServerEvent::from_row("appointment_updated", row));
+        }
+    }
+}
+
+async fn cancel_appointment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<CancelForm>,
+) -> Result<HttpResponse> {
+    let appointment_id = path.into_inner();
+    let Some(client) = clients::current_client(&req, &state.db).await else {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/account/login"))
+            .finish());
+    };
+    if !owned_open_appointment(&state, &client.id, &appointment_id).await {
+        return Ok(HttpResponse::NotFound().body("Appointment not found"));
+    }
+
+    let scope = Scope::parse(form.apply_to.as_deref());
+    let cancelled = recurrence::cancel(&state.db, &appointment_id, scope)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    for id in policy::late_cancellations(&state, &cancelled).await {
+        policy::record_strike(&state, &id, STRIKE_LATE_CANCELLATION)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+    for id in &cancelled {
+        payments::settle(&state, id).await;
+        waitlist::slot_freed(&state, id).await;
+    }
+    log_activity(
+        &state.db,
+        "appointment_cancelled",
+        &if cancelled.len() > 1 {
+            format!("{} cancelled {} visits in a series.", client.name, cancelled.len())
+        } else {
+            format!("{} cancelled their appointment.", client.name)
+        },
+        None,
+        Some(&appointment_id),
+    )
+    .await;
+    broadcast_updates(&state, &cancelled).await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/account"))
+        .finish())
+}
+
+async fn reschedule_appointment(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<RescheduleForm>,
+) -> Result<HttpResponse> {
+    let appointment_id = path.into_inner();
+    let Some(client) = clients::current_client(&req, &state.db).await else {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((header::LOCATION, "/account/login"))
+            .finish());
+    };
+    if !owned_open_appointment(&state, &client.id, &appointment_id).await {
+        return Ok(HttpResponse::NotFound().body("Appointment not found"));
+    }
+    let zone = schedule::form_zone(&req, &state.time, form.timezone.as_deref());
+    let locale = Locale::negotiate(&req);
+    let new_start = schedule::parse_local(&form.scheduled_for, zone);
+    let error = match new_start.map(|at| validation::within_horizon(at, chrono::Utc::now(), state.booking.horizon_days)) {
+        None => Some(locale.t("Please pick a valid date and time.").to_string()),
+        Some(Err(HorizonError::Past)) => Some(locale.t("Please pick a time in the future.").to_string()),
+        Some(Err(HorizonError::TooFar)) => Some(locale.format(
+            "Visits can be booked up to {days} days ahead.",
+            &[("days", &state.booking.horizon_days)],
+        )),
+        Some(Ok(())) => None,
+    };
+    let (Some(new_start), None) = (new_start, error.clone()) else {
+        return render_account(&req, &state, client, Some((&appointment_id, error.unwrap_or_default()))).await;
+    };
+
+    // A new time needs the barber to confirm again, so moved visits go back to pending. Series
+    // step in the barber's zone, so a visit keeps its local hour across a clock change.
+    let scope = Scope::parse(form.apply_to.as_deref());
+    let series_zone = schedule::appointment_zone(&state.db, &state.time, &appointment_id).await;
+    let moves = recurrence::planned_moves(&state.db, &appointment_id, new_start, scope, series_zone)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    for (id, start) in &moves {
+        let Some(row) = fetch_appointment_event(&state.db, id).await else {
+            continue;
+        };
+        if let Some(barber_id) = row.barber_id.as_deref()
+            && !dispatch::barber_is_free(&state.db, barber_id, id, &row.service, *start).await
+        {
+            let message = locale.format(
+                "Your barber is already booked at {time}. Please pick another time.",
+                &[("time", &schedule::display(&schedule::to_stored(*start), zone))],
+            );
+            return render_account(&req, &state, client, Some((&appointment_id, message))).await;
+        }
+    }
+    let moved = recurrence::reschedule(&state.db, &appointment_id, new_start, scope, true, series_zone)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    log_activity(
+        &state.db,
+        "appointment_rescheduled",
+        &format!(
+            "{} moved {} to {}.",
+            client.name,
+            if moved.len() > 1 { "their series" } else { "their appointment" },
+            form.scheduled_for.trim()
+        ),
+        None,
+        Some(&appointment_id),
+    )
+    .await;
+    broadcast_updates(&state, &moved).await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((header::LOCATION, "/account"))
+        .finish())
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use actix_web::{http::header, web, HttpRequest, HttpResponse, Result};
 use actix_web::http::header::Header;
 use actix_web_httpauth::headers::authorization::{Authorization, Basic};
@@ -7,11 +9,33 @@ use serde_json::json;
 
 use crate::{
     auth::{authenticate_credentials, clear_logout_cookie, logout_cookie, new_id, AUTH_REALM},
+    clients::{self, NewLocation},
+    cms,
     db::{fetch_appointment_event, log_activity},
-    models::{AppointmentRow, CmsBlockRow, ServiceOption, STATUS_PENDING, ROLE_ADMIN},
+    dispatch,
+    geo::{self, Coverage},
+    geocode,
+    i18n::{self, locale_cookie, Locale},
+    invoices,
+    line_items::{self, NewItem, MAX_GUESTS},
+    models::{
+        addon_catalog, format_money, service_catalog, AddOnOption, AppointmentRow, ServiceOption, CURRENCY_CODE,
+        REVIEW_APPROVED, REVIEW_REQUESTED, STATUS_PENDING, ROLE_ADMIN, ROLE_BARBER,
+    },
+    pages,
+    payments::{self, Charge, ChargeRequest},
+    policy,
+    pricing,
+    profiles,
     push,
+    recurrence::{self, Frequency, RecurrenceEnd},
+    reviews,
+    routes::{portal, Layout},
+    schedule,
     state::{AppState, ServerEvent},
     templates::render,
+    validation::{self, FieldErrors, HorizonError},
+    waitlist,
 };
 
 #[derive(Clone, Debug)]
@@ -25,6 +49,7 @@ struct BarberSummary {
 #[derive(Template)]
 #[template(path = "home.html")]
 struct HomeTemplate {
+    layout: Layout,
     hero_html: String,
     about_html: String,
     services_html: String,
@@ -40,28 +65,69 @@ struct BookingView {
     address: String,
     scheduled_for: String,
     notes: String,
+    latitude: String,
+    longitude: String,
+    location_label: String,
+    access_notes: String,
+    gate_code: String,
+    save_location: bool,
+    repeat: String,
+    repeat_until: String,
+    repeat_count: String,
+    guests: Vec<GuestView>,
+    waitlist_token: String,
+    /// The zone `scheduled_for` is typed in, sent back with the form.
+    timezone: String,
+}
+
+#[derive(Clone, Debug)]
+struct GuestView {
+    name: String,
+    service: String,
+}
+
+#[derive(Clone, Debug)]
+struct LocationOption {
+    id: String,
+    label: String,
+    address: String,
+    latitude: String,
+    longitude: String,
+    selected: bool,
 }
 
 #[derive(Template)]
 #[template(path = "book.html")]
 struct BookingTemplate {
+    layout: Layout,
     services: Vec<ServiceOption>,
+    addons: Vec<AddOnOption>,
     barbers: Vec<BarberSummary>,
     form: BookingView,
     errors: Vec<String>,
+    field_errors: FieldErrors,
     vapid_public_key: String,
+    signed_in: bool,
+    locations: Vec<LocationOption>,
+    max_guests: usize,
+    deposit_percent: i64,
+    cancellation_policy: Option<String>,
 }
 
 #[derive(Template)]
 #[template(path = "book_success.html")]
 struct BookingSuccessTemplate {
+    layout: Layout,
     appointment_id: String,
+    quote: String,
+    deposit: String,
     vapid_public_key: String,
 }
 
 #[derive(Template)]
 #[template(path = "status.html")]
 struct StatusTemplate {
+    layout: Layout,
     appointment_id: String,
     found: bool,
     client_name: String,
@@ -69,12 +135,153 @@ struct StatusTemplate {
     scheduled_for: String,
     status: String,
     barber_name: String,
+    invoice_number: String,
+}
+
+#[derive(Clone, Debug)]
+struct InvoiceLineView {
+    description: String,
+    amount: String,
+}
+
+#[derive(Clone, Debug)]
+struct InvoiceTotalView {
+    label: String,
+    amount: String,
+    strong: bool,
+}
+
+#[derive(Template)]
+#[template(path = "invoice.html")]
+struct InvoiceTemplate {
+    layout: Layout,
+    appointment_id: String,
+    number: String,
+    issued_on: String,
+    business_name: String,
+    business_details: Vec<String>,
+    client_name: String,
+    client_email: String,
+    address: String,
+    barber_name: String,
+    lines: Vec<InvoiceLineView>,
+    totals: Vec<InvoiceTotalView>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct WaitlistView {
+    client_name: String,
+    client_phone: String,
+    client_email: String,
+    wanted_date: String,
+}
+
+#[derive(Template)]
+#[template(path = "waitlist.html")]
+struct WaitlistTemplate {
+    layout: Layout,
+    services: Vec<ServiceOption>,
+    barbers: Vec<BarberSummary>,
+    form: WaitlistView,
+    errors: Vec<String>,
+    joined: bool,
+    claim_minutes: i64,
+    vapid_public_key: String,
 }
 
 #[derive(Template)]
 #[template(path = "barbers.html")]
 struct BarbersTemplate {
-    barbers: Vec<BarberSummary>,
+    layout: Layout,
+    barbers: Vec<BarberProfile>,
+}
+
+#[derive(Clone, Debug)]
+struct BarberProfile {
+    id: String,
+    display_name: String,
+    initials: String,
+    photo_path: String,
+    bio: String,
+    specialties: Vec<String>,
+    languages: String,
+    services: Vec<String>,
+    service_area: String,
+    rating: String,
+    reviews: Vec<PublicReview>,
+}
+
+#[derive(Template)]
+#[template(path = "barber.html")]
+struct BarberPageTemplate {
+    layout: Layout,
+    barber: BarberProfile,
+}
+
+#[derive(Template)]
+#[template(path = "page.html")]
+struct PageTemplate {
+    layout: Layout,
+    title: String,
+    meta_description: String,
+    blocks: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+struct PublicReview {
+    stars: String,
+    comment: String,
+    client_name: String,
+}
+
+#[derive(Template)]
+#[template(path = "review.html")]
+struct ReviewTemplate {
+    layout: Layout,
+    token: String,
+    barber_name: String,
+    client_name: String,
+    rating: i64,
+    comment: String,
+    errors: Vec<String>,
+    submitted: bool,
+}
+
+#[derive(Deserialize)]
+struct ReviewForm {
+    rating: Option<i64>,
+    comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CoverageQuery {
+    lat: Option<f64>,
+    lon: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct BookingPrefill {
+    rebook: Option<String>,
+    waitlist: Option<String>,
+    preferred_barber_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WaitlistPrefill {
+    date: Option<String>,
+    service: Option<String>,
+    barber_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WaitlistForm {
+    client_name: String,
+    client_phone: String,
+    client_email: Option<String>,
+    service: String,
+    wanted_date: String,
+    preferred_barber_id: Option<String>,
+    push_subscription: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -90,14 +297,37 @@ struct BookingForm {
     latitude: Option<String>,
     longitude: Option<String>,
     push_subscription: Option<String>,
+    location_id: Option<String>,
+    save_location: Option<String>,
+    location_label: Option<String>,
+    access_notes: Option<String>,
+    gate_code: Option<String>,
+    repeat: Option<String>,
+    repeat_until: Option<String>,
+    repeat_count: Option<String>,
+    payment_token: Option<String>,
+    waitlist_token: Option<String>,
+    /// The zone the form showed its times in.
+    timezone: Option<String>,
+    /// Extra people arrive as numbered `guest_name_N` / `guest_service_N` pairs.
+    #[serde(flatten)]
+    extra: HashMap<String, String>,
 }
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(web::resource("/").route(web::get().to(home)))
         .service(web::resource("/book").route(web::get().to(show_booking)).route(web::post().to(create_booking)))
+        .service(web::resource("/book/coverage").route(web::get().to(booking_coverage)))
         .service(web::resource("/barbers").route(web::get().to(list_barbers)))
+        .service(web::resource("/barbers/{id}").route(web::get().to(show_barber)))
+        .service(web::resource("/p/{slug}").route(web::get().to(show_page)))
+        .service(web::resource("/waitlist").route(web::get().to(show_waitlist)).route(web::post().to(join_waitlist)))
+        .service(web::resource("/review/{token}").route(web::get().to(show_review)).route(web::post().to(submit_review)))
         .service(web::resource("/status/{id}").route(web::get().to(status_page)))
+        .service(web::resource("/status/{id}/invoice").route(web::get().to(invoice_page)))
+        .service(web::resource("/status/{id}/invoice.pdf").route(web::get().to(invoice_pdf)))
         .service(web::resource("/status/{id}/subscribe").route(web::post().to(subscribe_notifications)))
+        .service(web::resource("/language/{code}").route(web::get().to(switch_language)))
         .service(web::resource("/login").route(web::get().to(login)))
         .service(web::resource("/logout").route(web::get().to(logout)))
         .service(web::resource("/health").route(web::get().to(health)));
@@ -115,6 +345,22 @@ async fn logout(req: HttpRequest) -> HttpResponse {
         .finish()
 }
 
+#[derive(Deserialize)]
+struct LanguageQuery {
+    next: Option<String>,
+}
+
+/// The language switcher: remembers the choice in a cookie and returns to the page it came from.
+async fn switch_language(req: HttpRequest, path: web::Path<String>, query: web::Query<LanguageQuery>) -> HttpResponse {
+    let next = query.next.as_deref().filter(|next| next.starts_with('/') && !next.starts_with("//")).unwrap_or("/");
+    let mut response = HttpResponse::SeeOther();
+    response.append_header((header::LOCATION, next));
+    if let Some(locale) = Locale::parse(&path.into_inner()) {
+        response.cookie(locale_cookie(&req, locale));
+    }
+    response.finish()
+}
+
 #[derive(Deserialize)]
 struct LoginQuery {
     next: Option<String>,
@@ -172,14 +418,16 @@ fn auth_challenge() -> HttpResponse {
         .finish()
 }
 
-async fn home(state: web::Data<AppState>) -> Result<HttpResponse> {
-    let hero_html = cms_block_html(&state, "home_hero").await;
-    let about_html = cms_block_html(&state, "home_about").await;
-    let services_html = cms_block_html(&state, "home_services").await;
+async fn home(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse> {
+    let layout = Layout::load(&state, &req).await;
+    let hero_html = cms::localized_html(&state.db, "home_hero", layout.locale).await;
+    let about_html = cms::localized_html(&state.db, "home_about", layout.locale).await;
+    let services_html = cms::localized_html(&state.db, "home_services", layout.locale).await;
     let services = service_catalog();
     let barbers = fetch_barbers(&state).await.unwrap_or_default();
 
     Ok(render(HomeTemplate {
+        layout,
         hero_html,
         about_html,
         services_html,
@@ -188,39 +436,421 @@ async fn home(state: web::Data<AppState>) -> Result<HttpResponse> {
     }))
 }
 
-async fn show_booking(state: web::Data<AppState>) -> Result<HttpResponse> {
-    let services = service_catalog();
-    let barbers = fetch_barbers(&state).await.unwrap_or_default();
+async fn show_booking(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<BookingPrefill>,
+) -> Result<HttpResponse> {
+    let layout = Layout::load(&state, &req).await;
+    let locale = layout.locale;
+    let mut services = service_catalog();
+    let mut barbers = fetch_barbers(&state).await.unwrap_or_default();
+    let mut form = BookingView::default();
+    let client = clients::current_client(&req, &state.db).await;
+    let mut selected_location = String::new();
+
+    if let Some(client) = &client {
+        form.client_name = client.name.clone();
+        form.client_phone = client.phone.clone().unwrap_or_default();
+        form.client_email = client.email.clone().unwrap_or_default();
+    }
+
+    // "Book with this barber" from a barber's profile page.
+    if let Some(barber_id) = query.preferred_barber_id.as_deref() {
+        for barber in &mut barbers {
+            barber.selected = barber.id == barber_id;
+        }
+    }
+
+    // "Book again" from the client portal: same service, barber and address as a past visit.
+    if let Some(rebook_id) = query.rebook.as_deref()
+        && let Some(client) = &client
+        && let Some(previous) = fetch_client_appointment(&state, &client.id, rebook_id).await
+    {
+        form = BookingView {
+            client_name: client.name.clone(),
+            client_phone: client.phone.clone().unwrap_or(previous.client_phone),
+            client_email: client.email.clone().or(previous.client_email).unwrap_or_default(),
+            address: previous.address,
+            latitude: previous.latitude.map(|value| value.to_string()).unwrap_or_default(),
+            longitude: previous.longitude.map(|value| value.to_string()).unwrap_or_default(),
+            guests: line_items::fetch_items(&state.db, &previous.id)
+                .await
+                .into_iter()
+                .skip(1)
+                .take(MAX_GUESTS)
+                .map(|item| GuestView {
+                    name: item.person_name,
+                    service: item.service,
+                })
+                .collect(),
+            ..BookingView::default()
+        };
+        selected_location = fetch_appointment_location(&state, &previous.id).await.unwrap_or_default();
+        for service in &mut services {
+            service.selected = service.name == previous.service;
+        }
+        if let Some(barber_id) = previous.barber_id.as_deref() {
+            for barber in &mut barbers {
+                barber.selected = barber.id == barber_id;
+            }
+        }
+    }
+
+    // A claim link from a waitlist offer books the freed slot.
+    let mut errors = Vec::new();
+    if let Some(token) = query.waitlist.as_deref().filter(|token| !token.is_empty()) {
+        match waitlist::open_offer(&state.db, token).await {
+            Some(offer) => {
+                for service in &mut services {
+                    service.selected = service.name == offer.service;
+                }
+                for barber in &mut barbers {
+                    barber.selected = offer.barber_id.as_deref() == Some(barber.id.as_str());
+                }
+                form.client_name = offer.client_name;
+                form.client_phone = offer.client_phone;
+                form.client_email = offer.client_email.unwrap_or_default();
+                form.scheduled_for = schedule::input_value(&offer.scheduled_for, layout.zone);
+                form.waitlist_token = offer.token;
+            }
+            None => errors.push(
+                locale
+                    .t("This waitlist offer has expired or was already taken. You can still request another time.")
+                    .to_string(),
+            ),
+        }
+    }
+
+    form.timezone = layout.zone.name().to_string();
+
+    let locations = match &client {
+        Some(client) => location_options(&state, &client.id, &selected_location).await,
+        None => Vec::new(),
+    };
+    let prepay = match &client {
+        Some(client) => policy::requires_prepayment(&state.db, &state.policy, &client.id).await,
+        None => false,
+    };
 
     Ok(render(BookingTemplate {
+        layout,
         services,
+        addons: addon_catalog(),
         barbers,
-        form: BookingView::default(),
-        errors: Vec::new(),
+        form,
+        errors,
+        field_errors: FieldErrors::default(),
         vapid_public_key: state.push.public_key.clone(),
+        signed_in: client.is_some(),
+        locations,
+        max_guests: MAX_GUESTS,
+        deposit_percent: deposit_percent(&state, prepay),
+        cancellation_policy: state.policy.summary(locale),
     }))
 }
 
+/// Zero when no payment provider is configured, which hides the card field.
+/// Clients with too many no-shows pay the whole quote up front.
+fn deposit_percent(state: &AppState, prepay: bool) -> i64 {
+    if state.payments.is_none() {
+        0
+    } else if prepay {
+        100
+    } else {
+        state.deposits.percent
+    }
+}
+
+/// Add-ons arrive as `addon_<key>` checkboxes.
+fn selected_addons(form: &BookingForm) -> Vec<AddOnOption> {
+    addon_catalog()
+        .into_iter()
+        .map(|mut addon| {
+            addon.selected = form.extra.contains_key(&format!("addon_{}", addon.key));
+            addon
+        })
+        .collect()
+}
+
+/// Guests in form order; rows left completely blank are dropped.
+fn parse_guests(form: &BookingForm) -> Vec<GuestView> {
+    (1..=MAX_GUESTS)
+        .map(|index| {
+            let field = |name: &str| {
+                form.extra
+                    .get(&format!("{name}_{index}"))
+                    .map(|value| value.trim().to_string())
+                    .unwrap_or_default()
+            };
+            GuestView {
+                name: field("guest_name"),
+                service: field("guest_service"),
+            }
+        })
+        .filter(|guest| !guest.name.is_empty() || !guest.service.is_empty())
+        .collect()
+}
+
+fn party_items(form: &BookingForm, guests: &[GuestView]) -> Result<Vec<NewItem>, &'static str> {
+    let mut items = vec![NewItem {
+        person_name: form.client_name.trim().to_string(),
+        service: form.service.clone(),
+    }];
+    for (index, guest) in guests.iter().enumerate() {
+        if !line_items::is_known_service(&guest.service) {
+            return Err("Pick a service for everyone in your group.");
+        }
+        items.push(NewItem {
+            person_name: if guest.name.is_empty() {
+                format!("Guest {}", index + 1)
+            } else {
+                guest.name.clone()
+            },
+            service: guest.service.clone(),
+        });
+    }
+    Ok(items)
+}
+
 async fn create_booking(
+    req: HttpRequest,
     state: web::Data<AppState>,
     form: web::Form<BookingForm>,
 ) -> Result<HttpResponse> {
-    let form = form.into_inner();
-    let mut errors = Vec::new();
+    let mut form = form.into_inner();
+    let locale = Locale::negotiate(&req);
+    let zone = schedule::form_zone(&req, &state.time, form.timezone.as_deref());
+    let client = clients::current_client(&req, &state.db).await;
+    let saved_location = match (&client, form.location_id.as_deref().filter(|id| !id.is_empty())) {
+        (Some(client), Some(location_id)) => clients::fetch_location(&state.db, &client.id, location_id).await,
+        _ => None,
+    };
+    if let Some(location) = &saved_location {
+        form.address = location.address.clone();
+        if let (Some(lat), Some(lon)) = (location.latitude, location.longitude) {
+            form.latitude = Some(lat.to_string());
+            form.longitude = Some(lon.to_string());
+        }
+    }
+    let too_far = locale.format(
+        "Visits can be booked up to {days} days ahead.",
+        &[("days", &state.booking.horizon_days)],
+    );
+    let mut fields = FieldErrors::default();
+    let mut field = |name: &'static str, message: &str| fields.add(name, locale.t(message));
+
+    // The pin comes from the map as two hidden fields; half a pin or one off the globe is dropped.
+    let coordinate = |value: Option<&str>| {
+        value
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<f64>().ok())
+    };
+    let point = match (coordinate(form.latitude.as_deref()), coordinate(form.longitude.as_deref())) {
+        // Without a pin the address is looked up now, so the service area check sees where the visit is.
+        (None, None) => geocode::locate(&state, &form.address)
+            .await
+            .map(|found| (found.latitude, found.longitude)),
+        (Some(Some(lat)), Some(Some(lon))) if validation::is_valid_point(lat, lon) => Some((lat, lon)),
+        _ => {
+            field("location", "The pinned location isn't valid. Move the pin again or pick the address from the list.");
+            None
+        }
+    };
+    let latitude = point.map(|(lat, _)| lat);
+    let longitude = point.map(|(_, lon)| lon);
+
     if form.client_name.trim().is_empty() {
-        errors.push("Full name is required.".to_string());
+        field("client_name", "Full name is required.");
     }
     if form.client_phone.trim().is_empty() {
-        errors.push("Phone number is required.".to_string());
+        field("client_phone", "Phone number is required.");
+    } else {
+        match validation::to_e164(&form.client_phone, state.booking.phone_country_code.as_deref()) {
+            Some(phone) => form.client_phone = phone,
+            None => field(
+                "client_phone",
+                "Enter a phone number with its country code, e.g. +385 91 234 5678.",
+            ),
+        }
+    }
+    form.client_email = form
+        .client_email
+        .as_deref()
+        .map(str::trim)
+        .filter(|email| !email.is_empty())
+        .map(str::to_string);
+    if form.client_email.as_deref().is_some_and(|email| !validation::is_valid_email(email)) {
+        field("client_email", "Enter a valid email address, e.g. name@example.com.");
     }
     if form.address.trim().is_empty() {
-        errors.push("Service address is required.".to_string());
+        field("address", "Service address is required.");
     }
-    if form.service.trim().is_empty() {
-        errors.push("Please select a service.".to_string());
+    if form.service.trim().is_empty() || !service_catalog().iter().any(|service| service.name == form.service) {
+        field("service", "Please select a service.");
     }
-    if form.scheduled_for.trim().is_empty() {
-        errors.push("Please pick a date and time.".to_string());
+    let scheduled_at = schedule::parse_local(&form.scheduled_for, zone);
+    match scheduled_at {
+        _ if form.scheduled_for.trim().is_empty() => field("scheduled_for", "Please pick a date and time."),
+        None => field("scheduled_for", "Please pick a valid date and time."),
+        Some(at) => match validation::within_horizon(at, chrono::Utc::now(), state.booking.horizon_days) {
+            Ok(()) => {}
+            Err(HorizonError::Past) => field("scheduled_for", "Please pick a time in the future."),
+            Err(HorizonError::TooFar) => field("scheduled_for", &too_far),
+        },
+    }
+    let guests = parse_guests(&form);
+    let items = match party_items(&form, &guests) {
+        Ok(items) => items,
+        Err(message) => {
+            field("guests", message);
+            Vec::new()
+        }
+    };
+    let recurrence = match parse_recurrence(&form) {
+        Ok(recurrence) => recurrence,
+        Err(message) => {
+            field("repeat", message);
+            None
+        }
+    };
+
+    let mut preferred_barber = form
+        .preferred_barber_id
+        .clone()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if !preferred_barber.is_empty() && !is_active_barber(&state, &preferred_barber).await {
+        field("preferred_barber_id", "That barber isn't taking bookings. Choose another barber or leave it open.");
+        preferred_barber.clear();
+    }
+
+    let coverage = geo::area_coverage(&state.db, latitude, longitude).await;
+    if coverage == Coverage::Outside && state.areas.reject_outside {
+        field(
+            "location",
+            "Sorry, that location is outside the areas we currently serve. Move the pin or pick a different address.",
+        );
+    }
+    if let (Some(lat), Some(lon)) = (latitude, longitude)
+        && !preferred_barber.is_empty()
+    {
+        let reach = geo::fetch_barber_reach(&state.db).await.unwrap_or_default();
+        let covers = reach
+            .iter()
+            .any(|barber| barber.id == preferred_barber && barber.covers(lat, lon));
+        if !covers {
+            field(
+                "preferred_barber_id",
+                "Your preferred barber doesn't travel to this location. Choose another barber or leave it open.",
+            );
+        }
+    }
+
+    let mut errors = Vec::new();
+    if !fields.is_empty() {
+        errors.push(locale.t("Please check the highlighted fields.").to_string());
+    }
+    let mut waitlist_token = form.waitlist_token.clone().unwrap_or_default().trim().to_string();
+    if !waitlist_token.is_empty() {
+        match waitlist::open_offer(&state.db, &waitlist_token).await {
+            None => {
+                errors.push(
+                    locale
+                        .t("This waitlist offer has expired or was already taken. You can still request another time.")
+                        .to_string(),
+                );
+                waitlist_token.clear();
+            }
+            // The offer only holds the freed visit, not any booking made through its link.
+            Some(offer)
+                if scheduled_at != schedule::parse_scheduled_for(&offer.scheduled_for)
+                    || form.service != offer.service
+                    || Some(preferred_barber.as_str()).filter(|id| !id.is_empty()) != offer.barber_id.as_deref() =>
+            {
+                errors.push(
+                    locale
+                        .t("This waitlist offer only holds the freed visit. Keep its service, time and barber to claim it.")
+                        .to_string(),
+                );
+            }
+            Some(_) => {}
+        }
+    }
+
+    let addons: Vec<AddOnOption> = selected_addons(&form)
+        .into_iter()
+        .filter(|addon| addon.selected)
+        .collect();
+    let travel_km = pricing::travel_distance_km(
+        &state.db,
+        Some(preferred_barber.as_str()).filter(|id| !id.is_empty()),
+        latitude,
+        longitude,
+    )
+    .await;
+    let quote = pricing::build_quote(&state.pricing, &items, &addons, travel_km);
+
+    let known_client = match &client {
+        Some(client) => Some(client.id.clone()),
+        None => {
+            clients::find_known(
+                &state.db,
+                &form.client_phone,
+                form.client_email.as_deref(),
+                state.booking.phone_country_code.as_deref(),
+            )
+            .await
+        }
+    };
+    let prepay = match &known_client {
+        Some(client_id) => policy::requires_prepayment(&state.db, &state.policy, client_id).await,
+        None => false,
+    };
+    if errors.is_empty() && prepay && state.payments.is_none() {
+        errors.push(
+            locale
+                .t("After several missed visits, bookings have to be paid up front, which isn't possible online right now. Please contact us to book.")
+                .to_string(),
+        );
+    }
+
+    // The deposit is charged before anything is saved so a declined card can be fixed on the same form.
+    // If saving the booking then fails, the charge is refunded below.
+    let mut deposit = None;
+    if errors.is_empty()
+        && let Some(provider) = state.payments.clone()
+    {
+        let amount_cents = if prepay {
+            quote.total_cents
+        } else {
+            state.deposits.amount_for(quote.total_cents)
+        };
+        let payment_method = form.payment_token.as_deref().map(str::trim).unwrap_or_default();
+        if amount_cents > 0 && payment_method.is_empty() && prepay {
+            errors.push(
+                locale
+                    .t("After several missed visits, bookings have to be paid in full up front. Please add card details.")
+                    .to_string(),
+            );
+        } else if amount_cents > 0 && payment_method.is_empty() {
+            errors.push(locale.t("Card details are required to pay the deposit.").to_string());
+        } else if amount_cents > 0 {
+            let request = ChargeRequest {
+                amount_cents,
+                currency: CURRENCY_CODE.to_string(),
+                payment_method: payment_method.to_string(),
+                customer: None,
+                description: format!("Barber2Go deposit for {}", form.client_name.trim()),
+                off_session: false,
+            };
+            match provider.charge(&request).await {
+                Ok(charge) => deposit = Some((provider.name(), amount_cents, charge)),
+                Err(err) => errors.push(locale.format("The deposit couldn't be charged: {error}", &[("error", &err)])),
+            }
+        }
     }
 
     if !errors.is_empty() {
@@ -228,13 +858,23 @@ async fn create_booking(
         for service in &mut services {
             service.selected = form.service == service.name;
         }
-        let mut barbers = fetch_barbers(&state).await.unwrap_or_default();
-        let selected_id = form.preferred_barber_id.as_deref();
+        let mut barbers = fetch_barbers_covering(&state, latitude, longitude)
+            .await
+            .unwrap_or_default();
         for barber in &mut barbers {
-            barber.selected = selected_id == Some(barber.id.as_str());
+            barber.selected = preferred_barber == barber.id;
         }
+        let locations = match &client {
+            Some(client) => {
+                let selected = saved_location.as_ref().map(|location| location.id.as_str()).unwrap_or_default();
+                location_options(&state, &client.id, selected).await
+            }
+            None => Vec::new(),
+        };
         return Ok(render(BookingTemplate {
+            layout: Layout::load(&state, &req).await,
             services,
+            addons: selected_addons(&form),
             barbers,
             form: BookingView {
                 client_name: form.client_name,
@@ -243,64 +883,160 @@ async fn create_booking(
                 address: form.address,
                 scheduled_for: form.scheduled_for,
                 notes: form.notes.unwrap_or_default(),
+                latitude: latitude.map(|value| value.to_string()).unwrap_or_default(),
+                longitude: longitude.map(|value| value.to_string()).unwrap_or_default(),
+                location_label: form.location_label.unwrap_or_default(),
+                access_notes: form.access_notes.unwrap_or_default(),
+                gate_code: form.gate_code.unwrap_or_default(),
+                save_location: form.save_location.is_some(),
+                repeat: form.repeat.unwrap_or_default(),
+                repeat_until: form.repeat_until.unwrap_or_default(),
+                repeat_count: form.repeat_count.unwrap_or_default(),
+                guests,
+                waitlist_token,
+                timezone: zone.name().to_string(),
             },
             errors,
+            field_errors: fields,
             vapid_public_key: state.push.public_key.clone(),
+            signed_in: client.is_some(),
+            locations,
+            max_guests: MAX_GUESTS,
+            deposit_percent: deposit_percent(&state, prepay),
+            cancellation_policy: state.policy.summary(locale),
         }));
     }
 
     let appointment_id = new_id();
     let now = chrono::Utc::now().to_rfc3339();
-    let preferred_barber = form
-        .preferred_barber_id
-        .unwrap_or_default()
-        .trim()
-        .to_string();
     let barber_id = if preferred_barber.is_empty() {
         None
     } else {
         Some(preferred_barber)
     };
-    let latitude = form
-        .latitude
-        .as_deref()
-        .and_then(|value| value.parse::<f64>().ok());
-    let longitude = form
-        .longitude
-        .as_deref()
-        .and_then(|value| value.parse::<f64>().ok());
+    let outside_area = coverage != Coverage::Inside;
+    let unassigned = barber_id.is_none();
+    // Everything the booking is made of is saved together; if any of it fails the half-saved
+    // appointment is removed and a deposit already taken is refunded.
+    let saved = async {
+        let client_id = match &client {
+            Some(client) => client.id.clone(),
+            None => {
+                clients::find_or_create(
+                    &state.db,
+                    &form.client_name,
+                    &form.client_phone,
+                    form.client_email.as_deref(),
+                    &form.address,
+                    state.booking.phone_country_code.as_deref(),
+                )
+                .await?
+            }
+        };
+        let location_id = match (&saved_location, &client) {
+            (Some(location), _) => Some(location.id.clone()),
+            (None, Some(client)) if form.save_location.is_some() => Some(
+                clients::create_location(
+                    &state.db,
+                    &client.id,
+                    NewLocation {
+                        label: form.location_label.as_deref().unwrap_or_default(),
+                        address: &form.address,
+                        latitude,
+                        longitude,
+                        access_notes: form.access_notes.as_deref(),
+                        gate_code: form.gate_code.as_deref(),
+                    },
+                )
+                .await?,
+            ),
+            _ => None,
+        };
 
-    sqlx::query(
-        r#"INSERT INTO appointments
-           (id, client_name, client_phone, client_email, address, service, notes, requested_at, scheduled_for, status, barber_id, latitude, longitude)
-           VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
-    )
-    .bind(&appointment_id)
-    .bind(&form.client_name)
-    .bind(&form.client_phone)
-    .bind(form.client_email)
-    .bind(&form.address)
-    .bind(&form.service)
-    .bind(form.notes)
-    .bind(now)
-    .bind(&form.scheduled_for)
-    .bind(STATUS_PENDING)
-    .bind(barber_id)
-    .bind(latitude)
-    .bind(longitude)
-    .execute(&state.db)
-    .await
-    .map_err(actix_web::error::ErrorInternalServerError)?;
+        sqlx::query(
+            r#"INSERT INTO appointments
+               (id, client_name, client_phone, client_email, address, service, notes, requested_at, scheduled_for, status, barber_id, latitude, longitude, outside_area, client_id, location_id, locale)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(&appointment_id)
+        .bind(&form.client_name)
+        .bind(&form.client_phone)
+        .bind(form.client_email.as_deref())
+        .bind(&form.address)
+        .bind(&form.service)
+        .bind(form.notes.as_deref())
+        .bind(&now)
+        .bind(scheduled_at.map(schedule::to_stored))
+        .bind(STATUS_PENDING)
+        .bind(barber_id.as_deref())
+        .bind(latitude)
+        .bind(longitude)
+        .bind(outside_area)
+        .bind(&client_id)
+        .bind(location_id)
+        .bind(locale.code())
+        .execute(&state.db)
+        .await?;
+
+        line_items::save_items(&state.db, &appointment_id, &items).await?;
+        pricing::save_quote(&state.db, &appointment_id, &quote, &addons).await?;
+        if let Some((provider, amount_cents, charge)) = &deposit {
+            payments::record_deposit(&state.db, provider, &appointment_id, *amount_cents, charge).await?;
+        }
+        if !waitlist_token.is_empty() {
+            waitlist::claim(&state.db, &waitlist_token, &appointment_id).await?;
+        }
+        Ok::<_, sqlx::Error>(client_id)
+    }
+    .await;
+    let client_id = match saved {
+        Ok(client_id) => client_id,
+        Err(err) => {
+            let _ = sqlx::query("DELETE FROM appointments WHERE id = ?")
+                .bind(&appointment_id)
+                .execute(&state.db)
+                .await;
+            if let Some((_, amount_cents, charge)) = &deposit {
+                refund_unsaved_deposit(&state, *amount_cents, charge, &form.client_name).await;
+            }
+            return Err(actix_web::error::ErrorInternalServerError(err));
+        }
+    };
+    if client.is_none() {
+        portal::send_contact_confirmation(&state, &client_id, locale).await;
+    }
 
     log_activity(
         &state.db,
         "appointment_created",
-        &format!("New appointment requested for {}.", form.client_name),
+        &if items.len() > 1 {
+            format!(
+                "New group appointment requested for {} and {} more.",
+                form.client_name,
+                items.len() - 1
+            )
+        } else {
+            format!("New appointment requested for {}.", form.client_name)
+        },
         None,
         Some(&appointment_id),
     )
     .await;
 
+    if outside_area {
+        log_activity(
+            &state.db,
+            "appointment_flagged",
+            &format!(
+                "Appointment for {} could not be matched to a service area.",
+                form.client_name
+            ),
+            None,
+            Some(&appointment_id),
+        )
+        .await;
+    }
+
     if let Some(subscription) = form
         .push_subscription
         .as_deref()
@@ -311,8 +1047,8 @@ async fn create_booking(
         push::notify_appointment(
             &state,
             &appointment_id,
-            "Appointment request received",
-            "We received your booking request. We'll confirm shortly.",
+            locale.t("Appointment request received"),
+            locale.t("We received your booking request. We'll confirm shortly."),
             Some(status_url.as_str()),
         )
         .await;
@@ -324,30 +1060,103 @@ async fn create_booking(
             .send(ServerEvent::from_row("appointment_created", row));
     }
 
+    if let Some((frequency, end)) = recurrence {
+        let zone = schedule::appointment_zone(&state.db, &state.time, &appointment_id).await;
+        let occurrences = recurrence::create_series(&state.db, &appointment_id, frequency, end, zone)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        log_activity(
+            &state.db,
+            "series_created",
+            &format!(
+                "{} set up a {} series with {} more visits.",
+                form.client_name,
+                frequency.as_str(),
+                occurrences.len()
+            ),
+            None,
+            Some(&appointment_id),
+        )
+        .await;
+        for occurrence_id in &occurrences {
+            if let Some(row) = fetch_appointment_event(&state.db, occurrence_id).await {
+                let _ = state
+                    .events
+                    .send(ServerEvent::from_row("appointment_created", row));
+            }
+        }
+    }
+
+    if latitude.is_none() || longitude.is_none() {
+        geocode::spawn_for_appointment(&state, &appointment_id);
+    }
+    if unassigned {
+        dispatch::spawn(&state, &appointment_id);
+    }
+
     Ok(render(BookingSuccessTemplate {
+        layout: Layout::load(&state, &req).await,
+        quote: pricing::fetch_quote(&state.db, &appointment_id)
+            .await
+            .map(|quote| pricing::summary(&quote))
+            .unwrap_or_default(),
+        deposit: deposit
+            .map(|(_, amount_cents, _)| format_money(amount_cents))
+            .unwrap_or_default(),
         appointment_id,
         vapid_public_key: state.push.public_key.clone(),
     }))
 }
 
+/// Gives back a deposit charged for a booking that couldn't be saved, so the client isn't left
+/// paying for a visit that doesn't exist.
+async fn refund_unsaved_deposit(state: &AppState, amount_cents: i64, charge: &Charge, client_name: &str) {
+    let Some(provider) = state.payments.clone() else {
+        return;
+    };
+    let (kind, message) = match provider.refund(&charge.reference, amount_cents).await {
+        Ok(_) => ("payment_refunded", format!(
+            "Refunded the {} deposit for {client_name} because the booking couldn't be saved.",
+            format_money(amount_cents)
+        )),
+        Err(err) => ("payment_failed", format!(
+            "Refunding the {} deposit ({}) for {client_name} failed after the booking couldn't be saved: {err}",
+            format_money(amount_cents),
+            charge.reference
+        )),
+    };
+    log_activity(&state.db, kind, &message, None, None).await;
+}
+
 async fn status_page(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<String>,
 ) -> Result<HttpResponse> {
     let appointment_id = path.into_inner();
     let row = fetch_appointment_event(&state.db, &appointment_id).await;
 
+    let invoice_number = invoices::fetch_invoice(&state.db, &appointment_id)
+        .await
+        .map(|invoice| state.invoices.display_number(invoice.number))
+        .unwrap_or_default();
+
+    let layout = Layout::load(&state, &req).await;
+    let zone = layout.zone;
     let template = match row {
         Some(row) => StatusTemplate {
+            barber_name: row.barber_name.unwrap_or_else(|| layout.t("Unassigned").to_string()),
+            layout,
             appointment_id,
             found: true,
             client_name: row.client_name,
             service: row.service,
-            scheduled_for: row.scheduled_for,
+            scheduled_for: schedule::display(&row.scheduled_for, zone),
             status: row.status,
-            barber_name: row.barber_name.unwrap_or_else(|| "Unassigned".to_string()),
+            invoice_number,
         },
         None => StatusTemplate {
+            layout,
             appointment_id,
             found: false,
             client_name: String::new(),
@@ -355,12 +1164,82 @@ async fn status_page(
             scheduled_for: String::new(),
             status: String::new(),
             barber_name: String::new(),
+            invoice_number,
         },
     };
 
     Ok(render(template))
 }
 
+async fn invoice_page(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let appointment_id = path.into_inner();
+    let Some(invoice) = invoices::fetch_invoice(&state.db, &appointment_id).await else {
+        return Ok(HttpResponse::NotFound().body("Invoice not found"));
+    };
+    let layout = Layout::load(&state, &req).await;
+    let lines = invoices::fetch_lines(&state.db, &invoice.id)
+        .await
+        .into_iter()
+        .map(|line| InvoiceLineView {
+            description: line.description,
+            amount: format_money(line.amount_cents),
+        })
+        .collect();
+    let totals = invoices::totals(&state.invoices, &invoice, layout.locale)
+        .into_iter()
+        .map(|total| InvoiceTotalView {
+            label: total.label,
+            amount: format_money(total.amount_cents),
+            strong: total.strong,
+        })
+        .collect();
+
+    let barber_name = invoice
+        .barber_name
+        .clone()
+        .unwrap_or_else(|| layout.t("Barber2Go team").to_string());
+
+    Ok(render(InvoiceTemplate {
+        layout,
+        appointment_id,
+        number: state.invoices.display_number(invoice.number),
+        issued_on: invoices::issued_on(&invoice).to_string(),
+        business_name: state.invoices.business_name.clone(),
+        business_details: state.invoices.detail_lines(),
+        client_name: invoice.client_name,
+        client_email: invoice.client_email.unwrap_or_default(),
+        address: invoice.address,
+        barber_name,
+        lines,
+        totals,
+    }))
+}
+
+async fn invoice_pdf(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse> {
+    let appointment_id = path.into_inner();
+    let Some(invoice) = invoices::fetch_invoice(&state.db, &appointment_id).await else {
+        return Ok(HttpResponse::NotFound().body("Invoice not found"));
+    };
+    let lines = invoices::fetch_lines(&state.db, &invoice.id).await;
+    let number = state.invoices.display_number(invoice.number);
+    let pdf = invoices::render_pdf(&state.invoices, &invoice, &lines);
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{number}.pdf\""),
+        ))
+        .body(pdf))
+}
+
 async fn subscribe_notifications(
     state: web::Data<AppState>,
     path: web::Path<String>,
@@ -381,11 +1260,12 @@ async fn subscribe_notifications(
 
     let _ = push::store_subscription(&state.db, &appointment_id, &raw).await;
     let status_url = format!("/status/{appointment_id}");
+    let locale = i18n::appointment_locale(&state.db, &appointment_id).await;
     push::notify_appointment(
         &state,
         &appointment_id,
-        "Notifications enabled",
-        "You'll receive updates about your appointment.",
+        locale.t("Notifications enabled"),
+        locale.t("You'll receive updates about your appointment."),
         Some(status_url.as_str()),
     )
     .await;
@@ -393,9 +1273,327 @@ async fn subscribe_notifications(
     Ok(HttpResponse::Ok().json(json!({ "ok": true })))
 }
 
-async fn list_barbers(state: web::Data<AppState>) -> Result<HttpResponse> {
-    let barbers = fetch_barbers(&state).await.unwrap_or_default();
-    Ok(render(BarbersTemplate { barbers }))
+async fn list_barbers(req: HttpRequest, state: web::Data<AppState>) -> Result<HttpResponse> {
+    let mut barbers = Vec::new();
+    for barber in fetch_barbers(&state).await.unwrap_or_default() {
+        barbers.push(barber_profile(&state, barber, 3).await);
+    }
+    Ok(render(BarbersTemplate {
+        layout: Layout::load(&state, &req).await,
+        barbers,
+    }))
+}
+
+async fn show_barber(req: HttpRequest, state: web::Data<AppState>, path: web::Path<String>) -> Result<HttpResponse> {
+    let barber_id = path.into_inner();
+    let Some(barber) = fetch_barbers(&state)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|barber| barber.id == barber_id)
+    else {
+        return Ok(HttpResponse::NotFound().body("Barber not found"));
+    };
+    Ok(render(BarberPageTemplate {
+        layout: Layout::load(&state, &req).await,
+        barber: barber_profile(&state, barber, 20).await,
+    }))
+}
+
+async fn show_page(req: HttpRequest, state: web::Data<AppState>, path: web::Path<String>) -> Result<HttpResponse> {
+    let Some(page) = pages::fetch_published(&state.db, &path.into_inner()).await else {
+        return Ok(HttpResponse::NotFound().body("Page not found"));
+    };
+    let layout = Layout::load(&state, &req).await;
+    let blocks = pages::localized_blocks(&state.db, &page.id, layout.locale).await;
+    Ok(render(PageTemplate {
+        layout,
+        title: page.title,
+        meta_description: page.meta_description.unwrap_or_default(),
+        blocks,
+    }))
+}
+
+/// The approved profile, rating and latest approved reviews for the public pages.
+async fn barber_profile(state: &web::Data<AppState>, barber: BarberSummary, review_limit: i64) -> BarberProfile {
+    let profile = profiles::fetch_published(&state.db, &barber.id).await.unwrap_or_default();
+    let reviews = reviews::for_barber(&state.db, &barber.id, Some(REVIEW_APPROVED), review_limit)
+        .await
+        .into_iter()
+        .map(|review| PublicReview {
+            stars: reviews::stars(review.rating.unwrap_or(0)),
+            comment: review.comment.unwrap_or_default(),
+            client_name: review.client_name.split_whitespace().next().unwrap_or_default().to_string(),
+        })
+        .collect();
+    BarberProfile {
+        rating: reviews::summary_for(&state.db, &barber.id).await.label(),
+        photo_path: profile.photo_path.unwrap_or_default(),
+        bio: profile.bio.unwrap_or_default(),
+        specialties: profiles::split_list(profile.specialties.as_deref()),
+        languages: profile.languages.unwrap_or_default(),
+        services: profiles::split_list(profile.services.as_deref()),
+        service_area: profile.service_area.unwrap_or_default(),
+        id: barber.id,
+        display_name: barber.display_name,
+        initials: barber.initials,
+        reviews,
+    }
+}
+
+async fn show_review(req: HttpRequest, state: web::Data<AppState>, path: web::Path<String>) -> Result<HttpResponse> {
+    let token = path.into_inner();
+    let Some(review) = reviews::fetch_by_token(&state.db, &token).await else {
+        return Ok(HttpResponse::NotFound().body("Review link not found"));
+    };
+    let layout = Layout::load(&state, &req).await;
+    Ok(render(ReviewTemplate {
+        barber_name: review.barber_name.unwrap_or_else(|| layout.t("your barber").to_string()),
+        layout,
+        token,
+        client_name: review.client_name,
+        rating: review.rating.unwrap_or(5),
+        comment: review.comment.unwrap_or_default(),
+        errors: Vec::new(),
+        submitted: review.status != REVIEW_REQUESTED,
+    }))
+}
+
+async fn submit_review(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    form: web::Form<ReviewForm>,
+) -> Result<HttpResponse> {
+    let token = path.into_inner();
+    let form = form.into_inner();
+    let Some(review) = reviews::fetch_by_token(&state.db, &token).await else {
+        return Ok(HttpResponse::NotFound().body("Review link not found"));
+    };
+    let comment = form.comment.unwrap_or_default();
+    let layout = Layout::load(&state, &req).await;
+
+    let mut errors = Vec::new();
+    let rating = form.rating.filter(|rating| (1..=5).contains(rating));
+    if rating.is_none() {
+        errors.push(layout.t("Please pick a rating from 1 to 5 stars.").to_string());
+    }
+    if comment.chars().count() > reviews::MAX_COMMENT_CHARS {
+        errors.push(layout.t_with(
+            "Please keep your comment under {count} characters.",
+            "count",
+            reviews::MAX_COMMENT_CHARS,
+        ));
+    }
+
+    let mut submitted = review.status != REVIEW_REQUESTED;
+    if !submitted
+        && errors.is_empty()
+        && let Some(rating) = rating
+    {
+        submitted = reviews::submit(&state, &token, rating, Some(&comment))
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
+    Ok(render(ReviewTemplate {
+        barber_name: review.barber_name.unwrap_or_else(|| layout.t("your barber").to_string()),
+        layout,
+        token,
+        client_name: review.client_name,
+        rating: rating.unwrap_or(5),
+        comment,
+        errors,
+        submitted,
+    }))
+}
+
+async fn show_waitlist(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<WaitlistPrefill>,
+) -> Result<HttpResponse> {
+    let mut services = service_catalog();
+    for service in &mut services {
+        service.selected = query.service.as_deref() == Some(service.name);
+    }
+    let mut barbers = fetch_barbers(&state).await.unwrap_or_default();
+    for barber in &mut barbers {
+        barber.selected = query.barber_id.as_deref() == Some(barber.id.as_str());
+    }
+    let mut form = WaitlistView {
+        wanted_date: query.date.clone().unwrap_or_default(),
+        ..WaitlistView::default()
+    };
+    if let Some(client) = clients::current_client(&req, &state.db).await {
+        form.client_name = client.name;
+        form.client_phone = client.phone.unwrap_or_default();
+        form.client_email = client.email.unwrap_or_default();
+    }
+
+    Ok(render(WaitlistTemplate {
+        layout: Layout::load(&state, &req).await,
+        services,
+        barbers,
+        form,
+        errors: Vec::new(),
+        joined: false,
+        claim_minutes: state.waitlist.claim_minutes,
+        vapid_public_key: state.push.public_key.clone(),
+    }))
+}
+
+async fn join_waitlist(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    form: web::Form<WaitlistForm>,
+) -> Result<HttpResponse> {
+    let form = form.into_inner();
+    let locale = Locale::negotiate(&req);
+    let today = schedule::today(schedule::client_zone(&req, &state.time));
+    let wanted_date = chrono::NaiveDate::parse_from_str(form.wanted_date.trim(), "%Y-%m-%d").ok();
+    let barber_id = form
+        .preferred_barber_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty());
+
+    let mut errors = Vec::new();
+    let mut error = |message: &str| errors.push(locale.t(message).to_string());
+    if form.client_name.trim().is_empty() {
+        error("Full name is required.");
+    }
+    if form.client_phone.trim().is_empty() {
+        error("Phone number is required.");
+    }
+    if !service_catalog().iter().any(|service| service.name == form.service) {
+        error("Please select a service.");
+    }
+    match wanted_date {
+        None => error("Please pick a date."),
+        Some(date) if date < today => error("Please pick a date from today on."),
+        Some(_) => {}
+    }
+
+    let client = clients::current_client(&req, &state.db).await;
+    if errors.is_empty()
+        && let Some(wanted_date) = wanted_date
+    {
+        let client_id = match &client {
+            Some(client) => Some(client.id.clone()),
+            None => {
+                clients::find_known(
+                    &state.db,
+                    &form.client_phone,
+                    form.client_email.as_deref(),
+                    state.booking.phone_country_code.as_deref(),
+                )
+                .await
+            }
+        };
+        waitlist::join(
+            &state.db,
+            waitlist::NewEntry {
+                client_id: client_id.as_deref(),
+                client_name: &form.client_name,
+                client_phone: &form.client_phone,
+                client_email: form.client_email.as_deref(),
+                service: &form.service,
+                wanted_date,
+                barber_id,
+                push_subscription: form.push_subscription.as_deref(),
+                locale,
+            },
+        )
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+        log_activity(
+            &state.db,
+            "waitlist_joined",
+            &format!(
+                "{} joined the waitlist for {} on {}.",
+                form.client_name.trim(),
+                form.service,
+                wanted_date.format("%Y-%m-%d")
+            ),
+            None,
+            None,
+        )
+        .await;
+    }
+
+    let mut services = service_catalog();
+    for service in &mut services {
+        service.selected = service.name == form.service;
+    }
+    let mut barbers = fetch_barbers(&state).await.unwrap_or_default();
+    for barber in &mut barbers {
+        barber.selected = barber_id == Some(barber.id.as_str());
+    }
+    Ok(render(WaitlistTemplate {
+        layout: Layout::load(&state, &req).await,
+        services,
+        barbers,
+        joined: errors.is_empty(),
+        form: WaitlistView {
+            client_name: form.client_name,
+            client_phone: form.client_phone,
+            client_email: form.client_email.unwrap_or_default(),
+            wanted_date: form.wanted_date,
+        },
+        errors,
+        claim_minutes: state.waitlist.claim_minutes,
+        vapid_public_key: state.push.public_key.clone(),
+    }))
+}
+
+async fn booking_coverage(
+    state: web::Data<AppState>,
+    query: web::Query<CoverageQuery>,
+) -> Result<HttpResponse> {
+    let coverage = geo::area_coverage(&state.db, query.lat, query.lon).await;
+    let barbers = fetch_barbers_covering(&state, query.lat, query.lon)
+        .await
+        .unwrap_or_default();
+    let served = match coverage {
+        Coverage::Inside => Some(true),
+        Coverage::Outside => Some(false),
+        Coverage::Unknown => None,
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "served": served,
+        "barbers": barbers
+            .into_iter()
+            .map(|barber| json!({ "id": barber.id, "display_name": barber.display_name }))
+            .collect::<Vec<_>>(),
+    })))
+}
+
+async fn fetch_barbers_covering(
+    state: &web::Data<AppState>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+) -> Result<Vec<BarberSummary>, sqlx::Error> {
+    let (Some(latitude), Some(longitude)) = (latitude, longitude) else {
+        return fetch_barbers(state).await;
+    };
+    let reach = geo::fetch_barber_reach(&state.db).await?;
+    Ok(reach
+        .into_iter()
+        .filter(|barber| barber.covers(latitude, longitude))
+        .map(|barber| barber_summary(barber.id, barber.display_name))
+        .collect())
+}
+
+async fn is_active_barber(state: &web::Data<AppState>, barber_id: &str) -> bool {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE id = ? AND role = ? AND active = 1")
+        .bind(barber_id)
+        .bind(ROLE_BARBER)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0)
+        > 0
 }
 
 async fn fetch_barbers(state: &web::Data<AppState>) -> Result<Vec<BarberSummary>, sqlx::Error> {
@@ -407,63 +1605,94 @@ async fn fetch_barbers(state: &web::Data<AppState>) -> Result<Vec<BarberSummary>
 
     Ok(rows
         .into_iter()
-        .map(|(id, display_name)| {
-            let initials = display_name
-                .split_whitespace()
-                .filter_map(|part| part.chars().next())
-                .take(2)
-                .collect::<String>();
-            BarberSummary {
-                id,
-                display_name,
-                initials: initials.to_uppercase(),
-                selected: false,
-            }
-        })
+        .map(|(id, display_name)| barber_summary(id, display_name))
         .collect())
 }
 
-fn service_catalog() -> Vec<ServiceOption> {
-    vec![
-        ServiceOption {
-            name: "Signature Cut",
-            duration: "45 min",
-            description: "Precision cut, styling, and lineup.",
-            selected: false,
-        },
-        ServiceOption {
-            name: "Fade & Line-Up",
-            duration: "35 min",
-            description: "Skin fade with sharp finishing touches.",
-            selected: false,
-        },
-        ServiceOption {
-            name: "Beard Sculpt",
-            duration: "25 min",
-            description: "Shape, trim, and conditioning for the beard.",
-            selected: false,
-        },
-        ServiceOption {
-            name: "Full Grooming",
-            duration: "60 min",
-            description: "Cut, beard, and grooming refresh.",
-            selected: false,
+fn barber_summary(id: String, display_name: String) -> BarberSummary {
+    let initials = display_name
+        .split_whitespace()
+        .filter_map(|part| part.chars().next())
+        .take(2)
+        .collect::<String>();
+    BarberSummary {
+        id,
+        display_name,
+        initials: initials.to_uppercase(),
+        selected: false,
+    }
+}
+
+/// Reads the optional "repeat" fields; `Ok(None)` means a one-off booking.
+fn parse_recurrence(form: &BookingForm) -> Result<Option<(Frequency, RecurrenceEnd)>, &'static str> {
+    let Some(frequency) = form.repeat.as_deref().filter(|value| !value.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let frequency = Frequency::parse(frequency).ok_or("Choose how often the visit should repeat.")?;
+    let until = form.repeat_until.as_deref().map(str::trim).filter(|value| !value.is_empty());
+    let count = form.repeat_count.as_deref().map(str::trim).filter(|value| !value.is_empty());
+    let end = match (until, count) {
+        (Some(_), Some(_)) => return Err("Pick either an end date or a number of visits, not both."),
+        (Some(until), None) => RecurrenceEnd::Until(
+            chrono::NaiveDate::parse_from_str(until, "%Y-%m-%d").map_err(|_| "Enter a valid end date.")?,
+        ),
+        (None, Some(count)) => match count.parse::<u32>() {
+            Ok(count) if (2..=recurrence::MAX_OCCURRENCES).contains(&count) => RecurrenceEnd::Count(count),
+            _ => return Err("Repeating bookings need between 2 and 26 visits."),
         },
-    ]
+        (None, None) => return Err("Repeating bookings need an end date or a number of visits."),
+    };
+    if let (RecurrenceEnd::Until(until), Some(start)) = (end, schedule::parse_wall_clock(&form.scheduled_for))
+        && until <= start.date()
+    {
+        return Err("The repeat end date must be after the first visit.");
+    }
+    Ok(Some((frequency, end)))
+}
+
+async fn fetch_appointment_location(state: &web::Data<AppState>, appointment_id: &str) -> Option<String> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT location_id FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(&state.db)
+        .await
+        .unwrap_or(None)
+        .flatten()
+}
+
+async fn location_options(state: &web::Data<AppState>, client_id: &str, selected: &str) -> Vec<LocationOption> {
+    clients::fetch_locations(&state.db, client_id)
+        .await
+        .into_iter()
+        .map(|location| LocationOption {
+            selected: location.id == selected,
+            id: location.id,
+            label: location.label,
+            address: location.address,
+            latitude: location.latitude.map(|value| value.to_string()).unwrap_or_default(),
+            longitude: location.longitude.map(|value| value.to_string()).unwrap_or_default(),
+        })
+        .collect()
 }
 
-async fn cms_block_html(state: &web::Data<AppState>, key: &str) -> String {
-    let row = sqlx::query_as::<_, CmsBlockRow>(
-        "SELECT key, title, html FROM cms_blocks WHERE key = ?",
+async fn fetch_client_appointment(
+    state: &web::Data<AppState>,
+    client_id: &str,
+    appointment_id: &str,
+) -> Option<AppointmentRow> {
+    sqlx::query_as::<_, AppointmentRow>(
+        r#"SELECT a.id, a.client_name, a.client_phone, a.client_email, a.address, a.service,
+                  a.notes, a.requested_at, a.scheduled_for, a.status, a.barber_id,
+                  a.latitude, a.longitude,
+                  u.display_name as barber_name
+           FROM appointments a
+           LEFT JOIN users u ON a.barber_id = u.id
+           WHERE a.id = ? AND a.client_id = ?"#,
     )
-    .bind(key)
+    .bind(appointment_id)
+    .bind(client_id)
     .fetch_optional(&state.db)
-    .await;
-
-    row.ok()
-        .flatten()
-        .map(|block| block.html)
-        .unwrap_or_default()
+    .await
+    .unwrap_or(None)
 }
 
 #[allow(dead_code)]
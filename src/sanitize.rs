@@ -0,0 +1,240 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+/// Tags the CMS editor and Markdown can produce; anything else is unwrapped, keeping its text.
+const ALLOWED_TAGS: [&str; 22] = [
+    "a", "b", "blockquote", "br", "code", "em", "figcaption", "figure", "h1", "h2", "h3", "h4", "hr", "i", "img",
+    "li", "ol", "p", "pre", "strong", "u", "ul",
+];
+
+/// Tags removed together with everything inside them.
+const DROPPED_TAGS: [&str; 2] = ["script", "style"];
+
+const LINK_ATTRIBUTES: [&str; 2] = ["href", "title"];
+
+const IMAGE_ATTRIBUTES: [&str; 7] = ["alt", "height", "loading", "sizes", "src", "srcset", "width"];
+
+const URL_SCHEMES: [&str; 4] = ["http", "https", "mailto", "tel"];
+
+pub struct Sanitized {
+    pub html: String,
+    /// Human-readable descriptions of what was taken out, e.g. "onclick attribute on <a>".
+    pub removed: Vec<String>,
+}
+
+/// Cleans CMS HTML against the allowlist so stored blocks can be rendered unescaped.
+pub fn sanitize_html(input: &str) -> Sanitized {
+    let html = ammonia::Builder::empty()
+        .tags(HashSet::from(ALLOWED_TAGS))
+        .clean_content_tags(HashSet::from(DROPPED_TAGS))
+        .generic_attributes(HashSet::new())
+        .tag_attributes(HashMap::from([
+            ("a", HashSet::from(LINK_ATTRIBUTES)),
+            ("img", HashSet::from(IMAGE_ATTRIBUTES)),
+        ]))
+        .url_schemes(HashSet::from(URL_SCHEMES))
+        .attribute_filter(|_, attribute, value| {
+            if attribute == "srcset" && !srcset_allowed(value) {
+                return None;
+            }
+            Some(Cow::Borrowed(value))
+        })
+        .clean(input)
+        .to_string();
+    Sanitized {
+        html,
+        removed: removed_markup(input),
+    }
+}
+
+fn attribute_allowed(tag: &str, attribute: &str) -> bool {
+    match tag {
+        "a" => LINK_ATTRIBUTES.contains(&attribute),
+        "img" => IMAGE_ATTRIBUTES.contains(&attribute),
+        _ => false,
+    }
+}
+
+/// Relative URLs pass; absolute ones need an allowed scheme.
+fn url_allowed(url: &str) -> bool {
+    let url = url.trim();
+    match url.find([':', '/', '?', '#']) {
+        Some(index) if url[index..].starts_with(':') => {
+            URL_SCHEMES.contains(&url[..index].to_ascii_lowercase().as_str())
+        }
+        _ => true,
+    }
+}
+
+fn srcset_allowed(value: &str) -> bool {
+    value
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+        .all(url_allowed)
+}
+
+/// Walks the submitted markup and lists what the sanitizer drops, for the editor to show.
+fn removed_markup(input: &str) -> Vec<String> {
+    let mut removed = Vec::new();
+    let mut note = |entry: String| {
+        if !removed.contains(&entry) {
+            removed.push(entry);
+        }
+    };
+    let mut rest = input;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            note("HTML comments".to_string());
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(rest.len());
+        if name_len == 0 {
+            continue;
+        }
+        let tag = rest[..name_len].to_ascii_lowercase();
+        let (attributes, after) = parse_attributes(&rest[name_len..]);
+        rest = after;
+
+        if DROPPED_TAGS.contains(&tag.as_str()) {
+            note(format!("<{tag}> element and its content"));
+            let closing = format!("</{tag}");
+            rest = rest
+                .to_ascii_lowercase()
+                .find(&closing)
+                .map_or("", |end| &rest[end..]);
+            continue;
+        }
+        if !ALLOWED_TAGS.contains(&tag.as_str()) {
+            note(format!("<{tag}> tag (its text was kept)"));
+            continue;
+        }
+        for (attribute, value) in attributes {
+            if !attribute_allowed(&tag, &attribute) {
+                note(format!("{attribute} attribute on <{tag}>"));
+            } else if (attribute == "href" || attribute == "src") && !url_allowed(&value) {
+                note(format!("unsafe {attribute} URL on <{tag}>"));
+            } else if attribute == "srcset" && !srcset_allowed(&value) {
+                note(format!("unsafe srcset on <{tag}>"));
+            }
+        }
+    }
+    removed
+}
+
+/// Splits the inside of a start tag into `(name, value)` pairs and returns the text after its `>`.
+fn parse_attributes(input: &str) -> (Vec<(String, String)>, &str) {
+    let mut attributes = Vec::new();
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return (attributes, rest);
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return (attributes, after);
+        }
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, next) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &after[1..];
+                    body.find(quote).map_or((body, ""), |end| (&body[..end], &body[end + 1..]))
+                }
+                _ => {
+                    let end = after.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(after.len());
+                    after.split_at(end)
+                }
+            };
+            value = raw;
+            rest = next;
+        }
+        if !name.is_empty() {
+            attributes.push((name, value.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_and_style_blocks_are_dropped_with_their_content() {
+        let cleaned = sanitize_html("<p>Hi</p><script>alert(1)</script><STYLE>p { color: red }</STYLE>");
+
+        assert_eq!(cleaned.html, "<p>Hi</p>");
+        assert_eq!(
+            cleaned.removed,
+            ["<script> element and its content", "<style> element and its content"]
+        );
+    }
+
+    #[test]
+    fn event_handlers_and_style_attributes_are_removed() {
+        let cleaned = sanitize_html(
+            r#"<a href="/book" onclick="steal()">Book</a><img src="/a.jpg" OnError="steal()"><p style="color: red">Hi</p>"#,
+        );
+
+        assert!(!cleaned.html.contains("onclick"));
+        assert!(!cleaned.html.to_ascii_lowercase().contains("onerror"));
+        assert!(!cleaned.html.contains("style"));
+        assert!(cleaned.html.contains(r#"href="/book""#));
+        assert_eq!(
+            cleaned.removed,
+            ["onclick attribute on <a>", "onerror attribute on <img>", "style attribute on <p>"]
+        );
+    }
+
+    #[test]
+    fn script_and_data_urls_are_removed() {
+        let cleaned = sanitize_html(
+            r#"<a href="javascript:steal()">x</a><img src="data:image/svg+xml,<svg/>"><img srcset="/a.jpg 1x, javascript:steal() 2x">"#,
+        );
+
+        assert!(!cleaned.html.contains("javascript"));
+        assert!(!cleaned.html.contains("data:"));
+        assert!(!cleaned.html.contains("srcset"));
+        assert_eq!(
+            cleaned.removed,
+            ["unsafe href URL on <a>", "unsafe src URL on <img>", "unsafe srcset on <img>"]
+        );
+    }
+
+    #[test]
+    fn schemes_are_matched_regardless_of_case() {
+        let cleaned = sanitize_html(
+            r#"<a href="JaVaScRiPt:steal()">x</a><img src="DATA:text/html,hi"><img srcset="  Data:image/png;base64,AA 1x">"#,
+        );
+
+        assert!(!cleaned.html.to_ascii_lowercase().contains("javascript"));
+        assert!(!cleaned.html.to_ascii_lowercase().contains("data:"));
+        assert_eq!(
+            cleaned.removed,
+            ["unsafe href URL on <a>", "unsafe src URL on <img>", "unsafe srcset on <img>"]
+        );
+    }
+
+    #[test]
+    fn allowed_markup_passes_untouched() {
+        let input = r#"<p>Book a <a href="https://barber2go.example/book" title="Book">visit</a> or call <a href="tel:+385911234567">us</a>.</p><img src="/media/cut.jpg" alt="Cut">"#;
+        let cleaned = sanitize_html(input);
+
+        assert!(cleaned.removed.is_empty());
+        assert!(cleaned.html.contains(r#"href="https://barber2go.example/book""#));
+        assert!(cleaned.html.contains(r#"href="tel:+385911234567""#));
+        assert!(cleaned.html.contains(r#"src="/media/cut.jpg""#));
+        assert!(sanitize_html("<p>Plain <b>text</b></p>").removed.is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+use actix_web::HttpRequest;
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::SqlitePool;
+
+use crate::{
+    auth::AuthUser,
+    models::ROLE_BARBER,
+    state::TimeConfig,
+};
+
+/// Set by the public scripts from the browser's `Intl` zone, e.g. "Europe/Zagreb".
+pub const TIMEZONE_COOKIE: &str = "b2g_tz";
+
+/// How `scheduled_for` is stored: a UTC instant with a fixed width, so text order is time order.
+const STORED_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+/// What a `datetime-local` input expects as its value.
+const INPUT_FORMAT: &str = "%Y-%m-%dT%H:%M";
+const DISPLAY_FORMAT: &str = "%Y-%m-%d %H:%M %Z";
+
+/// A stored `scheduled_for`. Values saved before times were zoned have no offset and are read as UTC.
+pub fn parse_scheduled_for(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.with_timezone(&Utc))
+        .ok()
+        .or_else(|| parse_wall_clock(value).map(|at| at.and_utc()))
+}
+
+pub fn to_stored(at: DateTime<Utc>) -> String {
+    at.format(STORED_FORMAT).to_string()
+}
+
+/// A `datetime-local` value, with or without seconds, read as wall-clock time in `zone`.
+pub fn parse_local(value: &str, zone: Tz) -> Option<DateTime<Utc>> {
+    parse_wall_clock(value).map(|at| localize(at, zone))
+}
+
+/// A `datetime-local` value as it was typed, without a zone.
+pub fn parse_wall_clock(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, INPUT_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M"))
+        .ok()
+}
+
+/// Wall-clock time in `zone` as an instant. When clocks go back the earlier of the two readings
+/// wins; a time skipped when clocks go forward is moved past the gap, so 02:30 becomes 03:30.
+pub fn localize(at: NaiveDateTime, zone: Tz) -> DateTime<Utc> {
+    match zone.from_local_datetime(&at) {
+        LocalResult::Single(local) | LocalResult::Ambiguous(local, _) => local.with_timezone(&Utc),
+        // Read with the offset from before the jump, the time lands just as far past it.
+        LocalResult::None => {
+            let before = zone.offset_from_utc_datetime(&(at - Duration::days(1))).fix();
+            (at - Duration::seconds(i64::from(before.local_minus_utc()))).and_utc()
+        }
+    }
+}
+
+/// Wall-clock time of `at` in `zone`.
+pub fn wall_clock(at: DateTime<Utc>, zone: Tz) -> NaiveDateTime {
+    at.with_timezone(&zone).naive_local()
+}
+
+/// A stored time for people, e.g. "2026-03-29 10:00 CEST". Unreadable values are shown as they are.
+pub fn display(value: &str, zone: Tz) -> String {
+    parse_scheduled_for(value)
+        .map(|at| at.with_timezone(&zone).format(DISPLAY_FORMAT).to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// A stored time as the value of a `datetime-local` input in `zone`.
+pub fn input_value(value: &str, zone: Tz) -> String {
+    parse_scheduled_for(value)
+        .map(|at| wall_clock(at, zone).format(INPUT_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+/// The calendar day a stored time falls on in `zone`.
+pub fn local_date(value: &str, zone: Tz) -> Option<NaiveDate> {
+    parse_scheduled_for(value).map(|at| wall_clock(at, zone).date())
+}
+
+/// Stored bounds of `date` in `zone`, for `scheduled_for >= ? AND scheduled_for < ?`. Days are
+/// 23 or 25 hours long when the clocks change.
+pub fn day_bounds(date: NaiveDate, zone: Tz) -> (String, String) {
+    let start = localize(date.and_time(NaiveTime::MIN), zone);
+    let end = date
+        .succ_opt()
+        .map(|next| localize(next.and_time(NaiveTime::MIN), zone))
+        .unwrap_or(start + Duration::days(1));
+    (to_stored(start), to_stored(end))
+}
+
+/// Today's date where the business is.
+pub fn today(zone: Tz) -> NaiveDate {
+    wall_clock(Utc::now(), zone).date()
+}
+
+pub fn parse_zone(value: &str) -> Option<Tz> {
+    value.trim().parse().ok()
+}
+
+/// The zone a client sees and books in: their browser's, or the business's until it is known.
+pub fn client_zone(req: &HttpRequest, config: &TimeConfig) -> Tz {
+    req.cookie(TIMEZONE_COOKIE)
+        .and_then(|cookie| parse_zone(cookie.value()))
+        .unwrap_or(config.business_zone)
+}
+
+/// The zone a submitted form's times were typed in. Forms carry the zone their labels showed, so
+/// a cookie set while the form was open doesn't change how it is read.
+pub fn form_zone(req: &HttpRequest, config: &TimeConfig, submitted: Option<&str>) -> Tz {
+    submitted
+        .and_then(parse_zone)
+        .unwrap_or_else(|| client_zone(req, config))
+}
+
+/// A barber's own zone, or the business's when they have not set one.
+pub async fn barber_zone(pool: &SqlitePool, config: &TimeConfig, barber_id: Option<&str>) -> Tz {
+    let Some(barber_id) = barber_id else {
+        return config.business_zone;
+    };
+    sqlx::query_scalar::<_, Option<String>>("SELECT timezone FROM users WHERE id = ?")
+        .bind(barber_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .flatten()
+        .and_then(|zone| parse_zone(&zone))
+        .unwrap_or(config.business_zone)
+}
+
+/// Barbers see their own zone; admins see the business's.
+pub async fn staff_zone(pool: &SqlitePool, config: &TimeConfig, user: &AuthUser) -> Tz {
+    if user.role == ROLE_BARBER {
+        barber_zone(pool, config, Some(&user.id)).await
+    } else {
+        config.business_zone
+    }
+}
+
+/// Where a visit happens: the assigned barber's zone, or the business's.
+pub async fn appointment_zone(pool: &SqlitePool, config: &TimeConfig, appointment_id: &str) -> Tz {
+    let barber_id = sqlx::query_scalar::<_, Option<String>>("SELECT barber_id FROM appointments WHERE id = ?")
+        .bind(appointment_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap_or(None)
+        .flatten();
+    barber_zone(pool, config, barber_id.as_deref()).await
+}
@@ -1,14 +1,99 @@
+use std::{path::PathBuf, sync::Arc};
+
+use chrono_tz::Tz;
 use serde::Serialize;
 use sqlx::SqlitePool;
 use tokio::sync::broadcast;
 
-use crate::models::AppointmentRow;
+use crate::{
+    geocode::Geocoder, messaging::MessageTransport, models::AppointmentRow, payments::PaymentProvider,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: SqlitePool,
     pub events: broadcast::Sender<ServerEvent>,
     pub push: PushConfig,
+    pub areas: ServiceAreaConfig,
+    pub dispatch: DispatchConfig,
+    pub routes: RouteConfig,
+    pub geocoder: Option<Arc<dyn Geocoder>>,
+    pub messages: Arc<dyn MessageTransport>,
+    pub portal: PortalConfig,
+    pub pricing: PricingConfig,
+    pub payments: Option<Arc<dyn PaymentProvider>>,
+    pub deposits: DepositConfig,
+    pub invoices: InvoiceConfig,
+    pub payouts: PayoutConfig,
+    pub policy: PolicyConfig,
+    pub waitlist: WaitlistConfig,
+    pub uploads: UploadConfig,
+    pub time: TimeConfig,
+    pub booking: BookingConfig,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// The state an unconfigured server starts with, on `db`: no push, payments or geocoder,
+    /// dispatch off and messages written to the log.
+    pub fn for_tests(db: SqlitePool) -> Self {
+        Self {
+            db,
+            events: broadcast::channel(16).0,
+            push: PushConfig {
+                public_key: String::new(),
+                private_key: String::new(),
+                subject: String::new(),
+            },
+            areas: ServiceAreaConfig { reject_outside: true },
+            dispatch: DispatchConfig {
+                enabled: false,
+                offer_timeout_secs: 300,
+            },
+            routes: RouteConfig { average_speed_kmh: 30.0 },
+            geocoder: None,
+            messages: Arc::new(crate::messaging::LogTransport),
+            portal: PortalConfig {
+                base_url: None,
+                link_ttl_minutes: 20,
+                session_days: 30,
+            },
+            pricing: PricingConfig {
+                travel_free_km: 5.0,
+                travel_per_km_cents: 100,
+                travel_max_cents: 2500,
+            },
+            payments: None,
+            deposits: DepositConfig { percent: 20 },
+            invoices: InvoiceConfig {
+                prefix: "B2G-".to_string(),
+                business_name: "Barber2Go".to_string(),
+                business_details: None,
+                tax_label: "VAT".to_string(),
+                tax_rate: 0.0,
+            },
+            payouts: PayoutConfig {
+                default_commission_percent: 0.0,
+            },
+            policy: PolicyConfig {
+                cutoff_hours: 24,
+                penalty: Penalty::None,
+                max_no_shows: 2,
+            },
+            waitlist: WaitlistConfig { claim_minutes: 30 },
+            uploads: UploadConfig {
+                dir: std::env::temp_dir().join("barber2go-test-uploads"),
+                max_bytes: 5 * 1024 * 1024,
+            },
+            time: TimeConfig {
+                business_zone: chrono_tz::UTC,
+            },
+            booking: BookingConfig {
+                horizon_days: 90,
+                phone_country_code: None,
+            },
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -24,6 +109,107 @@ impl PushConfig {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct ServiceAreaConfig {
+    /// When false, out-of-area bookings are accepted but flagged for review.
+    pub reject_outside: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct DispatchConfig {
+    pub enabled: bool,
+    pub offer_timeout_secs: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct RouteConfig {
+    pub average_speed_kmh: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct PortalConfig {
+    /// `PUBLIC_BASE_URL`, the only origin outbound links are built from. Links are never built from
+    /// request headers, since whoever sends the request chooses them.
+    pub base_url: Option<String>,
+    pub link_ttl_minutes: i64,
+    pub session_days: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct PricingConfig {
+    pub travel_free_km: f64,
+    pub travel_per_km_cents: i64,
+    pub travel_max_cents: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct DepositConfig {
+    /// Share of the quote charged at booking; the rest is charged when the visit is completed.
+    pub percent: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct InvoiceConfig {
+    pub prefix: String,
+    pub business_name: String,
+    /// Address, tax number or anything else the receipt header must show.
+    pub business_details: Option<String>,
+    pub tax_label: String,
+    /// Prices already include tax; the rate is only used to show the tax share.
+    pub tax_rate: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct PayoutConfig {
+    /// Share of service revenue the business keeps when a barber has no commission of their own.
+    pub default_commission_percent: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Penalty {
+    None,
+    /// A flat fee, taken from the deposit first and charged to the saved card for the rest.
+    Fee(i64),
+    /// Whatever was paid up front is kept.
+    Deposit,
+}
+
+#[derive(Clone, Debug)]
+pub struct PolicyConfig {
+    /// Cancelling closer to the visit than this counts as a late cancellation.
+    pub cutoff_hours: i64,
+    pub penalty: Penalty,
+    /// Clients with this many no-shows must pay the full quote when booking; zero disables it.
+    pub max_no_shows: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct WaitlistConfig {
+    /// How long a waitlisted client has to book a freed slot before it is offered to the next one.
+    pub claim_minutes: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadConfig {
+    /// Served under `/uploads`; keep it on the persistent volume in production.
+    pub dir: PathBuf,
+    pub max_bytes: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct TimeConfig {
+    /// Used for admins, unassigned visits and barbers without a zone of their own.
+    pub business_zone: Tz,
+}
+
+#[derive(Clone, Debug)]
+pub struct BookingConfig {
+    /// How far ahead visits can be booked.
+    pub horizon_days: i64,
+    /// Calling code, without `+`, for phone numbers typed without one.
+    pub phone_country_code: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct ServerEvent {
     pub kind: String,
@@ -0,0 +1,102 @@
+use std::{fmt, path::Path};
+
+use crate::state::UploadConfig;
+
+/// Public URL prefix the upload directory is served under.
+pub const PUBLIC_PREFIX: &str = "/uploads";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageKind {
+    Jpeg,
+    Png,
+    Webp,
+    Gif,
+}
+
+impl ImageKind {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageKind::Jpeg => "jpg",
+            ImageKind::Png => "png",
+            ImageKind::Webp => "webp",
+            ImageKind::Gif => "gif",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageKind::Jpeg => "image/jpeg",
+            ImageKind::Png => "image/png",
+            ImageKind::Webp => "image/webp",
+            ImageKind::Gif => "image/gif",
+        }
+    }
+}
+
+/// Detects the image type from the file's leading bytes; the client's content type and file name are not trusted.
+pub fn sniff_image(bytes: &[u8]) -> Option<ImageKind> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(ImageKind::Jpeg)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(ImageKind::Png)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some(ImageKind::Webp)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some(ImageKind::Gif)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    TooLarge(usize),
+    NotAnImage,
+    Io(std::io::Error),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::TooLarge(max_bytes) => {
+                write!(f, "Images can be at most {} MB.", max_bytes / (1024 * 1024))
+            }
+            UploadError::NotAnImage => write!(f, "Only JPEG, PNG, WebP and GIF images can be uploaded."),
+            UploadError::Io(err) => write!(f, "Saving the upload failed: {err}"),
+            UploadError::Database(err) => write!(f, "Saving the upload failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+/// Enforces the size limit and sniffs the image type.
+pub fn check_image(config: &UploadConfig, bytes: &[u8]) -> Result<ImageKind, UploadError> {
+    if bytes.len() > config.max_bytes {
+        return Err(UploadError::TooLarge(config.max_bytes));
+    }
+    sniff_image(bytes).ok_or(UploadError::NotAnImage)
+}
+
+/// Writes `bytes` to the upload directory as `name` and returns the URL it is served from.
+pub async fn save_file(config: &UploadConfig, name: &str, bytes: &[u8]) -> Result<String, UploadError> {
+    tokio::fs::create_dir_all(&config.dir).await.map_err(UploadError::Io)?;
+    tokio::fs::write(config.dir.join(name), bytes)
+        .await
+        .map_err(UploadError::Io)?;
+    Ok(format!("{PUBLIC_PREFIX}/{name}"))
+}
+
+/// Removes a file previously returned by `save_file`; anything outside the upload directory is ignored.
+pub async fn remove(config: &UploadConfig, url: &str) {
+    let Some(name) = url.strip_prefix(PUBLIC_PREFIX).and_then(|rest| rest.strip_prefix('/')) else {
+        return;
+    };
+    if name.is_empty() || Path::new(name).components().count() != 1 {
+        return;
+    }
+    if let Err(err) = tokio::fs::remove_file(config.dir.join(name)).await {
+        log::warn!("Removing upload {name} failed: {err}");
+    }
+}
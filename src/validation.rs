@@ -0,0 +1,98 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::clients::normalize_phone;
+
+/// E.164 allows at most 15 digits; shorter than 8 is never a full international number.
+const E164_DIGITS: std::ops::RangeInclusive<usize> = 8..=15;
+
+/// A phone number as `+<country code><number>`. Numbers typed without a country code get
+/// `country_code` in place of their leading trunk `0`, so "091 123 4567" becomes "+385911234567".
+pub fn to_e164(raw: &str, country_code: Option<&str>) -> Option<String> {
+    if raw.chars().any(|c| c.is_alphabetic()) {
+        return None;
+    }
+    let normalized = normalize_phone(raw)?;
+    let international = match normalized.strip_prefix('+') {
+        Some(digits) => digits.to_string(),
+        None => format!("{}{}", country_code?, normalized.trim_start_matches('0')),
+    };
+    (E164_DIGITS.contains(&international.len()) && !international.starts_with('0'))
+        .then(|| format!("+{international}"))
+}
+
+/// `local@domain.tld` with no spaces, one `@`, and a domain made of letters, digits and hyphens.
+pub fn is_valid_email(raw: &str) -> bool {
+    let email = raw.trim();
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    let local_ok = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c));
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_ok = email.len() <= 254
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels.last().is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+    local_ok && domain_ok
+}
+
+pub fn is_valid_point(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+}
+
+/// Whether `at` is still ahead and no more than `horizon_days` away.
+pub fn within_horizon(at: DateTime<Utc>, now: DateTime<Utc>, horizon_days: i64) -> Result<(), HorizonError> {
+    if at <= now {
+        Err(HorizonError::Past)
+    } else if at > now + Duration::days(horizon_days) {
+        Err(HorizonError::TooFar)
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HorizonError {
+    Past,
+    TooFar,
+}
+
+/// Messages tied to the form field they are about, shown next to that field.
+#[derive(Clone, Debug, Default)]
+pub struct FieldErrors(Vec<(&'static str, String)>);
+
+impl FieldErrors {
+    /// Keeps the first message per field; later checks on the same field usually follow from it.
+    pub fn add(&mut self, field: &'static str, message: impl Into<String>) {
+        if !self.has(field) {
+            self.0.push((field, message.into()));
+        }
+    }
+
+    pub fn has(&self, field: &str) -> bool {
+        self.0.iter().any(|(name, _)| *name == field)
+    }
+
+    /// The message for `field`, or an empty string.
+    pub fn get(&self, field: &str) -> &str {
+        self.0
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, message)| message.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
@@ -0,0 +1,315 @@
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use crate::{
+    auth::new_id,
+    db::log_activity,
+    i18n::Locale,
+    messaging::{Channel, OutboundMessage},
+    models::{
+        WaitlistEntryRow, WaitlistOfferRow, OFFER_ACCEPTED, OFFER_EXPIRED, OFFER_OPEN, OFFER_WITHDRAWN,
+        STATUS_CANCELLED, STATUS_DECLINED, WAITLIST_BOOKED, WAITLIST_REMOVED, WAITLIST_WAITING,
+    },
+    push,
+    schedule::{self, parse_scheduled_for},
+    state::AppState,
+};
+
+const SWEEP_INTERVAL_SECS: u64 = 30;
+
+pub struct NewEntry<'a> {
+    pub client_id: Option<&'a str>,
+    pub client_name: &'a str,
+    pub client_phone: &'a str,
+    pub client_email: Option<&'a str>,
+    pub service: &'a str,
+    pub wanted_date: NaiveDate,
+    pub barber_id: Option<&'a str>,
+    pub push_subscription: Option<&'a str>,
+    pub locale: Locale,
+}
+
+pub async fn join(pool: &SqlitePool, entry: NewEntry<'_>) -> Result<String, sqlx::Error> {
+    let id = new_id();
+    sqlx::query(
+        r#"INSERT INTO waitlist_entries
+           (id, client_id, client_name, client_phone, client_email, service, wanted_date, barber_id,
+            push_subscription, status, created_at, locale)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(&id)
+    .bind(entry.client_id)
+    .bind(entry.client_name.trim())
+    .bind(entry.client_phone.trim())
+    .bind(entry.client_email.map(str::trim).filter(|value| !value.is_empty()))
+    .bind(entry.service)
+    .bind(entry.wanted_date.format("%Y-%m-%d").to_string())
+    .bind(entry.barber_id)
+    .bind(entry.push_subscription.filter(|value| !value.trim().is_empty()))
+    .bind(WAITLIST_WAITING)
+    .bind(Utc::now().to_rfc3339())
+    .bind(entry.locale.code())
+    .execute(pool)
+    .await?;
+    Ok(id)
+}
+
+/// Entries still waiting for a date from today on, oldest first within each date.
+pub async fn list_entries(pool: &SqlitePool, today: NaiveDate) -> Vec<WaitlistEntryRow> {
+    sqlx::query_as::<_, WaitlistEntryRow>(
+        r#"SELECT e.id, e.client_name, e.client_phone, e.client_email, e.service, e.wanted_date,
+                  u.display_name as barber_name,
+                  (SELECT o.expires_at FROM waitlist_offers o WHERE o.entry_id = e.id AND o.status = ?) as offer_expires_at
+           FROM waitlist_entries e
+           LEFT JOIN users u ON u.id = e.barber_id
+           WHERE e.status = ? AND e.wanted_date >= ?
+           ORDER BY e.wanted_date, e.created_at"#,
+    )
+    .bind(OFFER_OPEN)
+    .bind(WAITLIST_WAITING)
+    .bind(today.format("%Y-%m-%d").to_string())
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// Offers a cancelled or declined slot to the first matching client on the waitlist.
+pub async fn slot_freed(state: &AppState, appointment_id: &str) {
+    let open = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM waitlist_offers WHERE appointment_id = ? AND status = ?")
+        .bind(appointment_id)
+        .bind(OFFER_OPEN)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(0);
+    if open == 0 {
+        offer_next(state, appointment_id).await;
+    }
+}
+
+/// Takes the entry off the waitlist and moves any slot it was holding on to the next client.
+pub async fn remove(state: &AppState, entry_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE waitlist_entries SET status = ? WHERE id = ?")
+        .bind(WAITLIST_REMOVED)
+        .bind(entry_id)
+        .execute(&state.db)
+        .await?;
+    let held = sqlx::query_scalar::<_, String>(
+        "UPDATE waitlist_offers SET status = ?, responded_at = ? WHERE entry_id = ? AND status = ? RETURNING appointment_id",
+    )
+    .bind(OFFER_WITHDRAWN)
+    .bind(Utc::now().to_rfc3339())
+    .bind(entry_id)
+    .bind(OFFER_OPEN)
+    .fetch_all(&state.db)
+    .await?;
+    for appointment_id in held {
+        offer_next(state, &appointment_id).await;
+    }
+    Ok(())
+}
+
+/// The offer behind a claim link, while its window is still open.
+pub async fn open_offer(pool: &SqlitePool, token: &str) -> Option<WaitlistOfferRow> {
+    let offer = sqlx::query_as::<_, WaitlistOfferRow>(
+        r#"SELECT o.token, e.client_name, e.client_phone, e.client_email, a.service, a.scheduled_for,
+                  COALESCE(e.barber_id, a.barber_id) as barber_id, o.expires_at
+           FROM waitlist_offers o
+           JOIN waitlist_entries e ON e.id = o.entry_id
+           JOIN appointments a ON a.id = o.appointment_id
+           WHERE o.token = ? AND o.status = ? AND a.status IN (?, ?)"#,
+    )
+    .bind(token)
+    .bind(OFFER_OPEN)
+    .bind(STATUS_CANCELLED)
+    .bind(STATUS_DECLINED)
+    .fetch_optional(pool)
+    .await
+    .unwrap_or(None)?;
+    DateTime::parse_from_rfc3339(&offer.expires_at)
+        .is_ok_and(|expires_at| expires_at > Utc::now())
+        .then_some(offer)
+}
+
+/// Marks the offer as taken by the new booking; the entry leaves the waitlist.
+pub async fn claim(pool: &SqlitePool, token: &str, appointment_id: &str) -> Result<(), sqlx::Error> {
+    let entry_id = sqlx::query_scalar::<_, String>(
+        "UPDATE waitlist_offers SET status = ?, responded_at = ? WHERE token = ? AND status = ? RETURNING entry_id",
+    )
+    .bind(OFFER_ACCEPTED)
+    .bind(Utc::now().to_rfc3339())
+    .bind(token)
+    .bind(OFFER_OPEN)
+    .fetch_optional(pool)
+    .await?;
+    if let Some(entry_id) = entry_id {
+        sqlx::query("UPDATE waitlist_entries SET status = ?, appointment_id = ? WHERE id = ?")
+            .bind(WAITLIST_BOOKED)
+            .bind(appointment_id)
+            .bind(&entry_id)
+            .execute(pool)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Moves offers whose claim window has passed on to the next client, every `SWEEP_INTERVAL_SECS`.
+pub fn spawn_sweeper(state: &AppState) {
+    let state = state.clone();
+    actix_web::rt::spawn(async move {
+        loop {
+            sweep(&state).await;
+            tokio::time::sleep(Duration::from_secs(SWEEP_INTERVAL_SECS)).await;
+        }
+    });
+}
+
+async fn sweep(state: &AppState) {
+    let now = Utc::now();
+    let open = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT id, appointment_id, expires_at FROM waitlist_offers WHERE status = ?",
+    )
+    .bind(OFFER_OPEN)
+    .fetch_all(&state.db)
+    .await
+    .unwrap_or_default();
+
+    for (offer_id, appointment_id, expires_at) in open {
+        let expired = DateTime::parse_from_rfc3339(&expires_at).is_ok_and(|expires_at| expires_at <= now);
+        if !expired {
+            continue;
+        }
+        let closed = sqlx::query("UPDATE waitlist_offers SET status = ?, responded_at = ? WHERE id = ? AND status = ?")
+            .bind(OFFER_EXPIRED)
+            .bind(now.to_rfc3339())
+            .bind(&offer_id)
+            .bind(OFFER_OPEN)
+            .execute(&state.db)
+            .await
+            .map(|result| result.rows_affected() > 0)
+            .unwrap_or(false);
+        if closed {
+            offer_next(state, &appointment_id).await;
+        }
+    }
+}
+
+async fn offer_next(state: &AppState, appointment_id: &str) {
+    let slot = sqlx::query_as::<_, (String, String, Option<String>, String)>(
+        "SELECT scheduled_for, service, barber_id, status FROM appointments WHERE id = ?",
+    )
+    .bind(appointment_id)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    let Some((scheduled_for, service, barber_id, status)) = slot else {
+        return;
+    };
+    if status != STATUS_CANCELLED && status != STATUS_DECLINED {
+        return;
+    }
+    let Some(start) = parse_scheduled_for(&scheduled_for).filter(|start| *start > Utc::now()) else {
+        return;
+    };
+    // Wanted dates are days where the visit happens, so the slot is matched in the barber's zone.
+    let start = start.with_timezone(&schedule::barber_zone(&state.db, &state.time, barber_id.as_deref()).await);
+
+    // Clients already holding another slot are skipped so nobody juggles two offers at once.
+    let entry = sqlx::query_as::<_, (String, String, String, Option<String>, Option<String>, String)>(
+        r#"SELECT e.id, e.client_name, e.client_phone, e.client_email, e.push_subscription, e.locale
+           FROM waitlist_entries e
+           WHERE e.status = ? AND e.wanted_date = ? AND e.service = ?
+             AND (e.barber_id IS NULL OR e.barber_id = ?)
+             AND NOT EXISTS (SELECT 1 FROM waitlist_offers o
+                             WHERE o.entry_id = e.id AND (o.appointment_id = ? OR o.status = ?))
+           ORDER BY e.created_at
+           LIMIT 1"#,
+    )
+    .bind(WAITLIST_WAITING)
+    .bind(start.date_naive().format("%Y-%m-%d").to_string())
+    .bind(&service)
+    .bind(&barber_id)
+    .bind(appointment_id)
+    .bind(OFFER_OPEN)
+    .fetch_optional(&state.db)
+    .await
+    .unwrap_or(None);
+    let Some((entry_id, client_name, client_phone, client_email, push_subscription, locale)) = entry else {
+        return;
+    };
+    let locale = Locale::parse(&locale).unwrap_or_default();
+
+    let now = Utc::now();
+    let expires_at = now + chrono::Duration::minutes(state.waitlist.claim_minutes.max(1));
+    let token = Uuid::new_v4().simple().to_string();
+    let inserted = sqlx::query(
+        r#"INSERT INTO waitlist_offers (id, entry_id, appointment_id, token, status, offered_at, expires_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+    )
+    .bind(new_id())
+    .bind(&entry_id)
+    .bind(appointment_id)
+    .bind(&token)
+    .bind(OFFER_OPEN)
+    .bind(now.to_rfc3339())
+    .bind(expires_at.to_rfc3339())
+    .execute(&state.db)
+    .await;
+    if let Err(err) = inserted {
+        log::warn!("Creating waitlist offer for {appointment_id} failed: {err}");
+        return;
+    }
+
+    log_activity(
+        &state.db,
+        "waitlist_offered",
+        &format!(
+            "Offered the freed {service} slot at {} to {client_name} from the waitlist.",
+            start.format("%Y-%m-%d %H:%M %Z")
+        ),
+        None,
+        Some(appointment_id),
+    )
+    .await;
+
+    let time = start.format("%Y-%m-%d %H:%M %Z").to_string();
+    let minutes = state.waitlist.claim_minutes.max(1);
+    let link = format!("/book?waitlist={token}");
+    // The notification itself opens the link, so its text doesn't carry one.
+    if let Some(subscription) = push_subscription.as_deref() {
+        let body = locale.format(
+            "A {service} slot opened up at {time}. Tap to book it within {minutes} minutes.",
+            &[("service", &service), ("time", &time), ("minutes", &minutes)],
+        );
+        push::notify_subscription(state, subscription, locale.t("A slot opened up"), &body, &link).await;
+    }
+    let Some(base_url) = state.portal.base_url.as_deref() else {
+        log::error!("PUBLIC_BASE_URL is not set, so the waitlist offer to {client_name} was only sent as a push notification");
+        return;
+    };
+    let body = locale.format(
+        "Hi {name}, a {service} slot opened up at {time}. Book it within {minutes} minutes before it goes to the next person: {link}",
+        &[
+            ("name", &client_name),
+            ("service", &service),
+            ("time", &time),
+            ("minutes", &minutes),
+            ("link", &format!("{base_url}{link}")),
+        ],
+    );
+    let (channel, to) = match client_email.filter(|email| !email.trim().is_empty()) {
+        Some(email) => (Channel::Email, email),
+        None => (Channel::Sms, client_phone),
+    };
+    let message = OutboundMessage {
+        channel,
+        to,
+        subject: locale.t("A Barber2Go slot opened up").to_string(),
+        body,
+    };
+    if let Err(err) = state.messages.send(&message).await {
+        log::warn!("Sending waitlist offer to {client_name} failed: {err}");
+    }
+}
@@ -52,10 +52,11 @@ document.addEventListener("DOMContentLoaded", () => {
       mapSelector: "#barber-map",
       listSelector: "[data-barber-appointments]",
     });
+    initRouteMap();
   }
 });
 
-const STATUS_CLASSES = ["pending", "accepted", "declined", "completed"];
+const STATUS_CLASSES = ["pending", "accepted", "declined", "completed", "cancelled", "no_show"];
 const ZAGREB_CENTER = [45.815, 15.9819];
 const mapRegistry = {
   admin: null,
@@ -281,11 +282,11 @@ function buildBarberCard(payload) {
 
   const actions = document.createElement("div");
   actions.className = "card-actions";
-  actions.append(
-    buildStatusForm(payload.appointment_id, "accepted", "Accept", "primary"),
-    buildStatusForm(payload.appointment_id, "completed", "Complete", "light"),
-    buildStatusForm(payload.appointment_id, "declined", "Decline", "ghost")
-  );
+  actions.append(buildStatusForm(payload.appointment_id, "accepted", "Accept", "primary"));
+  if (statusValue === "accepted") {
+    actions.append(buildStatusForm(payload.appointment_id, "completed", "Complete", "light"));
+  }
+  actions.append(buildStatusForm(payload.appointment_id, "declined", "Decline", "ghost"));
 
   card.append(header, body, actions);
   return card;
@@ -461,6 +462,39 @@ function initAppointmentMap({ mapSelector, listSelector }) {
   return state;
 }
 
+function initRouteMap() {
+  const mapEl = document.querySelector("#route-map");
+  const listEl = document.querySelector("[data-route-stops]");
+  if (!mapEl || !listEl) return;
+
+  const map = L.map(mapEl).setView(ZAGREB_CENTER, 12);
+  L.tileLayer("https://{s}.tile.openstreetmap.org/{z}/{x}/{y}.png", {
+    maxZoom: 19,
+    attribution: "© OpenStreetMap",
+  }).addTo(map);
+
+  const points = [];
+  listEl.querySelectorAll("[data-route-stop]").forEach((el, index) => {
+    const lat = parseFloat(el.dataset.lat);
+    const lon = parseFloat(el.dataset.lon);
+    if (!Number.isFinite(lat) || !Number.isFinite(lon)) return;
+    points.push([lat, lon]);
+    const marker = L.marker([lat, lon]).addTo(map);
+    marker.bindTooltip(String(index + 1), { permanent: true, direction: "top" });
+    const popup = buildPopupContent(el);
+    if (popup) {
+      marker.bindPopup(popup);
+    }
+  });
+
+  if (points.length > 1) {
+    L.polyline(points, { color: "#d77a2b", weight: 4 }).addTo(map);
+  }
+  if (points.length > 0) {
+    map.fitBounds(L.latLngBounds(points), { padding: [40, 40] });
+  }
+}
+
 function syncMarkers(state) {
   if (!state || !state.listEl) return;
   const items = state.listEl.querySelectorAll("[data-appointment-id]");
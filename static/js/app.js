@@ -25,6 +25,12 @@ document.addEventListener("DOMContentLoaded", () => {
     navigator.serviceWorker.register("/sw.js").catch(() => undefined);
   }
 
+  // Pages show times in this zone from the next request on; forms keep the zone they were drawn in.
+  const timeZone = Intl.DateTimeFormat().resolvedOptions().timeZone;
+  if (timeZone && !document.cookie.split("; ").includes(`b2g_tz=${timeZone}`)) {
+    document.cookie = `b2g_tz=${timeZone}; path=/; max-age=31536000; SameSite=Lax`;
+  }
+
   const bookingForm = document.querySelector("[data-booking-form]");
   const vapidKey = document.querySelector("meta[name=\"vapid-public-key\"]")?.content || "";
   if (bookingForm && vapidKey) {
@@ -73,14 +79,14 @@ document.addEventListener("DOMContentLoaded", () => {
   if (notificationToggle && !vapidKey) {
     const label = notificationToggle.querySelector("[data-notification-label]");
     const indicator = notificationToggle.querySelector("[data-notification-indicator]");
-    if (label) label.textContent = "Notifications unavailable";
+    if (label) label.textContent = t("Notifications unavailable");
     if (indicator) indicator.classList.add("blocked");
     if (helper) {
-      helper.textContent = "Set VAPID keys and use HTTPS or localhost to enable notifications.";
+      helper.textContent = t("Set VAPID keys and use HTTPS or localhost to enable notifications.");
     }
     notificationToggle.addEventListener("click", () => {
       if (helper) {
-        helper.textContent = "Set VAPID keys and use HTTPS or localhost to enable notifications.";
+        helper.textContent = t("Set VAPID keys and use HTTPS or localhost to enable notifications.");
       }
     });
   }
@@ -93,10 +99,10 @@ document.addEventListener("DOMContentLoaded", () => {
 
     const setState = (state, text, helpText) => {
       if (label && text) {
-        label.textContent = text;
+        label.textContent = t(text);
       }
       if (helper && helpText !== undefined) {
-        helper.textContent = helpText;
+        helper.textContent = t(helpText);
       }
       if (!indicator) return;
       indicator.classList.remove("on", "blocked");
@@ -192,3 +198,16 @@ function urlBase64ToUint8Array(base64String) {
   const rawData = window.atob(base64);
   return Uint8Array.from([...rawData].map((char) => char.charCodeAt(0)));
 }
+
+// Translations of the script's messages for the page's language; see `i18n::CLIENT_MESSAGES`.
+const I18N_MESSAGES = (() => {
+  try {
+    return JSON.parse(document.getElementById("i18n-messages")?.textContent || "{}");
+  } catch {
+    return {};
+  }
+})();
+
+function t(text) {
+  return (text && I18N_MESSAGES[text]) || text;
+}
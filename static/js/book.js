@@ -3,13 +3,24 @@ const suggestions = document.getElementById("address-suggestions");
 const latField = document.getElementById("latitude");
 const lonField = document.getElementById("longitude");
 const mapContainer = document.getElementById("map");
+const barberSelect = document.getElementById("preferred_barber_id");
+const areaNotice = document.getElementById("area-notice");
+const savedLocationSelect = document.querySelector("[data-saved-location]");
+const newLocationFields = document.querySelector("[data-new-location]");
+const repeatSelect = document.getElementById("repeat");
+const repeatEnd = document.querySelector("[data-repeat-end]");
+const party = document.querySelector("[data-party]");
 let allowAutoFill = true;
 let reverseTimer;
+let coverageTimer;
 let activeController;
 const resultCache = new Map();
 
 if (mapContainer && window.L) {
-  const defaultCenter = [45.815, 15.9819];
+  const initialLat = parseFloat(latField?.value || "");
+  const initialLon = parseFloat(lonField?.value || "");
+  const hasInitial = Number.isFinite(initialLat) && Number.isFinite(initialLon);
+  const defaultCenter = hasInitial ? [initialLat, initialLon] : [45.815, 15.9819];
   const map = L.map("map").setView(defaultCenter, 13);
   L.tileLayer("https://{s}.tile.openstreetmap.org/{z}/{x}/{y}.png", {
     maxZoom: 19,
@@ -22,6 +33,7 @@ if (mapContainer && window.L) {
     if (!latField || !lonField) return;
     latField.value = lat.toFixed(6);
     lonField.value = lon.toFixed(6);
+    refreshCoverage(lat, lon);
   };
 
   const reverseLookup = (lat, lon) => {
@@ -55,7 +67,9 @@ if (mapContainer && window.L) {
   };
 
   updateLatLon(defaultCenter[0], defaultCenter[1]);
-  reverseLookup(defaultCenter[0], defaultCenter[1]);
+  if (!hasInitial) {
+    reverseLookup(defaultCenter[0], defaultCenter[1]);
+  }
 
   marker.on("dragend", () => {
     const pos = marker.getLatLng();
@@ -64,7 +78,7 @@ if (mapContainer && window.L) {
     reverseLookup(pos.lat, pos.lng);
   });
 
-  if ("geolocation" in navigator) {
+  if (!hasInitial && "geolocation" in navigator) {
     navigator.geolocation.getCurrentPosition(
       (position) => {
         const lat = position.coords.latitude;
@@ -85,8 +99,35 @@ if (mapContainer && window.L) {
     updateLatLon(event.latlng.lat, event.latlng.lng);
     allowAutoFill = true;
     reverseLookup(event.latlng.lat, event.latlng.lng);
+    clearSavedLocation();
   });
 
+  const applySavedLocation = () => {
+    const option = savedLocationSelect?.selectedOptions[0];
+    const picked = Boolean(option?.value);
+    if (newLocationFields) {
+      newLocationFields.hidden = picked;
+    }
+    if (!picked) return;
+    if (addressInput) {
+      addressInput.value = option.dataset.address || "";
+    }
+    const lat = parseFloat(option.dataset.lat || "");
+    const lon = parseFloat(option.dataset.lon || "");
+    if (Number.isFinite(lat) && Number.isFinite(lon)) {
+      marker.setLatLng([lat, lon]);
+      map.setView([lat, lon], 15);
+      updateLatLon(lat, lon);
+    }
+    allowAutoFill = false;
+  };
+
+  if (savedLocationSelect) {
+    savedLocationSelect.addEventListener("change", applySavedLocation);
+    applySavedLocation();
+  }
+  addressInput?.addEventListener("input", clearSavedLocation);
+
   let debounceTimer;
   if (addressInput && suggestions) {
     addressInput.addEventListener("input", () => {
@@ -185,6 +226,91 @@ if (mapContainer && window.L) {
   }
 }
 
+if (repeatSelect && repeatEnd) {
+  const toggleRepeatEnd = () => {
+    repeatEnd.hidden = !repeatSelect.value;
+  };
+  repeatSelect.addEventListener("change", toggleRepeatEnd);
+  toggleRepeatEnd();
+}
+
+if (party) {
+  const guestList = party.querySelector("[data-guest-list]");
+  const guestTemplate = party.querySelector("[data-guest-template]");
+  const addGuest = party.querySelector("[data-add-guest]");
+  const maxGuests = parseInt(party.dataset.maxGuests || "0", 10);
+
+  // Field names are numbered so the server can pair each name with its service.
+  const renumberGuests = () => {
+    const rows = guestList.querySelectorAll("[data-guest-row]");
+    rows.forEach((row, index) => {
+      row.querySelector("input").name = `guest_name_${index + 1}`;
+      row.querySelector("select").name = `guest_service_${index + 1}`;
+    });
+    addGuest.hidden = rows.length >= maxGuests;
+  };
+
+  addGuest.addEventListener("click", () => {
+    guestList.appendChild(guestTemplate.content.cloneNode(true));
+    renumberGuests();
+    guestList.querySelector("[data-guest-row]:last-child input")?.focus();
+  });
+  guestList.addEventListener("click", (event) => {
+    const remove = event.target.closest("[data-remove-guest]");
+    if (!remove) return;
+    remove.closest("[data-guest-row]").remove();
+    renumberGuests();
+  });
+  renumberGuests();
+}
+
+function clearSavedLocation() {
+  if (!savedLocationSelect || !savedLocationSelect.value) return;
+  savedLocationSelect.value = "";
+  if (newLocationFields) {
+    newLocationFields.hidden = false;
+  }
+}
+
+function refreshCoverage(lat, lon) {
+  clearTimeout(coverageTimer);
+  coverageTimer = setTimeout(async () => {
+    try {
+      const url = new URL("/book/coverage", window.location.origin);
+      url.searchParams.set("lat", lat.toString());
+      url.searchParams.set("lon", lon.toString());
+      const response = await fetch(url.toString());
+      if (!response.ok) return;
+      const result = await response.json();
+      if (areaNotice) {
+        areaNotice.hidden = result.served !== false;
+      }
+      renderBarberOptions(result.barbers || []);
+    } catch {
+      return;
+    }
+  }, 300);
+}
+
+function renderBarberOptions(barbers) {
+  if (!barberSelect) return;
+  const selected = barberSelect.value;
+  barberSelect.innerHTML = "";
+  const empty = document.createElement("option");
+  empty.value = "";
+  empty.textContent = t("No preference");
+  barberSelect.appendChild(empty);
+  barbers.forEach((barber) => {
+    const option = document.createElement("option");
+    option.value = barber.id;
+    option.textContent = barber.display_name;
+    if (barber.id === selected) {
+      option.selected = true;
+    }
+    barberSelect.appendChild(option);
+  });
+}
+
 function formatAddress(place) {
   const address = place?.address || {};
   const road = address.road || address.pedestrian || address.footway || address.path;
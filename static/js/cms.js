@@ -2,6 +2,10 @@ class EditableBlock extends HTMLElement {
   connectedCallback() {
     const key = this.dataset.key;
     const title = this.dataset.title || "Content block";
+    let format = this.dataset.format === "markdown" ? "markdown" : "html";
+    const sourceField = this.querySelector("textarea[data-source]");
+    const originalSource = sourceField ? sourceField.value : "";
+    sourceField?.remove();
     const original = this.innerHTML.trim();
 
     this.innerHTML = `
@@ -10,7 +14,14 @@ class EditableBlock extends HTMLElement {
           <div>
             <h3>${title}</h3>
           </div>
-          <button class="btn light" type="button">Save</button>
+          <div class="cms-block-actions">
+            <select class="cms-format" aria-label="Editing format">
+              <option value="html">Rich text</option>
+              <option value="markdown">Markdown</option>
+            </select>
+            <button class="btn ghost" type="button" data-save="draft">Save draft</button>
+            <button class="btn light" type="button" data-save="publish">Publish</button>
+          </div>
         </div>
         <div class="cms-toolbar" role="toolbar" aria-label="Content tools">
           <button type="button" data-command="bold" title="Bold"><strong>B</strong></button>
@@ -26,26 +37,53 @@ class EditableBlock extends HTMLElement {
           <button type="button" data-command="blockquote" title="Quote">Quote</button>
           <div class="divider"></div>
           <button type="button" data-command="createLink" title="Link">Link</button>
+          <button type="button" data-command="insertImage" title="Image">Image</button>
           <button type="button" data-command="removeFormat" title="Clear formatting">Clear</button>
         </div>
         <div class="cms-block-body" contenteditable="true" data-placeholder="Write content..."></div>
+        <div class="cms-markdown" hidden>
+          <textarea class="cms-markdown-source" rows="12" spellcheck="true" aria-label="Markdown source"></textarea>
+          <div class="cms-markdown-preview cms-content" aria-live="polite"></div>
+        </div>
         <p class="cms-status muted"></p>
       </div>
     `;
 
     const body = this.querySelector(".cms-block-body");
     const status = this.querySelector(".cms-status");
-    const button = this.querySelector(".cms-block-header .btn");
+    const button = this.querySelector('[data-save="publish"]');
+    const draftButton = this.querySelector('[data-save="draft"]');
     const toolbar = this.querySelector(".cms-toolbar");
+    const meta = this.closest(".cms-entry")?.querySelector(".cms-meta");
+    const formatSelect = this.querySelector(".cms-format");
+    const markdownPane = this.querySelector(".cms-markdown");
+    const markdownSource = this.querySelector(".cms-markdown-source");
+    const preview = this.querySelector(".cms-markdown-preview");
 
     body.innerHTML = original;
+    markdownSource.value = originalSource;
+    if (format === "markdown") {
+      preview.innerHTML = original;
+    }
+
+    const snapshot = () =>
+      format === "markdown" ? `markdown:${markdownSource.value}` : `html:${body.innerHTML.trim()}`;
+
+    const showFormat = () => {
+      formatSelect.value = format;
+      const markdown = format === "markdown";
+      toolbar.hidden = markdown;
+      body.hidden = markdown;
+      markdownPane.hidden = !markdown;
+    };
 
     let dirty = false;
-    let lastContent = body.innerHTML.trim();
+    let lastContent = snapshot();
 
     const setButtonState = (state) => {
       if (!button) return;
       button.classList.remove("cms-save-dirty", "cms-save-saved");
+      draftButton.classList.toggle("is-hidden", state !== "dirty");
       if (state === "hidden") {
         button.classList.add("is-hidden");
         return;
@@ -64,8 +102,10 @@ class EditableBlock extends HTMLElement {
     };
 
     const markDirty = () => {
-      const current = body.innerHTML.trim();
-      if (current === lastContent) {
+      if (snapshot() === lastContent) {
+        dirty = false;
+        status.textContent = "";
+        setButtonState("hidden");
         return;
       }
       dirty = true;
@@ -74,11 +114,54 @@ class EditableBlock extends HTMLElement {
     };
 
     setButtonState("hidden");
+    showFormat();
 
     body.addEventListener("input", () => {
       markDirty();
     });
 
+    let previewTimer = null;
+    let previewRequest = 0;
+    const renderPreview = async () => {
+      const request = ++previewRequest;
+      try {
+        const response = await fetch("/admin/cms/preview", {
+          method: "POST",
+          headers: { "Content-Type": "application/json" },
+          body: JSON.stringify({ markdown: markdownSource.value }),
+        });
+        if (!response.ok) throw new Error("Preview failed");
+        const result = await response.json();
+        if (request === previewRequest) {
+          preview.innerHTML = result.html;
+        }
+      } catch {
+        if (request === previewRequest) {
+          status.textContent = "Preview unavailable.";
+        }
+      }
+    };
+
+    markdownSource.addEventListener("input", () => {
+      markDirty();
+      clearTimeout(previewTimer);
+      previewTimer = setTimeout(renderPreview, 300);
+    });
+
+    formatSelect.addEventListener("change", () => {
+      format = formatSelect.value;
+      if (format === "markdown") {
+        if (!markdownSource.value.trim()) {
+          markdownSource.value = body.innerHTML.trim();
+        }
+        renderPreview();
+      } else {
+        body.innerHTML = preview.innerHTML;
+      }
+      showFormat();
+      markDirty();
+    });
+
     body.addEventListener("focus", () => {
       document.execCommand("defaultParagraphSeparator", false, "p");
     });
@@ -101,6 +184,14 @@ class EditableBlock extends HTMLElement {
         }
         return;
       }
+      if (command === "insertImage") {
+        const range = saveSelection(body);
+        openImagePicker((image) => {
+          insertImage(body, range, image);
+          markDirty();
+        });
+        return;
+      }
       if (command === "formatBlock") {
         const value = target.dataset.value || "p";
         document.execCommand("formatBlock", false, value);
@@ -129,11 +220,27 @@ class EditableBlock extends HTMLElement {
       markDirty();
     });
 
-    button.addEventListener("click", async () => {
-      status.textContent = "Saving...";
+    const showDraftState = (draft) => {
+      if (!meta) return;
+      const state = meta.querySelector("[data-draft-state]");
+      if (state) {
+        state.textContent = draft
+          ? "Editing an unpublished draft saved just now."
+          : "Editing the live version.";
+      }
+      meta.querySelectorAll("[data-draft-only]").forEach((element) => {
+        element.hidden = !draft;
+      });
+    };
+
+    const save = async (draft) => {
+      status.textContent = draft ? "Saving draft..." : "Publishing...";
       const payload = {
         key,
         html: body.innerHTML,
+        format,
+        markdown: markdownSource.value,
+        draft,
       };
 
       try {
@@ -147,20 +254,37 @@ class EditableBlock extends HTMLElement {
           throw new Error("Save failed");
         }
 
+        const result = await response.json();
+        const removed = result.removed || [];
+        if (result.format === "markdown") {
+          preview.innerHTML = result.html;
+        } else {
+          body.innerHTML = result.html;
+        }
         dirty = false;
-        lastContent = body.innerHTML.trim();
-        status.textContent = "Saved";
-        setTimeout(() => {
-          if (!dirty) {
-            status.textContent = "";
-          }
-        }, 3000);
+        lastContent = snapshot();
+        showDraftState(draft);
+        const saved = draft ? "Draft saved. The live site is unchanged." : "Published";
+        status.classList.toggle("warning", removed.length > 0);
+        if (removed.length > 0) {
+          status.textContent = `${saved} Removed unsupported markup: ${removed.join(", ")}.`;
+        } else {
+          status.textContent = saved;
+          setTimeout(() => {
+            if (!dirty) {
+              status.textContent = "";
+            }
+          }, 3000);
+        }
         setButtonState("saved");
       } catch (err) {
         status.textContent = "Save failed. Try again.";
         setButtonState("dirty");
       }
-    });
+    };
+
+    button.addEventListener("click", () => save(false));
+    draftButton.addEventListener("click", () => save(true));
 
     window.addEventListener("beforeunload", (event) => {
       if (dirty) {
@@ -249,3 +373,131 @@ function surroundSelectionWithLink(url) {
   newRange.selectNodeContents(anchor);
   selection.addRange(newRange);
 }
+
+function saveSelection(container) {
+  const selection = window.getSelection();
+  if (!selection || selection.rangeCount === 0) return null;
+  const range = selection.getRangeAt(0);
+  return container.contains(range.commonAncestorContainer) ? range.cloneRange() : null;
+}
+
+function insertImage(container, range, image) {
+  const img = document.createElement("img");
+  img.src = image.src;
+  if (image.srcset) {
+    img.srcset = image.srcset;
+    img.sizes = "(max-width: 700px) 100vw, 640px";
+  }
+  img.alt = image.alt || "";
+  img.loading = "lazy";
+  if (!range) {
+    container.appendChild(img);
+    return;
+  }
+  range.deleteContents();
+  range.insertNode(img);
+  range.setStartAfter(img);
+  range.collapse(true);
+  const selection = window.getSelection();
+  selection.removeAllRanges();
+  selection.addRange(range);
+}
+
+let imagePicker = null;
+
+function openImagePicker(onPick) {
+  if (!imagePicker) {
+    imagePicker = createImagePicker();
+  }
+  imagePicker.onPick = onPick;
+  imagePicker.load();
+  imagePicker.dialog.showModal();
+}
+
+function createImagePicker() {
+  const dialog = document.createElement("dialog");
+  dialog.className = "mobile-menu media-picker";
+  dialog.innerHTML = `
+    <div class="mobile-menu-header">
+      <strong>Insert image</strong>
+      <button class="btn ghost" type="button" data-picker-close>Close</button>
+    </div>
+    <label class="btn light media-picker-upload">
+      Upload new
+      <input type="file" accept="image/jpeg,image/png,image/webp,image/gif" hidden />
+    </label>
+    <p class="muted" data-picker-status></p>
+    <div class="media-picker-grid" data-picker-grid></div>
+  `;
+  document.body.appendChild(dialog);
+
+  const grid = dialog.querySelector("[data-picker-grid]");
+  const status = dialog.querySelector("[data-picker-status]");
+  const input = dialog.querySelector("input[type=file]");
+  const picker = { dialog, onPick: null };
+
+  const pick = (image) => {
+    dialog.close();
+    if (picker.onPick) {
+      picker.onPick(image);
+    }
+  };
+
+  const render = (images) => {
+    grid.innerHTML = "";
+    status.textContent = images.length ? "" : "No images yet. Upload one to get started.";
+    images.forEach((image) => {
+      const button = document.createElement("button");
+      button.type = "button";
+      button.title = image.alt || image.name;
+      const thumb = document.createElement("img");
+      thumb.src = image.thumb;
+      thumb.alt = image.alt || "";
+      thumb.loading = "lazy";
+      button.appendChild(thumb);
+      button.addEventListener("click", () => pick(image));
+      grid.appendChild(button);
+    });
+  };
+
+  picker.load = async () => {
+    status.textContent = "Loading...";
+    try {
+      const response = await fetch("/admin/media.json", { headers: { Accept: "application/json" } });
+      if (!response.ok) throw new Error("Load failed");
+      render(await response.json());
+    } catch {
+      status.textContent = "Could not load the media library.";
+    }
+  };
+
+  input.addEventListener("change", async () => {
+    const file = input.files && input.files[0];
+    if (!file) return;
+    const alt = prompt("Describe the image for screen readers", "") || "";
+    const data = new FormData();
+    data.append("file", file);
+    data.append("alt_text", alt);
+    status.textContent = "Uploading...";
+    try {
+      const response = await fetch("/admin/media", {
+        method: "POST",
+        headers: { Accept: "application/json" },
+        body: data,
+      });
+      const result = await response.json();
+      if (!response.ok) {
+        status.textContent = result.error || "Upload failed.";
+        return;
+      }
+      pick(result);
+    } catch {
+      status.textContent = "Upload failed. Try again.";
+    } finally {
+      input.value = "";
+    }
+  });
+
+  dialog.querySelector("[data-picker-close]").addEventListener("click", () => dialog.close());
+  return picker;
+}
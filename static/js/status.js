@@ -24,7 +24,7 @@ document.addEventListener("DOMContentLoaded", () => {
       }
       const value = payload[key];
       if (key === "barber_name" && (!value || value === "")) {
-        el.textContent = "Unassigned";
+        el.textContent = t("Unassigned");
         return;
       }
       if (value === null || value === undefined || value === "") return;
@@ -39,11 +39,11 @@ document.addEventListener("DOMContentLoaded", () => {
   });
 });
 
-const STATUS_CLASSES = ["pending", "accepted", "declined", "completed"];
+const STATUS_CLASSES = ["pending", "accepted", "declined", "completed", "cancelled", "no_show"];
 
 function updateStatus(el, status) {
   if (!status) return;
-  el.textContent = status;
+  el.textContent = t(status);
   STATUS_CLASSES.forEach((cls) => el.classList.remove(cls));
   el.classList.add(status);
 }